@@ -0,0 +1,18 @@
+-- Item and coin trades proposed between friends
+CREATE TYPE trade_status AS ENUM('pending', 'accepted', 'rejected', 'cancelled');
+
+CREATE TABLE IF NOT EXISTS
+	trades (
+		id SERIAL PRIMARY KEY,
+		sender_id INTEGER NOT NULL REFERENCES characters (user_id) ON DELETE CASCADE,
+		recipient_id INTEGER NOT NULL REFERENCES characters (user_id) ON DELETE CASCADE,
+		-- what the sender gives away
+		offered_items INTEGER[] NOT NULL DEFAULT '{}',
+		offered_coins INTEGER NOT NULL DEFAULT 0,
+		-- what the sender receives in return
+		requested_items INTEGER[] NOT NULL DEFAULT '{}',
+		requested_coins INTEGER NOT NULL DEFAULT 0,
+		status trade_status NOT NULL DEFAULT 'pending',
+		created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		resolved TIMESTAMP
+	);
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS "purchases";
+
+ALTER TABLE users
+DROP COLUMN IF EXISTS is_admin;
@@ -0,0 +1,16 @@
+-- Admins can manage other users' data, such as refunding purchases
+ALTER TABLE users
+ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Receipts of every item bought from the shop
+CREATE TABLE IF NOT EXISTS
+	purchases (
+		id SERIAL PRIMARY KEY,
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		item_id INTEGER NOT NULL REFERENCES items (id),
+		price INTEGER NOT NULL,
+		discount INTEGER NOT NULL DEFAULT 0,
+		price_paid INTEGER NOT NULL,
+		purchased_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		refunded_at TIMESTAMP
+	);
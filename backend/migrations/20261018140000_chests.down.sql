@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS "chest_pity";
+
+DROP TABLE IF EXISTS "user_chests";
+
+DROP TABLE IF EXISTS "chest_drops";
+
+DROP TABLE IF EXISTS "chests";
+
+DROP TYPE IF EXISTS chest_source;
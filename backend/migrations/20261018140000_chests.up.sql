@@ -0,0 +1,68 @@
+-- Reward chests that roll a random item from a drop table keyed by rarity
+CREATE TYPE chest_source AS ENUM('quest', 'level_up');
+
+CREATE TABLE IF NOT EXISTS
+	chests (
+		id SERIAL PRIMARY KEY,
+		name TEXT UNIQUE NOT NULL,
+		-- number of openings in a row without a rare or better drop before one is guaranteed
+		pity_threshold INTEGER NOT NULL
+	);
+
+-- Relative weight of each rarity, published to players as odds
+CREATE TABLE IF NOT EXISTS
+	chest_drops (
+		chest_id INTEGER NOT NULL REFERENCES chests (id),
+		rarity item_rarity NOT NULL,
+		weight INTEGER NOT NULL CHECK (weight >= 0),
+		PRIMARY KEY (chest_id, rarity)
+	);
+
+-- Chests granted to users, the reward is filled in once opened
+CREATE TABLE IF NOT EXISTS
+	user_chests (
+		id SERIAL PRIMARY KEY,
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		chest_id INTEGER NOT NULL REFERENCES chests (id),
+		source chest_source NOT NULL,
+		granted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		opened_at TIMESTAMP,
+		rarity item_rarity,
+		item_id INTEGER REFERENCES items (id),
+		-- coins given instead of an item the user already owned
+		coins INTEGER
+	);
+
+-- Openings in a row without a rare or better drop, per user and chest
+CREATE TABLE IF NOT EXISTS
+	chest_pity (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		chest_id INTEGER NOT NULL REFERENCES chests (id),
+		misses INTEGER NOT NULL DEFAULT 0,
+		PRIMARY KEY (user_id, chest_id)
+	);
+
+DO $$
+	DECLARE
+  		chests_json jsonb;
+	BEGIN
+		SELECT 
+			pg_read_file('/docker-entrypoint-initdb.d/chests.json')::jsonb INTO chests_json;
+		INSERT INTO
+			chests (name, pity_threshold)
+		SELECT
+			data ->> 'name',
+			(data ->> 'pity_threshold')::INTEGER
+		FROM
+			jsonb_array_elements(chests_json) AS data;
+		INSERT INTO
+			chest_drops (chest_id, rarity, weight)
+		SELECT
+			c.id,
+			odds.key::item_rarity,
+			odds.value::INTEGER
+		FROM
+			jsonb_array_elements(chests_json) AS data
+			JOIN chests c ON c.name = data ->> 'name',
+			jsonb_each_text(data -> 'drops') AS odds;
+END $$;
@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS "notifications";
+
+DROP TYPE IF EXISTS notification_kind;
+
+DROP TABLE IF EXISTS "wishlist";
+
+ALTER TABLE shop
+DROP COLUMN IF EXISTS discount;
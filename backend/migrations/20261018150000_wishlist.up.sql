@@ -0,0 +1,25 @@
+-- Percentage taken off the price of an item while it is in the shop rotation
+ALTER TABLE shop
+ADD COLUMN discount INTEGER NOT NULL DEFAULT 0 CHECK (discount BETWEEN 0 AND 100);
+
+-- Items from the full catalog that users want to be told about
+CREATE TABLE IF NOT EXISTS
+	wishlist (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		item_id INTEGER NOT NULL REFERENCES items (id),
+		added_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		PRIMARY KEY (user_id, item_id)
+	);
+
+CREATE TYPE notification_kind AS ENUM('wishlist_in_shop', 'wishlist_on_sale');
+
+CREATE TABLE IF NOT EXISTS
+	notifications (
+		id SERIAL PRIMARY KEY,
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		kind notification_kind NOT NULL,
+		message TEXT NOT NULL,
+		item_id INTEGER REFERENCES items (id),
+		created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		read BOOLEAN NOT NULL DEFAULT FALSE
+	);
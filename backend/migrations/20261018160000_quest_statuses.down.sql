@@ -0,0 +1 @@
+-- Postgres cannot drop enum values, statuses are mapped back in the quest lifecycle down migration
@@ -0,0 +1,6 @@
+-- New values must be committed before they can be used, so they get their own migration
+ALTER TYPE quest_status ADD VALUE IF NOT EXISTS 'claimed';
+
+ALTER TYPE quest_status ADD VALUE IF NOT EXISTS 'abandoned';
+
+ALTER TYPE quest_status ADD VALUE IF NOT EXISTS 'expired';
@@ -0,0 +1,16 @@
+DROP TABLE IF EXISTS "quest_rerolls";
+
+UPDATE quests
+SET
+	status = 'complete'
+WHERE
+	status = 'claimed';
+
+DELETE FROM quests
+WHERE
+	status IN ('abandoned', 'expired');
+
+ALTER TABLE quests
+DROP COLUMN IF EXISTS created_at,
+DROP COLUMN IF EXISTS expires_at,
+DROP COLUMN IF EXISTS claimed_at;
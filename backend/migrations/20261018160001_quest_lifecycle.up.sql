@@ -0,0 +1,32 @@
+ALTER TABLE quests
+ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+ADD COLUMN expires_at TIMESTAMP,
+ADD COLUMN claimed_at TIMESTAMP;
+
+UPDATE quests
+SET
+	expires_at = created_at + CASE difficulty
+		WHEN 'easy' THEN INTERVAL '1 day'
+		WHEN 'medium' THEN INTERVAL '3 days'
+		ELSE INTERVAL '7 days'
+	END;
+
+ALTER TABLE quests
+ALTER COLUMN expires_at SET NOT NULL;
+
+-- Rewards of quests completed so far have already been granted
+UPDATE quests
+SET
+	status = 'claimed',
+	claimed_at = CURRENT_TIMESTAMP
+WHERE
+	status = 'complete';
+
+-- Number of quests rerolled by a user on a given day
+CREATE TABLE IF NOT EXISTS
+	quest_rerolls (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		day DATE NOT NULL,
+		count INTEGER NOT NULL DEFAULT 0,
+		PRIMARY KEY (user_id, day)
+	);
@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS "quest_boards";
+
+ALTER TABLE quests
+DROP COLUMN IF EXISTS board;
+
+DROP TYPE IF EXISTS "quest_board";
+
+ALTER TABLE settings
+DROP COLUMN IF EXISTS timezone;
@@ -0,0 +1,18 @@
+ALTER TABLE settings
+ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+CREATE TYPE quest_board AS ENUM('daily', 'weekly');
+
+-- Quests created by hand through POST /quests have no board
+ALTER TABLE quests
+ADD COLUMN board QUEST_BOARD;
+
+-- Boards that have already been issued to a user, keyed by the local date the period starts on
+CREATE TABLE IF NOT EXISTS
+	quest_boards (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		board QUEST_BOARD NOT NULL,
+		starts_on DATE NOT NULL,
+		created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		PRIMARY KEY (user_id, board, starts_on)
+	);
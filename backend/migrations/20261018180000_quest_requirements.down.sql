@@ -0,0 +1,41 @@
+ALTER TABLE quests
+ADD COLUMN workout_duration INTEGER,
+ADD COLUMN exercise_category EXERCISE_CATEGORY,
+ADD COLUMN exercise_muscle EXERCISE_MUSCLE;
+
+-- Kinds that did not exist before are dropped
+UPDATE quests
+SET
+	workout_duration = (
+		SELECT
+			(requirement ->> 'minutes')::INTEGER
+		FROM
+			jsonb_array_elements(requirements) requirement
+		WHERE
+			requirement ->> 'kind' = 'workout_duration'
+		LIMIT
+			1
+	),
+	exercise_category = (
+		SELECT
+			(requirement ->> 'category')::EXERCISE_CATEGORY
+		FROM
+			jsonb_array_elements(requirements) requirement
+		WHERE
+			requirement ->> 'kind' = 'exercise_category'
+		LIMIT
+			1
+	),
+	exercise_muscle = (
+		SELECT
+			(requirement ->> 'muscle')::EXERCISE_MUSCLE
+		FROM
+			jsonb_array_elements(requirements) requirement
+		WHERE
+			requirement ->> 'kind' = 'exercise_muscle'
+		LIMIT
+			1
+	);
+
+ALTER TABLE quests
+DROP COLUMN IF EXISTS requirements;
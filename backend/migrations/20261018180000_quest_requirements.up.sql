@@ -0,0 +1,32 @@
+-- Requirements are stored as tagged JSON so new kinds don't need schema changes
+ALTER TABLE quests
+ADD COLUMN requirements JSONB NOT NULL DEFAULT '[]';
+
+UPDATE quests
+SET
+	requirements = (
+		SELECT
+			COALESCE(jsonb_agg(requirement), '[]')
+		FROM
+			(
+				SELECT
+					jsonb_build_object('kind', 'workout_duration', 'minutes', workout_duration) AS requirement
+				WHERE
+					workout_duration IS NOT NULL
+				UNION ALL
+				SELECT
+					jsonb_build_object('kind', 'exercise_category', 'category', exercise_category)
+				WHERE
+					exercise_category IS NOT NULL
+				UNION ALL
+				SELECT
+					jsonb_build_object('kind', 'exercise_muscle', 'muscle', exercise_muscle)
+				WHERE
+					exercise_muscle IS NOT NULL
+			) requirements
+	);
+
+ALTER TABLE quests
+DROP COLUMN workout_duration,
+DROP COLUMN exercise_category,
+DROP COLUMN exercise_muscle;
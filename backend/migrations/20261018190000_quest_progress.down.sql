@@ -0,0 +1,3 @@
+ALTER TABLE quests
+DROP COLUMN IF EXISTS goal,
+DROP COLUMN IF EXISTS progress;
@@ -0,0 +1,4 @@
+-- Progress-based quests accumulate a quantity towards a goal instead of counting workouts
+ALTER TABLE quests
+ADD COLUMN goal JSONB,
+ADD COLUMN progress REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,21 @@
+DELETE FROM quests
+WHERE
+	campaign_step_id IS NOT NULL;
+
+ALTER TABLE quests
+DROP COLUMN IF EXISTS campaign_step_id;
+
+UPDATE quests
+SET
+	expires_at = created_at
+WHERE
+	expires_at IS NULL;
+
+ALTER TABLE quests
+ALTER COLUMN expires_at SET NOT NULL;
+
+DROP TABLE IF EXISTS "campaign_progress";
+
+DROP TABLE IF EXISTS "campaign_steps";
+
+DROP TABLE IF EXISTS "campaigns";
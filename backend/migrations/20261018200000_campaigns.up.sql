@@ -0,0 +1,101 @@
+-- Authored quest chains, loaded from campaigns.json
+CREATE TABLE IF NOT EXISTS
+	campaigns (
+		id SERIAL PRIMARY KEY,
+		key TEXT UNIQUE NOT NULL,
+		name TEXT NOT NULL,
+		description TEXT NOT NULL,
+		-- given once every step of the campaign is completed
+		reward_item_id INTEGER NOT NULL REFERENCES items (id)
+	);
+
+CREATE TABLE IF NOT EXISTS
+	campaign_steps (
+		id SERIAL PRIMARY KEY,
+		campaign_id INTEGER NOT NULL REFERENCES campaigns (id),
+		key TEXT NOT NULL,
+		position INTEGER NOT NULL,
+		name TEXT NOT NULL,
+		story TEXT NOT NULL,
+		-- steps with a class are only part of the campaign for characters of that class
+		class TEXT,
+		prerequisites TEXT[] NOT NULL,
+		difficulty quest_difficulty NOT NULL,
+		number_of_workouts_needed INTEGER NOT NULL,
+		requirements JSONB NOT NULL,
+		goal JSONB,
+		UNIQUE (campaign_id, key)
+	);
+
+CREATE TABLE IF NOT EXISTS
+	campaign_progress (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		campaign_id INTEGER NOT NULL REFERENCES campaigns (id),
+		started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		completed_at TIMESTAMP,
+		PRIMARY KEY (user_id, campaign_id)
+	);
+
+-- Campaign steps are issued as quests that never expire
+ALTER TABLE quests
+ADD COLUMN campaign_step_id INTEGER REFERENCES campaign_steps (id),
+ALTER COLUMN expires_at DROP NOT NULL;
+
+DO $$
+	DECLARE
+  		campaigns_json jsonb;
+	BEGIN
+		SELECT 
+			pg_read_file('/docker-entrypoint-initdb.d/campaigns.json')::jsonb INTO campaigns_json;
+		INSERT INTO
+			campaigns (key, name, description, reward_item_id)
+		SELECT
+			data ->> 'key',
+			data ->> 'name',
+			data ->> 'description',
+			(
+				SELECT
+					id
+				FROM
+					items
+				WHERE
+					name = data ->> 'reward_item'
+				LIMIT
+					1
+			)
+		FROM
+			jsonb_array_elements(campaigns_json) AS data;
+		INSERT INTO
+			campaign_steps (
+				campaign_id,
+				key,
+				position,
+				name,
+				story,
+				class,
+				prerequisites,
+				difficulty,
+				number_of_workouts_needed,
+				requirements,
+				goal
+			)
+		SELECT
+			c.id,
+			step.value ->> 'key',
+			step.ordinality,
+			step.value ->> 'name',
+			step.value ->> 'story',
+			step.value ->> 'class',
+			ARRAY(
+				SELECT
+					jsonb_array_elements_text(step.value -> 'prerequisites')
+			),
+			(step.value ->> 'difficulty')::quest_difficulty,
+			(step.value ->> 'number_of_workouts_needed')::INTEGER,
+			step.value -> 'requirements',
+			NULLIF(step.value -> 'goal', 'null'::jsonb)
+		FROM
+			jsonb_array_elements(campaigns_json) AS data
+			JOIN campaigns c ON c.key = data ->> 'key',
+			jsonb_array_elements(data -> 'steps') WITH ORDINALITY AS step;
+END $$;
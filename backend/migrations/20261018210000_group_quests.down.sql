@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS "group_quest_members";
+
+DROP TABLE IF EXISTS "group_quests";
+
+DROP TYPE IF EXISTS "group_member_status";
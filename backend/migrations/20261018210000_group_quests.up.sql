@@ -0,0 +1,32 @@
+-- Quests shared between friends, every member's workouts count towards one goal
+CREATE TYPE group_member_status AS ENUM('invited', 'joined', 'declined', 'left');
+
+CREATE TABLE IF NOT EXISTS
+	group_quests (
+		id SERIAL PRIMARY KEY,
+		owner_id INTEGER NOT NULL REFERENCES users (id),
+		name TEXT NOT NULL,
+		difficulty quest_difficulty NOT NULL,
+		status quest_status NOT NULL DEFAULT 'incomplete',
+		number_of_workouts_needed INTEGER NOT NULL,
+		number_of_workouts_completed INTEGER NOT NULL DEFAULT 0,
+		requirements JSONB NOT NULL DEFAULT '[]',
+		goal JSONB,
+		progress REAL NOT NULL DEFAULT 0,
+		created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		expires_at TIMESTAMP NOT NULL,
+		completed_at TIMESTAMP
+	);
+
+-- Contribution of each member towards the shared goal
+CREATE TABLE IF NOT EXISTS
+	group_quest_members (
+		group_quest_id INTEGER NOT NULL REFERENCES group_quests (id),
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		status group_member_status NOT NULL,
+		number_of_workouts_completed INTEGER NOT NULL DEFAULT 0,
+		progress REAL NOT NULL DEFAULT 0,
+		invited_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		joined_at TIMESTAMP,
+		PRIMARY KEY (group_quest_id, user_id)
+	);
@@ -0,0 +1,5 @@
+ALTER TABLE quests
+DROP COLUMN IF EXISTS seed;
+
+ALTER TABLE group_quests
+DROP COLUMN IF EXISTS seed;
@@ -0,0 +1,6 @@
+-- Seed the quest was generated from, the same seed reproduces the same quest
+ALTER TABLE quests
+ADD COLUMN seed BIGINT;
+
+ALTER TABLE group_quests
+ADD COLUMN seed BIGINT;
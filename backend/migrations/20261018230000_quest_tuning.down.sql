@@ -0,0 +1,3 @@
+ALTER TABLE settings
+DROP COLUMN IF EXISTS quest_stretch,
+DROP COLUMN IF EXISTS target_neglected_muscles;
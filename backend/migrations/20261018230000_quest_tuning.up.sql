@@ -0,0 +1,4 @@
+-- Adaptive quests ask for this much more than the user's recent training per difficulty step
+ALTER TABLE settings
+ADD COLUMN quest_stretch REAL NOT NULL DEFAULT 0.1 CHECK (quest_stretch BETWEEN 0 AND 1),
+ADD COLUMN target_neglected_muscles BOOLEAN NOT NULL DEFAULT FALSE;
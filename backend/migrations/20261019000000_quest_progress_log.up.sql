@@ -0,0 +1,24 @@
+-- Workouts that advanced each quest, progress made before this table existed is not linked to any workout
+CREATE TABLE IF NOT EXISTS
+	quest_progress (
+		quest_id INTEGER NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+		history_id INTEGER NOT NULL REFERENCES history (id) ON DELETE CASCADE,
+		delta REAL NOT NULL DEFAULT 0, -- progress added towards the goal, every workout counts once
+		created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		PRIMARY KEY (quest_id, history_id)
+	);
+
+CREATE INDEX IF NOT EXISTS quest_progress_history_id_idx ON quest_progress (history_id);
+
+-- Same for the contributions of group quest members
+CREATE TABLE IF NOT EXISTS
+	group_quest_progress (
+		group_quest_id INTEGER NOT NULL REFERENCES group_quests (id) ON DELETE CASCADE,
+		history_id INTEGER NOT NULL REFERENCES history (id) ON DELETE CASCADE,
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		delta REAL NOT NULL DEFAULT 0,
+		created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		PRIMARY KEY (group_quest_id, history_id)
+	);
+
+CREATE INDEX IF NOT EXISTS group_quest_progress_history_id_idx ON group_quest_progress (history_id);
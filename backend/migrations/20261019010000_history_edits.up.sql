@@ -0,0 +1,6 @@
+-- Highest level a character reached, level-up chests are only granted once per level even after de-levelling
+ALTER TABLE characters
+ADD COLUMN highest_level INTEGER NOT NULL DEFAULT 1;
+
+UPDATE characters
+SET highest_level = level;
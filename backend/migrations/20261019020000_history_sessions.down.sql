@@ -0,0 +1,11 @@
+DROP INDEX IF EXISTS history_user_id_started_at_idx;
+
+ALTER TABLE history
+ALTER COLUMN date DROP EXPRESSION,
+ALTER COLUMN duration DROP EXPRESSION;
+
+ALTER TABLE history
+DROP CONSTRAINT IF EXISTS history_session_check,
+DROP COLUMN IF EXISTS started_at,
+DROP COLUMN IF EXISTS ended_at,
+DROP COLUMN IF EXISTS timezone;
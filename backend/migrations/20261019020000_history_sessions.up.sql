@@ -0,0 +1,37 @@
+-- Workouts are logged with when they started and ended, in the timezone the user was in
+ALTER TABLE history
+ADD COLUMN started_at TIMESTAMPTZ,
+ADD COLUMN ended_at TIMESTAMPTZ,
+ADD COLUMN timezone TEXT;
+
+-- Workouts logged before only have a day, they are placed at noon so the day is the same in every timezone offset
+UPDATE history h
+SET timezone = COALESCE(
+		(
+			SELECT timezone
+			FROM settings
+			WHERE user_id = h.user_id
+		),
+		'UTC'
+	);
+
+UPDATE history
+SET started_at = (date + TIME '12:00') AT TIME ZONE timezone,
+	ended_at = ((date + TIME '12:00') AT TIME ZONE timezone) + make_interval(mins => duration);
+
+ALTER TABLE history
+ALTER COLUMN started_at SET NOT NULL,
+ALTER COLUMN ended_at SET NOT NULL,
+ALTER COLUMN timezone SET NOT NULL,
+ADD CONSTRAINT history_session_check CHECK (ended_at >= started_at);
+
+-- The day and duration are derived from the timestamps
+ALTER TABLE history
+DROP COLUMN date,
+DROP COLUMN duration;
+
+ALTER TABLE history
+ADD COLUMN date DATE NOT NULL GENERATED ALWAYS AS ((started_at AT TIME ZONE timezone)::DATE) STORED,
+ADD COLUMN duration INTEGER NOT NULL GENERATED ALWAYS AS ((EXTRACT(EPOCH FROM ended_at - started_at) / 60)::INTEGER) STORED;
+
+CREATE INDEX IF NOT EXISTS history_user_id_started_at_idx ON history (user_id, started_at DESC, id DESC);
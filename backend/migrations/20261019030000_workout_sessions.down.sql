@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS "workout_sessions";
+
+DROP TYPE IF EXISTS "workout_session_status";
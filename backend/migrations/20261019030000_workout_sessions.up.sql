@@ -0,0 +1,24 @@
+CREATE TYPE workout_session_status AS ENUM('active', 'paused', 'finished', 'abandoned');
+
+-- Workouts in progress, sets are logged as they are done and the session becomes a history entry once finished
+CREATE TABLE IF NOT EXISTS
+	workout_sessions (
+		id SERIAL PRIMARY KEY,
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		routine_id INTEGER REFERENCES routines (id) ON DELETE SET NULL,
+		name TEXT NOT NULL,
+		planned JSONB NOT NULL DEFAULT '[]', -- exercises of the routine the session was started from
+		exercises JSONB NOT NULL DEFAULT '[]',
+		status WORKOUT_SESSION_STATUS NOT NULL DEFAULT 'active',
+		started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		paused_at TIMESTAMPTZ,
+		paused_seconds INTEGER NOT NULL DEFAULT 0,
+		last_activity_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		ended_at TIMESTAMPTZ,
+		history_id INTEGER REFERENCES history (id) ON DELETE SET NULL
+	);
+
+-- A user has at most one session in progress
+CREATE UNIQUE INDEX IF NOT EXISTS workout_sessions_open_idx ON workout_sessions (user_id)
+WHERE
+	status IN ('active', 'paused');
@@ -0,0 +1,14 @@
+-- Responses of requests sent with an Idempotency-Key header, replayed when the request is retried
+CREATE TABLE IF NOT EXISTS
+	idempotency_keys (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		key TEXT NOT NULL,
+		request_hash BYTEA NOT NULL, -- method, path and body the key was first used with
+		status_code SMALLINT, -- NULL while the request is handled
+		content_type TEXT,
+		response BYTEA,
+		created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+		PRIMARY KEY (user_id, key)
+	);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_created_at_idx ON idempotency_keys (created_at);
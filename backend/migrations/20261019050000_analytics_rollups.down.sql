@@ -0,0 +1,5 @@
+DROP MATERIALIZED VIEW IF EXISTS "history_exercise_rollups";
+
+DROP MATERIALIZED VIEW IF EXISTS "history_daily_rollups";
+
+DROP FUNCTION IF EXISTS exercise_sets (JSONB);
@@ -0,0 +1,3 @@
+-- Entered by the user in kg, lifts are compared to strength standards relative to it
+ALTER TABLE settings
+ADD COLUMN bodyweight REAL CHECK (bodyweight > 0);
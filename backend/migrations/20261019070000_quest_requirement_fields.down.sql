@@ -0,0 +1 @@
+DROP FUNCTION IF EXISTS quest_requirement;
@@ -0,0 +1,11 @@
+-- First requirement of a kind, quests still expose the duration, category and muscle they had as columns
+CREATE FUNCTION quest_requirement (requirements JSONB, kind TEXT, field TEXT) RETURNS TEXT AS $$
+	SELECT
+		requirement ->> field
+	FROM
+		jsonb_array_elements(requirements) requirement
+	WHERE
+		requirement ->> 'kind' = kind
+	LIMIT
+		1
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,2 @@
+ALTER TABLE quests
+DROP COLUMN IF EXISTS user_chest_id;
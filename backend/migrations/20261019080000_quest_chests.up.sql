@@ -0,0 +1,3 @@
+-- Chest granted when the quest was claimed, taken back with the quest's rewards while unopened
+ALTER TABLE quests
+ADD COLUMN user_chest_id INTEGER REFERENCES user_chests (id) ON DELETE SET NULL;
@@ -0,0 +1,27 @@
+-- Exercises are written with their sets in set_details next to the aggregated number of sets
+CREATE OR REPLACE FUNCTION exercise_sets (exercise JSONB) RETURNS SETOF JSONB AS $$
+	SELECT value
+	FROM jsonb_array_elements(
+			CASE
+				WHEN jsonb_typeof(exercise -> 'set_details') = 'array' THEN exercise -> 'set_details'
+				WHEN jsonb_typeof(exercise -> 'sets') = 'array' THEN exercise -> 'sets'
+				ELSE '[]'
+			END
+		)
+	UNION ALL
+	SELECT jsonb_build_object(
+			'reps', exercise -> 'reps',
+			'weight', exercise -> 'weight',
+			'distance', CASE WHEN n = 1 THEN exercise -> 'distance' END
+		)
+	FROM generate_series(
+			1,
+			CASE
+				WHEN jsonb_typeof(exercise -> 'set_details') = 'array' THEN 0
+				WHEN jsonb_typeof(exercise -> 'sets') <> 'number' THEN 0
+				WHEN (exercise ->> 'sets')::INTEGER <= 0
+				AND COALESCE((exercise ->> 'distance')::REAL, 0) > 0 THEN 1
+				ELSE (exercise ->> 'sets')::INTEGER
+			END
+		) AS n
+$$ LANGUAGE SQL IMMUTABLE;
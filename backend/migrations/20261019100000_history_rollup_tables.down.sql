@@ -0,0 +1,92 @@
+DROP TABLE IF EXISTS history_exercise_rollups;
+
+DROP TABLE IF EXISTS history_daily_rollups;
+
+-- Exercises are written with their sets in set_details next to the aggregated number of sets
+CREATE OR REPLACE FUNCTION exercise_sets (exercise JSONB) RETURNS SETOF JSONB AS $$
+	SELECT value
+	FROM jsonb_array_elements(
+			CASE
+				WHEN jsonb_typeof(exercise -> 'set_details') = 'array' THEN exercise -> 'set_details'
+				WHEN jsonb_typeof(exercise -> 'sets') = 'array' THEN exercise -> 'sets'
+				ELSE '[]'
+			END
+		)
+	UNION ALL
+	SELECT jsonb_build_object(
+			'reps', exercise -> 'reps',
+			'weight', exercise -> 'weight',
+			'distance', CASE WHEN n = 1 THEN exercise -> 'distance' END
+		)
+	FROM generate_series(
+			1,
+			CASE
+				WHEN jsonb_typeof(exercise -> 'set_details') = 'array' THEN 0
+				WHEN jsonb_typeof(exercise -> 'sets') <> 'number' THEN 0
+				WHEN (exercise ->> 'sets')::INTEGER <= 0
+				AND COALESCE((exercise ->> 'distance')::REAL, 0) > 0 THEN 1
+				ELSE (exercise ->> 'sets')::INTEGER
+			END
+		) AS n
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Totals of the workouts of each day, read by the analytics instead of the history
+CREATE MATERIALIZED VIEW IF NOT EXISTS
+	history_daily_rollups AS
+SELECT h.user_id,
+	h.date,
+	COUNT(*)::INTEGER AS sessions,
+	SUM(h.duration)::INTEGER AS minutes,
+	COALESCE(SUM(totals.volume), 0)::REAL AS volume, -- reps × weight of the working sets
+	COALESCE(SUM(totals.distance), 0)::REAL AS distance
+FROM history h
+	CROSS JOIN LATERAL (
+		SELECT SUM(
+				COALESCE((s.value ->> 'reps')::REAL, 0) * COALESCE((s.value ->> 'weight')::REAL, 0)
+			) FILTER (
+				WHERE COALESCE(s.value ->> 'type', 'working') <> 'warmup'
+			) AS volume,
+			SUM(COALESCE((s.value ->> 'distance')::REAL, 0)) AS distance
+		FROM jsonb_array_elements(h.exercises) AS logged (exercise)
+			CROSS JOIN LATERAL exercise_sets(logged.exercise) AS s (value)
+	) AS totals
+GROUP BY h.user_id,
+	h.date;
+
+-- Totals of every exercise of each day, muscles and categories are looked up in the catalog
+CREATE MATERIALIZED VIEW IF NOT EXISTS
+	history_exercise_rollups AS
+SELECT h.user_id,
+	h.date,
+	logged.exercise ->> 'id' AS exercise_id,
+	COALESCE(
+		SUM(
+			COALESCE((s.value ->> 'reps')::REAL, 0) * COALESCE((s.value ->> 'weight')::REAL, 0)
+		) FILTER (
+			WHERE COALESCE(s.value ->> 'type', 'working') <> 'warmup'
+		),
+		0
+	)::REAL AS volume,
+	COUNT(s.value) FILTER (
+		WHERE COALESCE(s.value ->> 'type', 'working') <> 'warmup'
+	)::INTEGER AS sets,
+	COALESCE(
+		SUM(COALESCE((s.value ->> 'reps')::INTEGER, 0)) FILTER (
+			WHERE COALESCE(s.value ->> 'type', 'working') <> 'warmup'
+		),
+		0
+	)::INTEGER AS reps,
+	COALESCE(SUM(COALESCE((s.value ->> 'distance')::REAL, 0)), 0)::REAL AS distance,
+	COALESCE(SUM(COALESCE((s.value ->> 'duration')::INTEGER, 0)), 0)::INTEGER AS duration -- seconds
+FROM history h
+	CROSS JOIN LATERAL jsonb_array_elements(h.exercises) AS logged (exercise)
+	LEFT JOIN LATERAL exercise_sets(logged.exercise) AS s (value) ON TRUE
+WHERE logged.exercise ->> 'id' IS NOT NULL
+GROUP BY h.user_id,
+	h.date,
+	logged.exercise ->> 'id';
+
+-- Unique indexes let the rollups be refreshed without blocking reads
+CREATE UNIQUE INDEX IF NOT EXISTS history_daily_rollups_user_id_date_idx ON history_daily_rollups (user_id, date);
+
+CREATE UNIQUE INDEX IF NOT EXISTS history_exercise_rollups_user_id_date_exercise_id_idx ON history_exercise_rollups (user_id, date, exercise_id);
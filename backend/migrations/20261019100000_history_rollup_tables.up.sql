@@ -0,0 +1,34 @@
+-- The analytics rollups are tables kept up to date by every write to the history instead of views
+-- refreshed on a timer, the server rolls up the days logged before on startup
+DROP MATERIALIZED VIEW IF EXISTS history_exercise_rollups;
+
+DROP MATERIALIZED VIEW IF EXISTS history_daily_rollups;
+
+-- Sets are expanded by the server only
+DROP FUNCTION IF EXISTS exercise_sets (JSONB);
+
+-- Totals of the workouts of each day, read by the analytics instead of the history
+CREATE TABLE IF NOT EXISTS
+	history_daily_rollups (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		date DATE NOT NULL,
+		sessions INTEGER NOT NULL,
+		minutes INTEGER NOT NULL,
+		volume REAL NOT NULL, -- reps × weight of the working sets
+		distance REAL NOT NULL,
+		PRIMARY KEY (user_id, date)
+	);
+
+-- Totals of every exercise of each day, muscles and categories are looked up in the catalog
+CREATE TABLE IF NOT EXISTS
+	history_exercise_rollups (
+		user_id INTEGER NOT NULL REFERENCES users (id),
+		date DATE NOT NULL,
+		exercise_id TEXT NOT NULL,
+		volume REAL NOT NULL,
+		sets INTEGER NOT NULL, -- working sets
+		reps INTEGER NOT NULL,
+		distance REAL NOT NULL,
+		duration INTEGER NOT NULL, -- seconds
+		PRIMARY KEY (user_id, date, exercise_id)
+	);
@@ -0,0 +1,3 @@
+UPDATE settings
+SET bodyweight = bodyweight / 2.2046226
+WHERE bodyweight IS NOT NULL;
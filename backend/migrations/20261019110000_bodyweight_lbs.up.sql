@@ -0,0 +1,4 @@
+-- Bodyweight is stored in lbs like the lifts it is compared to
+UPDATE settings
+SET bodyweight = bodyweight * 2.2046226
+WHERE bodyweight IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Only the Forge of Strength step and the quests issued from it were labelled kg
+UPDATE quests q
+SET goal = jsonb_set(q.goal, '{unit}', '"kg"')
+FROM campaign_steps s
+WHERE q.campaign_step_id = s.id AND s.key = 'forge_of_strength';
+
+UPDATE campaign_steps
+SET goal = jsonb_set(goal, '{unit}', '"kg"')
+WHERE key = 'forge_of_strength';
@@ -0,0 +1,13 @@
+-- Volume is reps × weight of lifts logged in lbs, so volume goals were always counted in lbs. Goals
+-- labelled kg keep their target and only get the right unit.
+UPDATE campaign_steps
+SET goal = jsonb_set(goal, '{unit}', '"lbs"')
+WHERE goal ->> 'metric' = 'volume' AND goal ->> 'unit' <> 'lbs';
+
+UPDATE quests
+SET goal = jsonb_set(goal, '{unit}', '"lbs"')
+WHERE goal ->> 'metric' = 'volume' AND goal ->> 'unit' <> 'lbs';
+
+UPDATE group_quests
+SET goal = jsonb_set(goal, '{unit}', '"lbs"')
+WHERE goal ->> 'metric' = 'volume' AND goal ->> 'unit' <> 'lbs';
@@ -0,0 +1,3 @@
+ALTER TABLE group_quests
+DROP COLUMN IF EXISTS workouts_per_member,
+DROP COLUMN IF EXISTS target_per_member;
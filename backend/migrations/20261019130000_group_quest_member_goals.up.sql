@@ -0,0 +1,30 @@
+-- What each member of a group quest puts in, the shared goal is that times the members who joined
+ALTER TABLE group_quests
+ADD COLUMN workouts_per_member INTEGER,
+ADD COLUMN target_per_member REAL; -- NULL without a goal
+
+-- Shared goals were scaled by the members invited when the quest was created
+UPDATE group_quests q
+SET workouts_per_member = GREATEST(ROUND(q.number_of_workouts_needed::REAL / m.invited), 1),
+	target_per_member = (q.goal ->> 'target')::REAL / m.invited
+FROM (
+	SELECT group_quest_id, COUNT(*) AS invited
+	FROM group_quest_members
+	GROUP BY group_quest_id
+) m
+WHERE m.group_quest_id = q.id;
+
+ALTER TABLE group_quests
+ALTER COLUMN workouts_per_member SET NOT NULL;
+
+-- Quests in progress only ask for the members who joined, they complete with the next workout that counts
+UPDATE group_quests q
+SET number_of_workouts_needed = q.workouts_per_member * m.joined,
+	goal = jsonb_set(q.goal, '{target}', to_jsonb(q.target_per_member * m.joined))
+FROM (
+	SELECT group_quest_id, COUNT(*) AS joined
+	FROM group_quest_members
+	WHERE status = 'joined'
+	GROUP BY group_quest_id
+) m
+WHERE m.group_quest_id = q.id AND q.status = 'incomplete';
@@ -0,0 +1,196 @@
+use {
+    crate::{
+        endpoints::shop::item_price,
+        utils::{
+            jwt::AuthenticatedUser,
+            schemas::*,
+        },
+    },
+    actix_web::{
+        HttpResponse,
+        get,
+        web,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::PgPool,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct CatalogQuery {
+    pub category: Option<ItemCategory>,
+    pub rarity: Option<ItemRarity>,
+    pub owned: Option<bool>,
+    pub page: Option<i64>, // starts at 1
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct CatalogItem {
+    pub id: i32,
+    pub name: String,
+    pub category: ItemCategory,
+    pub rarity: ItemRarity,
+    pub path: String,
+    pub price: i32,
+    pub owned: bool,
+    pub buyable: bool, // in the current shop rotation and not owned yet
+}
+
+#[derive(Serialize)]
+pub struct CategoryCompletion {
+    pub category: ItemCategory,
+    pub owned: i64,
+    pub total: i64,
+    pub percentage: f64,
+}
+
+#[derive(Serialize)]
+pub struct CatalogResponse {
+    pub items: Vec<CatalogItem>,
+    pub page: i64,
+    pub page_size: i64,
+    pub total: i64, // number of items matching the filters
+    pub completion: Vec<CategoryCompletion>,
+}
+
+// Browse every item with the user's ownership, for the collection book
+#[get("/items")]
+pub async fn browse_items(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<CatalogQuery>,
+) -> HttpResponse {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let Some(offset) = (page - 1).checked_mul(page_size) else {
+        return HttpResponse::BadRequest().body("Page is out of range");
+    };
+
+    let inventory_result = sqlx::query!(
+        r#"
+        SELECT inventory as "inventory: Inventory"
+        FROM characters
+        WHERE user_id = $1
+        "#,
+        user.id
+    )
+    .fetch_one(pool.get_ref())
+    .await;
+
+    let owned_ids = match inventory_result {
+        Ok(query) => query.inventory.all_items(),
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch inventory"),
+    };
+
+    let items_result = sqlx::query!(
+        r#"
+        SELECT i.id, i.name, i.category as "category: ItemCategory",
+               i.rarity as "rarity: ItemRarity", i.path,
+               i.id = ANY($1) as "owned!",
+               EXISTS(SELECT 1 FROM shop s WHERE s.item_id = i.id) as "in_shop!"
+        FROM items i
+        WHERE ($2::item_category IS NULL OR i.category = $2)
+          AND ($3::item_rarity IS NULL OR i.rarity = $3)
+          AND ($4::BOOLEAN IS NULL OR (i.id = ANY($1)) = $4)
+        ORDER BY i.category, i.rarity, i.id
+        LIMIT $5 OFFSET $6
+        "#,
+        &owned_ids,
+        query.category as Option<ItemCategory>,
+        query.rarity as Option<ItemRarity>,
+        query.owned,
+        page_size,
+        offset
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let queries = match items_result {
+        Ok(queries) => queries,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch items"),
+    };
+
+    // Counted apart so pages past the end still report the total
+    let total_result = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "total!"
+        FROM items i
+        WHERE ($2::item_category IS NULL OR i.category = $2)
+          AND ($3::item_rarity IS NULL OR i.rarity = $3)
+          AND ($4::BOOLEAN IS NULL OR (i.id = ANY($1)) = $4)
+        "#,
+        &owned_ids,
+        query.category as Option<ItemCategory>,
+        query.rarity as Option<ItemRarity>,
+        query.owned,
+    )
+    .fetch_one(pool.get_ref())
+    .await;
+
+    let total = match total_result {
+        Ok(total) => total,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to count items"),
+    };
+
+    let items: Vec<CatalogItem> = queries
+        .into_iter()
+        .map(|q| CatalogItem {
+            id: q.id,
+            name: q.name,
+            category: q.category,
+            rarity: q.rarity,
+            path: q.path,
+            price: item_price(q.rarity),
+            owned: q.owned,
+            buyable: q.in_shop && !q.owned,
+        })
+        .collect();
+
+    let completion_result = sqlx::query!(
+        r#"
+        SELECT category as "category: ItemCategory",
+               COUNT(*) FILTER (WHERE id = ANY($1)) as "owned!",
+               COUNT(*) as "total!"
+        FROM items
+        GROUP BY category
+        ORDER BY category
+        "#,
+        &owned_ids
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let completion = match completion_result {
+        Ok(queries) => queries
+            .into_iter()
+            .map(|q| CategoryCompletion {
+                category: q.category,
+                owned: q.owned,
+                total: q.total,
+                percentage: if q.total > 0 {
+                    q.owned as f64 * 100.0 / q.total as f64
+                } else {
+                    0.0
+                },
+            })
+            .collect(),
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch completion"),
+    };
+
+    HttpResponse::Ok().json(CatalogResponse {
+        items,
+        page,
+        page_size,
+        total,
+        completion,
+    })
+}
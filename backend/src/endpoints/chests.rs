@@ -0,0 +1,335 @@
+use {
+    crate::{
+        endpoints::shop::sell_back_price,
+        utils::{
+            chests::{
+                pity_reached,
+                roll_rarity,
+            },
+            inventory::{
+                TransferItem,
+                lock_holdings,
+                save_holdings,
+            },
+            jwt::AuthenticatedUser,
+            schemas::*,
+        },
+    },
+    actix_web::{
+        HttpResponse,
+        get,
+        post,
+        web,
+    },
+    serde::Serialize,
+    sqlx::PgPool,
+};
+
+#[derive(Serialize)]
+pub struct RarityOdds {
+    pub rarity: ItemRarity,
+    pub weight: i32,
+    pub chance: f64, // percentage
+}
+
+#[derive(Serialize)]
+pub struct ChestOdds {
+    pub chest_id: i32,
+    pub name: String,
+    pub pity_threshold: i32,
+    pub misses: i32, // openings in a row without a rare or better drop for this user
+    pub odds: Vec<RarityOdds>,
+}
+
+#[derive(Serialize)]
+pub struct UserChest {
+    pub id: i32,
+    pub name: String,
+    pub source: ChestSource,
+    pub granted_at: chrono::NaiveDateTime,
+    pub opened_at: Option<chrono::NaiveDateTime>,
+    pub rarity: Option<ItemRarity>,
+    pub item_id: Option<i32>,
+    pub coins: Option<i32>,
+}
+
+// Published drop odds of every chest, along with the user's pity counters
+#[get("/chests/odds")]
+pub async fn read_chest_odds(user: AuthenticatedUser, pool: web::Data<PgPool>) -> HttpResponse {
+    let chests_result = sqlx::query!(
+        r#"
+        SELECT c.id, c.name, c.pity_threshold, COALESCE(p.misses, 0) as "misses!"
+        FROM chests c
+        LEFT JOIN chest_pity p ON p.chest_id = c.id AND p.user_id = $1
+        ORDER BY c.id
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let chests = match chests_result {
+        Ok(queries) => queries,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch chests"),
+    };
+
+    let drops_result = sqlx::query!(
+        r#"
+        SELECT chest_id, rarity as "rarity: ItemRarity", weight
+        FROM chest_drops
+        ORDER BY chest_id, weight DESC
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let drops = match drops_result {
+        Ok(queries) => queries,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch drop tables"),
+    };
+
+    let odds: Vec<ChestOdds> = chests
+        .into_iter()
+        .map(|chest| {
+            let chest_drops: Vec<_> = drops.iter().filter(|d| d.chest_id == chest.id).collect();
+            let total: i32 = chest_drops.iter().map(|d| d.weight).sum();
+
+            ChestOdds {
+                chest_id: chest.id,
+                name: chest.name,
+                pity_threshold: chest.pity_threshold,
+                misses: chest.misses,
+                odds: chest_drops
+                    .into_iter()
+                    .map(|d| RarityOdds {
+                        rarity: d.rarity,
+                        weight: d.weight,
+                        chance: if total > 0 {
+                            d.weight as f64 * 100.0 / total as f64
+                        } else {
+                            0.0
+                        },
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(odds)
+}
+
+// Chests owned by the user, unopened ones first
+#[get("/chests")]
+pub async fn read_chests(user: AuthenticatedUser, pool: web::Data<PgPool>) -> HttpResponse {
+    let chests_result = sqlx::query_as!(
+        UserChest,
+        r#"
+        SELECT uc.id, c.name, uc.source as "source: ChestSource", uc.granted_at, uc.opened_at,
+               uc.rarity as "rarity: ItemRarity", uc.item_id, uc.coins
+        FROM user_chests uc
+        JOIN chests c ON uc.chest_id = c.id
+        WHERE uc.user_id = $1
+        ORDER BY uc.opened_at IS NOT NULL, uc.granted_at DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match chests_result {
+        Ok(chests) => HttpResponse::Ok().json(chests),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch chests"),
+    }
+}
+
+// Open a chest, items that are already owned are converted into coins
+#[post("/chests/{id}/open")]
+pub async fn open_chest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    user_chest_id: web::Path<i32>,
+) -> HttpResponse {
+    let user_chest_id = user_chest_id.into_inner();
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to start transaction"),
+    };
+
+    // Lock the chest so it can only be opened once
+    let chest_result = sqlx::query!(
+        r#"
+        SELECT uc.chest_id, c.name, c.pity_threshold
+        FROM user_chests uc
+        JOIN chests c ON uc.chest_id = c.id
+        WHERE uc.id = $1 AND uc.user_id = $2 AND uc.opened_at IS NULL
+        FOR UPDATE OF uc
+        "#,
+        user_chest_id,
+        user.id
+    )
+    .fetch_optional(&mut *tx)
+    .await;
+
+    let chest = match chest_result {
+        Ok(Some(chest)) => chest,
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("Chest not found or already opened");
+        }
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch chest");
+        }
+    };
+
+    let drops_result = sqlx::query_as!(
+        ChestDrop,
+        r#"
+        SELECT rarity as "rarity: ItemRarity", weight
+        FROM chest_drops
+        WHERE chest_id = $1
+        "#,
+        chest.chest_id
+    )
+    .fetch_all(&mut *tx)
+    .await;
+
+    let drops = match drops_result {
+        Ok(drops) => drops,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch drop table");
+        }
+    };
+
+    let pity_result = sqlx::query!(
+        r#"
+        INSERT INTO chest_pity (user_id, chest_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, chest_id) DO UPDATE SET misses = chest_pity.misses
+        RETURNING misses
+        "#,
+        user.id,
+        chest.chest_id
+    )
+    .fetch_one(&mut *tx)
+    .await;
+
+    let misses = match pity_result {
+        Ok(query) => query.misses,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch pity counter");
+        }
+    };
+
+    let pity_reached = pity_reached(misses, chest.pity_threshold);
+    let rarity = match roll_rarity(&drops, pity_reached, &mut rand::rng()) {
+        Some(rarity) => rarity,
+        None => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Chest has an empty drop table");
+        }
+    };
+
+    let item_result = sqlx::query_as!(
+        TransferItem,
+        r#"
+        SELECT id, category as "category: ItemCategory", rarity as "rarity: ItemRarity"
+        FROM items
+        WHERE rarity = $1
+        ORDER BY RANDOM()
+        LIMIT 1
+        "#,
+        rarity as ItemRarity
+    )
+    .fetch_optional(&mut *tx)
+    .await;
+
+    let item = match item_result {
+        Ok(item) => item,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to select item");
+        }
+    };
+
+    let mut holdings = match lock_holdings(&mut tx, user.id).await {
+        Ok(holdings) => holdings,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch user data");
+        }
+    };
+
+    // Duplicate protection, owned items are converted into their sell-back value
+    let (item_id, coins) = match item {
+        Some(item) if holdings.give_item(&item).is_ok() => (Some(item.id), 0),
+        _ => (None, sell_back_price(rarity)),
+    };
+    holdings.coins += coins;
+
+    if save_holdings(&mut tx, &holdings).await.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update user data");
+    }
+
+    let new_misses = if rarity.is_rare_or_better() {
+        0
+    } else {
+        misses + 1
+    };
+
+    let pity_update = sqlx::query!(
+        r#"
+        UPDATE chest_pity
+        SET misses = $3
+        WHERE user_id = $1 AND chest_id = $2
+        "#,
+        user.id,
+        chest.chest_id,
+        new_misses
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if pity_update.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update pity counter");
+    }
+
+    let chest_update = sqlx::query!(
+        r#"
+        UPDATE user_chests
+        SET opened_at = CURRENT_TIMESTAMP, rarity = $2, item_id = $3, coins = $4
+        WHERE id = $1
+        "#,
+        user_chest_id,
+        rarity as ItemRarity,
+        item_id,
+        coins
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if chest_update.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update chest");
+    }
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().body("Failed to commit transaction");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Chest opened",
+        "name": chest.name,
+        "rarity": rarity,
+        "item_id": item_id,
+        "coins": coins,
+        "duplicate": item_id.is_none(),
+        "pity_triggered": pity_reached,
+        "remaining_coins": holdings.coins
+    }))
+}
@@ -0,0 +1,421 @@
+use {
+    super::quests::{
+        NewQuest,
+        insert_quest,
+    },
+    crate::utils::{
+        inventory::{
+            TransferItem,
+            lock_holdings,
+            save_holdings,
+        },
+        jwt::AuthenticatedUser,
+        requirements::{
+            QuestGoal,
+            QuestRequirement,
+        },
+        schemas::{
+            ItemCategory,
+            ItemRarity,
+            QuestDifficulty,
+            QuestStatus,
+        },
+    },
+    actix_web::{
+        self,
+        HttpResponse,
+        Result,
+        error::ErrorNotFound,
+        get,
+        post,
+        web,
+    },
+    chrono::NaiveDateTime,
+    serde::Serialize,
+    sqlx::{
+        PgConnection,
+        PgPool,
+        types::Json,
+    },
+    std::collections::HashMap,
+};
+
+#[derive(Clone, Debug)]
+pub struct CampaignStep {
+    pub id: i32,
+    pub campaign_id: i32,
+    pub key: String,
+    pub position: i32,
+    pub name: String,
+    pub story: String,
+    pub class: Option<String>, // only part of the campaign for this class
+    pub prerequisites: Vec<String>,
+    pub difficulty: QuestDifficulty,
+    pub number_of_workouts_needed: i32,
+    pub requirements: Json<Vec<QuestRequirement>>,
+    pub goal: Option<Json<QuestGoal>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepState {
+    Locked,
+    Available,
+    InProgress,
+    Abandoned,
+    Complete,
+}
+
+#[derive(Serialize)]
+pub struct StepProgress {
+    pub key: String,
+    pub position: i32,
+    pub name: String,
+    pub story: String,
+    pub class: Option<String>,
+    pub difficulty: QuestDifficulty,
+    pub state: StepState,
+    pub quest_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct CampaignProgress {
+    pub id: i32,
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub reward_item_id: i32,
+    pub started_at: Option<NaiveDateTime>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+    pub steps: Vec<StepProgress>,
+}
+
+// Latest quest issued for a step
+struct StepQuest {
+    id: i32,
+    status: QuestStatus,
+}
+
+async fn character_class(conn: &mut PgConnection, user_id: i32) -> Result<String, sqlx::Error> {
+    let class = sqlx::query_scalar!(
+        r#"
+            SELECT (class).name as "name!"
+            FROM characters
+            WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    return Ok(class);
+}
+
+// Steps of every campaign that are part of the campaign for the given class, in order
+async fn fetch_steps(
+    conn: &mut PgConnection,
+    class: &str,
+) -> Result<HashMap<i32, Vec<CampaignStep>>, sqlx::Error> {
+    let steps = sqlx::query_as!(
+        CampaignStep,
+        r#"
+            SELECT id, campaign_id, key, position, name, story, class, prerequisites,
+                difficulty as "difficulty: QuestDifficulty",
+                number_of_workouts_needed,
+                requirements as "requirements: Json<Vec<QuestRequirement>>",
+                goal as "goal: Json<QuestGoal>"
+            FROM campaign_steps
+            WHERE class IS NULL OR class = $1
+            ORDER BY campaign_id, position
+        "#,
+        class
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let mut campaigns: HashMap<i32, Vec<CampaignStep>> = HashMap::new();
+    for step in steps {
+        campaigns.entry(step.campaign_id).or_default().push(step);
+    }
+
+    return Ok(campaigns);
+}
+
+async fn fetch_step_quests(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<HashMap<i32, StepQuest>, sqlx::Error> {
+    let quests = sqlx::query!(
+        r#"
+            SELECT DISTINCT ON (campaign_step_id)
+                campaign_step_id as "campaign_step_id!", id, status as "status: QuestStatus"
+            FROM quests
+            WHERE user_id = $1 AND campaign_step_id IS NOT NULL
+            ORDER BY campaign_step_id, id DESC
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await?;
+
+    return Ok(quests
+        .into_iter()
+        .map(|quest| {
+            let step_quest = StepQuest {
+                id: quest.id,
+                status: quest.status,
+            };
+            (quest.campaign_step_id, step_quest)
+        })
+        .collect());
+}
+
+// A step unlocks once all of its prerequisites are complete.
+// Prerequisites that belong to another class's branch are not part of this campaign and are ignored.
+fn step_states(steps: &[CampaignStep], quests: &HashMap<i32, StepQuest>) -> Vec<StepState> {
+    let status = |step: &CampaignStep| quests.get(&step.id).map(|quest| quest.status);
+    let is_complete = |step: &CampaignStep| {
+        matches!(
+            status(step),
+            Some(QuestStatus::Complete) | Some(QuestStatus::Claimed)
+        )
+    };
+
+    let unlocked = |step: &CampaignStep| {
+        step.prerequisites.iter().all(|key| {
+            steps
+                .iter()
+                .find(|other| &other.key == key)
+                .is_none_or(is_complete)
+        })
+    };
+
+    return steps
+        .iter()
+        .map(|step| match status(step) {
+            Some(QuestStatus::Complete) | Some(QuestStatus::Claimed) => StepState::Complete,
+            Some(QuestStatus::Incomplete) => StepState::InProgress,
+            Some(QuestStatus::Abandoned) | Some(QuestStatus::Expired) => StepState::Abandoned,
+            None if unlocked(step) => StepState::Available,
+            None => StepState::Locked,
+        })
+        .collect();
+}
+
+// Issues quests for the steps that unlocked in the user's started campaigns and completes finished campaigns.
+// Abandoned steps are only issued again for the campaign being restarted.
+pub async fn advance_campaigns(
+    conn: &mut PgConnection,
+    user_id: i32,
+    restarted_campaign_id: Option<i32>,
+) -> Result<(), sqlx::Error> {
+    let class = character_class(conn, user_id).await?;
+    let campaigns = fetch_steps(conn, &class).await?;
+    let quests = fetch_step_quests(conn, user_id).await?;
+
+    let started = sqlx::query!(
+        r#"
+            SELECT p.campaign_id, c.reward_item_id
+            FROM campaign_progress p
+            JOIN campaigns c ON p.campaign_id = c.id
+            WHERE p.user_id = $1 AND p.completed_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for campaign in started {
+        let Some(steps) = campaigns.get(&campaign.campaign_id) else {
+            continue;
+        };
+        let states = step_states(steps, &quests);
+
+        if states.iter().all(|&state| state == StepState::Complete) {
+            complete_campaign(conn, user_id, campaign.campaign_id, campaign.reward_item_id).await?;
+            continue;
+        }
+
+        for (step, state) in steps.iter().zip(states) {
+            let issue = match state {
+                StepState::Available => true,
+                StepState::Abandoned => restarted_campaign_id == Some(campaign.campaign_id),
+                _ => false,
+            };
+
+            if issue {
+                let quest = NewQuest {
+                    name: step.name.clone(),
+                    difficulty: step.difficulty,
+                    board: None,
+                    campaign_step_id: Some(step.id),
+                    number_of_workouts_needed: step.number_of_workouts_needed,
+                    requirements: step.requirements.0.clone(),
+                    goal: step.goal.as_ref().map(|goal| goal.0.clone()),
+                    expires_at: None,
+                    seed: None,
+                };
+                insert_quest(conn, user_id, quest).await;
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+// Marks the campaign completed and gives its reward item, unless the user already owns it
+async fn complete_campaign(
+    conn: &mut PgConnection,
+    user_id: i32,
+    campaign_id: i32,
+    reward_item_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            UPDATE campaign_progress
+            SET completed_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND campaign_id = $2
+        "#,
+        user_id,
+        campaign_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let item = sqlx::query_as!(
+        TransferItem,
+        r#"
+            SELECT id, category as "category: ItemCategory", rarity as "rarity: ItemRarity"
+            FROM items
+            WHERE id = $1
+        "#,
+        reward_item_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let mut holdings = lock_holdings(conn, user_id).await?;
+    if holdings.give_item(&item).is_ok() {
+        save_holdings(conn, &holdings).await?;
+    }
+
+    return Ok(());
+}
+
+// Every campaign with the user's progress, steps of other classes are left out
+#[get("/campaigns")]
+pub async fn read_campaigns(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut conn = pool.acquire().await.unwrap();
+    let class = character_class(&mut conn, user.id).await.unwrap();
+    let mut campaigns_steps = fetch_steps(&mut conn, &class).await.unwrap();
+    let quests = fetch_step_quests(&mut conn, user.id).await.unwrap();
+
+    let campaigns = sqlx::query!(
+        r#"
+            SELECT c.id, c.key, c.name, c.description, c.reward_item_id,
+                p.started_at as "started_at?", p.completed_at
+            FROM campaigns c
+            LEFT JOIN campaign_progress p ON p.campaign_id = c.id AND p.user_id = $1
+            ORDER BY c.id
+        "#,
+        user.id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    let campaigns: Vec<CampaignProgress> = campaigns
+        .into_iter()
+        .map(|campaign| {
+            let steps = campaigns_steps.remove(&campaign.id).unwrap_or_default();
+            let states = step_states(&steps, &quests);
+
+            let steps: Vec<StepProgress> = steps
+                .into_iter()
+                .zip(states)
+                .map(|(step, state)| StepProgress {
+                    quest_id: quests.get(&step.id).map(|quest| quest.id),
+                    key: step.key,
+                    position: step.position,
+                    name: step.name,
+                    story: step.story,
+                    class: step.class,
+                    difficulty: step.difficulty,
+                    state,
+                })
+                .collect();
+
+            CampaignProgress {
+                id: campaign.id,
+                key: campaign.key,
+                name: campaign.name,
+                description: campaign.description,
+                reward_item_id: campaign.reward_item_id,
+                started_at: campaign.started_at,
+                completed_at: campaign.completed_at,
+                completed_steps: steps
+                    .iter()
+                    .filter(|step| step.state == StepState::Complete)
+                    .count(),
+                total_steps: steps.len(),
+                steps,
+            }
+        })
+        .collect();
+
+    return Ok(HttpResponse::Ok().json(campaigns));
+}
+
+// Starts a campaign, or restarts its abandoned steps
+#[post("/campaigns/{id}/start")]
+pub async fn start_campaign(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    campaign_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let campaign_id = campaign_id.into_inner();
+    let mut tx = pool.begin().await.unwrap();
+
+    let campaign = sqlx::query!(
+        r#"
+            SELECT id
+            FROM campaigns
+            WHERE id = $1
+        "#,
+        campaign_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if campaign.is_none() {
+        return Err(ErrorNotFound("Campaign not found"));
+    }
+
+    let _query = sqlx::query!(
+        r#"
+            INSERT INTO campaign_progress (user_id, campaign_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+        "#,
+        user.id,
+        campaign_id
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    advance_campaigns(&mut tx, user.id, Some(campaign_id))
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Campaign started"
+    })));
+}
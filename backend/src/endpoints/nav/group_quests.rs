@@ -0,0 +1,875 @@
+use {
+    super::{
+        quests::CreateQuestGoal,
+        trades::are_friends,
+    },
+    crate::utils::{
+        chests::grant_chest,
+        coins::add_coins,
+        generator::{
+            Words,
+            named_quest,
+            new_seed,
+            random_quest,
+        },
+        jwt::AuthenticatedUser,
+        level::add_exp,
+        requirements::{
+            QuestGoal,
+            QuestRequirement,
+            Requirement,
+            WorkoutContext,
+        },
+        schemas::{
+            ChestSource,
+            GroupMemberStatus,
+            GroupQuestRow,
+            QuestDifficulty,
+            QuestStatus,
+        },
+    },
+    actix_web::{
+        self,
+        HttpResponse,
+        Result,
+        error::{
+            ErrorBadRequest,
+            ErrorForbidden,
+            ErrorNotFound,
+        },
+        get,
+        post,
+        web,
+    },
+    chrono::{
+        NaiveDateTime,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::{
+        Acquire,
+        PgConnection,
+        PgPool,
+        types::Json,
+    },
+};
+
+#[derive(Serialize)]
+pub struct GroupQuestMember {
+    pub user_id: i32,
+    pub username: String,
+    pub status: GroupMemberStatus,
+    // contribution towards the shared goal
+    pub number_of_workouts_completed: i32,
+    pub progress: f32,
+    pub joined_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct GroupQuestDetails {
+    #[serde(flatten)]
+    pub quest: GroupQuestRow,
+    pub members: Vec<GroupQuestMember>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateGroupQuestRequest {
+    pub difficulty: QuestDifficulty,
+    #[serde(default)]
+    pub goal: Option<CreateQuestGoal>,
+    pub friend_ids: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct InviteToGroupQuestRequest {
+    pub friend_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct RespondToGroupQuestRequest {
+    pub accept: bool,
+}
+
+// Unfinished group quests past their expiry date can no longer be progressed
+async fn expire_group_quests(conn: &mut PgConnection) {
+    let _query = sqlx::query!(
+        r#"
+            UPDATE group_quests
+            SET status = 'expired'
+            WHERE status = 'incomplete' AND expires_at < NOW()
+        "#
+    )
+    .execute(conn)
+    .await
+    .unwrap();
+}
+
+async fn fetch_group_quests(conn: &mut PgConnection, ids: &[i32]) -> Vec<GroupQuestDetails> {
+    let quests = sqlx::query_as!(
+        GroupQuestRow,
+        r#"
+            SELECT id, owner_id, name,
+                difficulty as "difficulty: QuestDifficulty",
+                status as "status: QuestStatus",
+                number_of_workouts_needed,
+                number_of_workouts_completed,
+                requirements as "requirements: Json<Vec<QuestRequirement>>",
+                goal as "goal: Json<QuestGoal>",
+                progress, created_at, expires_at, completed_at, seed
+            FROM group_quests
+            WHERE id = ANY($1)
+            ORDER BY expires_at
+        "#,
+        ids
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    let members = sqlx::query!(
+        r#"
+            SELECT m.group_quest_id, m.user_id, c.username,
+                m.status as "status: GroupMemberStatus",
+                m.number_of_workouts_completed, m.progress, m.joined_at
+            FROM group_quest_members m
+            JOIN characters c ON c.user_id = m.user_id
+            WHERE m.group_quest_id = ANY($1)
+            ORDER BY m.progress DESC, m.number_of_workouts_completed DESC, m.invited_at
+        "#,
+        ids
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    return quests
+        .into_iter()
+        .map(|quest| GroupQuestDetails {
+            members: members
+                .iter()
+                .filter(|member| member.group_quest_id == quest.id)
+                .map(|member| GroupQuestMember {
+                    user_id: member.user_id,
+                    username: member.username.clone(),
+                    status: member.status,
+                    number_of_workouts_completed: member.number_of_workouts_completed,
+                    progress: member.progress,
+                    joined_at: member.joined_at,
+                })
+                .collect(),
+            quest,
+        })
+        .collect();
+}
+
+async fn member_status(
+    conn: &mut PgConnection,
+    group_quest_id: i32,
+    user_id: i32,
+) -> Option<GroupMemberStatus> {
+    return sqlx::query_scalar!(
+        r#"
+            SELECT status as "status: GroupMemberStatus"
+            FROM group_quest_members
+            WHERE group_quest_id = $1 AND user_id = $2
+        "#,
+        group_quest_id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+    .unwrap();
+}
+
+// Group quests the user is invited to or taking part in
+#[get("/group-quests")]
+pub async fn read_group_quests(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut conn = pool.acquire().await.unwrap();
+    expire_group_quests(&mut conn).await;
+
+    let ids = sqlx::query_scalar!(
+        r#"
+            SELECT q.id
+            FROM group_quests q
+            JOIN group_quest_members m ON m.group_quest_id = q.id
+            WHERE m.user_id = $1 AND m.status IN ('invited', 'joined')
+                AND q.status IN ('incomplete', 'complete')
+        "#,
+        user.id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    let quests = fetch_group_quests(&mut conn, &ids).await;
+    return Ok(HttpResponse::Ok().json(quests));
+}
+
+// A group quest with each member's contribution
+#[get("/group-quests/{id}")]
+pub async fn read_group_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    group_quest_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group_quest_id = group_quest_id.into_inner();
+    let mut conn = pool.acquire().await.unwrap();
+    expire_group_quests(&mut conn).await;
+
+    if member_status(&mut conn, group_quest_id, user.id).await.is_none() {
+        return Err(ErrorNotFound("Group quest not found"));
+    }
+
+    let quest = fetch_group_quests(&mut conn, &[group_quest_id])
+        .await
+        .pop()
+        .unwrap();
+    return Ok(HttpResponse::Ok().json(quest));
+}
+
+// Creates a group quest and invites friends into it, the shared goal grows with the number of friends who join
+#[post("/group-quests")]
+pub async fn create_group_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    request: web::Json<CreateGroupQuestRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let request = request.into_inner();
+    let difficulty = request.difficulty;
+
+    let mut friend_ids = request.friend_ids.clone();
+    friend_ids.sort_unstable();
+    friend_ids.dedup();
+    if friend_ids.len() != request.friend_ids.len() || friend_ids.contains(&user.id) {
+        return Err(ErrorBadRequest("Friends can only be invited once"));
+    }
+
+    let mut tx = pool.begin().await.unwrap();
+    expire_group_quests(&mut tx).await;
+
+    for &friend_id in &friend_ids {
+        if !are_friends(&mut tx, user.id, friend_id).await.unwrap() {
+            return Err(ErrorForbidden("User is not in your friends list"));
+        }
+    }
+
+    let active = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) as "count!"
+            FROM group_quests
+            WHERE owner_id = $1 AND difficulty = $2 AND status = 'incomplete'
+        "#,
+        user.id,
+        difficulty as QuestDifficulty,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    if active >= difficulty.max_active() {
+        return Err(ErrorBadRequest(
+            "Too many active group quests of this difficulty",
+        ));
+    }
+
+    let words = Words::load(&mut tx).await.unwrap();
+    let quest = match request.goal {
+        Some(goal) => {
+            if goal.target <= 0.0 {
+                return Err(ErrorBadRequest("Goal target must be positive"));
+            }
+
+            let goal = QuestGoal::new(goal.metric, goal.target, goal.exercise_id);
+            named_quest(new_seed(), goal, &words)
+        }
+        None => random_quest(new_seed(), difficulty, &words),
+    };
+
+    // Every member is expected to put in the workouts or the goal of a solo quest, only the owner has joined yet
+    let target_per_member = quest.goal.as_ref().map(|goal| goal.target);
+    let expires_at = Utc::now().naive_utc() + difficulty.lifetime();
+
+    let group_quest_id = sqlx::query_scalar!(
+        r#"
+            INSERT INTO group_quests (owner_id, name, difficulty, number_of_workouts_needed, requirements, goal, expires_at, seed,
+                workouts_per_member, target_per_member)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $4, $9)
+            RETURNING id
+        "#,
+        user.id,
+        quest.name,
+        difficulty as QuestDifficulty,
+        quest.number_of_workouts_needed,
+        Json(quest.requirements) as Json<Vec<QuestRequirement>>,
+        quest.goal.map(Json) as Option<Json<QuestGoal>>,
+        expires_at,
+        // the seed reproduces what each member puts in
+        quest.seed,
+        target_per_member,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    let _query = sqlx::query!(
+        r#"
+            INSERT INTO group_quest_members (group_quest_id, user_id, status, joined_at)
+            VALUES ($1, $2, 'joined', CURRENT_TIMESTAMP)
+        "#,
+        group_quest_id,
+        user.id,
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    let _query = sqlx::query!(
+        r#"
+            INSERT INTO group_quest_members (group_quest_id, user_id, status)
+            SELECT $1, friend_id, 'invited'
+            FROM UNNEST($2::INTEGER[]) AS friend_id
+        "#,
+        group_quest_id,
+        &friend_ids,
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    let quest = fetch_group_quests(&mut tx, &[group_quest_id])
+        .await
+        .pop()
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(quest));
+}
+
+// Only the owner can invite, friends that declined or left can be invited again
+#[post("/group-quests/{id}/invite")]
+pub async fn invite_to_group_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    group_quest_id: web::Path<i32>,
+    request: web::Json<InviteToGroupQuestRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group_quest_id = group_quest_id.into_inner();
+    let mut tx = pool.begin().await.unwrap();
+    expire_group_quests(&mut tx).await;
+
+    let owned = sqlx::query!(
+        r#"
+            SELECT id
+            FROM group_quests
+            WHERE id = $1 AND owner_id = $2 AND status = 'incomplete'
+        "#,
+        group_quest_id,
+        user.id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if owned.is_none() {
+        return Err(ErrorNotFound("No group quest in progress owned by you with this id"));
+    }
+
+    if !are_friends(&mut tx, user.id, request.friend_id).await.unwrap() {
+        return Err(ErrorForbidden("User is not in your friends list"));
+    }
+
+    let invited = sqlx::query!(
+        r#"
+            INSERT INTO group_quest_members (group_quest_id, user_id, status)
+            VALUES ($1, $2, 'invited')
+            ON CONFLICT (group_quest_id, user_id) DO UPDATE
+            SET status = 'invited', invited_at = CURRENT_TIMESTAMP
+            WHERE group_quest_members.status IN ('declined', 'left')
+            RETURNING user_id
+        "#,
+        group_quest_id,
+        request.friend_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if invited.is_none() {
+        return Err(ErrorBadRequest("Friend is already invited or taking part"));
+    }
+
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Friend invited"
+    })));
+}
+
+// Locks a group quest in progress while its members change, false when there is none with this id
+async fn lock_group_quest(conn: &mut PgConnection, group_quest_id: i32) -> bool {
+    return sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM group_quests
+            WHERE id = $1 AND status = 'incomplete'
+            FOR UPDATE
+        "#,
+        group_quest_id
+    )
+    .fetch_optional(conn)
+    .await
+    .unwrap()
+    .is_some();
+}
+
+// The shared goal follows the members who joined. Once a member leaves the progress made so far can be
+// enough, the quest is then completed and the members left are rewarded.
+async fn rescale_group_quest(conn: &mut PgConnection, group_quest_id: i32) {
+    let quest = sqlx::query_as!(
+        GroupQuestRow,
+        r#"
+            UPDATE group_quests q
+            SET number_of_workouts_needed = q.workouts_per_member * m.joined,
+                goal = jsonb_set(q.goal, '{target}', to_jsonb(q.target_per_member * m.joined))
+            FROM (
+                SELECT COUNT(*)::INTEGER as joined
+                FROM group_quest_members
+                WHERE group_quest_id = $1 AND status = 'joined'
+            ) m
+            WHERE q.id = $1
+            RETURNING q.id, q.owner_id, q.name,
+                q.difficulty as "difficulty: QuestDifficulty",
+                q.status as "status: QuestStatus",
+                q.number_of_workouts_needed,
+                q.number_of_workouts_completed,
+                q.requirements as "requirements: Json<Vec<QuestRequirement>>",
+                q.goal as "goal: Json<QuestGoal>",
+                q.progress, q.created_at, q.expires_at, q.completed_at, q.seed
+        "#,
+        group_quest_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+
+    let complete = match &quest.goal {
+        Some(goal) => quest.progress >= goal.target,
+        None => quest.number_of_workouts_completed >= quest.number_of_workouts_needed,
+    };
+    if !complete {
+        return;
+    }
+
+    let _query = sqlx::query!(
+        r#"
+            UPDATE group_quests
+            SET status = 'complete', completed_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+        "#,
+        group_quest_id
+    )
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    reward_members(conn, vec![quest]).await;
+}
+
+#[post("/group-quests/{id}/respond")]
+pub async fn respond_to_group_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    group_quest_id: web::Path<i32>,
+    request: web::Json<RespondToGroupQuestRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group_quest_id = group_quest_id.into_inner();
+    let mut tx = pool.begin().await.unwrap();
+    expire_group_quests(&mut tx).await;
+
+    let status = if request.accept {
+        GroupMemberStatus::Joined
+    } else {
+        GroupMemberStatus::Declined
+    };
+
+    if !lock_group_quest(&mut tx, group_quest_id).await {
+        return Err(ErrorNotFound("No pending invite to this group quest"));
+    }
+
+    let query = sqlx::query!(
+        r#"
+            UPDATE group_quest_members
+            SET status = $3::group_member_status,
+                joined_at = CASE WHEN $3::group_member_status = 'joined' THEN CURRENT_TIMESTAMP END
+            WHERE group_quest_id = $1 AND user_id = $2 AND status = 'invited'
+            RETURNING user_id
+        "#,
+        group_quest_id,
+        user.id,
+        status as GroupMemberStatus,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if query.is_none() {
+        return Err(ErrorNotFound("No pending invite to this group quest"));
+    }
+
+    rescale_group_quest(&mut tx, group_quest_id).await;
+    tx.commit().await.unwrap();
+
+    let message = if request.accept {
+        "Joined group quest"
+    } else {
+        "Declined group quest"
+    };
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": message
+    })));
+}
+
+// Contributions made so far keep counting towards the shared goal
+#[post("/group-quests/{id}/leave")]
+pub async fn leave_group_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    group_quest_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group_quest_id = group_quest_id.into_inner();
+    let mut tx = pool.begin().await.unwrap();
+    expire_group_quests(&mut tx).await;
+
+    let locked = lock_group_quest(&mut tx, group_quest_id).await;
+    let query = sqlx::query!(
+        r#"
+            UPDATE group_quest_members m
+            SET status = 'left'
+            FROM group_quests q
+            WHERE m.group_quest_id = q.id AND q.id = $1 AND m.user_id = $2
+                AND m.status = 'joined' AND q.owner_id != $2
+            RETURNING m.user_id
+        "#,
+        group_quest_id,
+        user.id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if !locked || query.is_none() {
+        return Err(ErrorBadRequest(
+            "Not taking part in this group quest, owners cannot leave",
+        ));
+    }
+
+    rescale_group_quest(&mut tx, group_quest_id).await;
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Left group quest"
+    })));
+}
+
+// Adds a member's workout to the group quests they take part in, and rewards every member of the completed ones.
+// Edited workouts only count towards the group quests they did not count towards yet.
+pub async fn apply_workout_to_group_quests(
+    conn: &mut PgConnection,
+    user_id: i32,
+    history_id: i32,
+    context: &WorkoutContext,
+) {
+    let mut tx = conn.begin().await.unwrap();
+    expire_group_quests(&mut tx).await;
+
+    // Members logging workouts at the same time must not overwrite each other's progress
+    let quests = sqlx::query_as!(
+        GroupQuestRow,
+        r#"
+            SELECT q.id, q.owner_id, q.name,
+                q.difficulty as "difficulty: QuestDifficulty",
+                q.status as "status: QuestStatus",
+                q.number_of_workouts_needed,
+                q.number_of_workouts_completed,
+                q.requirements as "requirements: Json<Vec<QuestRequirement>>",
+                q.goal as "goal: Json<QuestGoal>",
+                q.progress, q.created_at, q.expires_at, q.completed_at, q.seed
+            FROM group_quests q
+            JOIN group_quest_members m ON m.group_quest_id = q.id
+            WHERE m.user_id = $1 AND m.status = 'joined' AND q.status = 'incomplete'
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM group_quest_progress p
+                    WHERE p.group_quest_id = q.id AND p.history_id = $2
+                )
+            ORDER BY q.id
+            FOR UPDATE OF q
+        "#,
+        user_id,
+        history_id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
+
+    let mut completed = Vec::new();
+    for quest in quests {
+        if !quest
+            .requirements
+            .iter()
+            .all(|requirement| requirement.is_met(context))
+        {
+            continue;
+        }
+
+        let amount = match &quest.goal {
+            Some(goal) => goal.measure(context),
+            None => 0.0,
+        };
+        // Goal quests only count workouts that add to the goal
+        if quest.goal.is_some() && amount <= 0.0 {
+            continue;
+        }
+        let number_of_workouts_completed = quest.number_of_workouts_completed + 1;
+        let progress = quest.progress + amount;
+
+        let complete = match &quest.goal {
+            Some(goal) => progress >= goal.target,
+            None => number_of_workouts_completed >= quest.number_of_workouts_needed,
+        };
+        let status = if complete {
+            QuestStatus::Complete
+        } else {
+            QuestStatus::Incomplete
+        };
+
+        let _query = sqlx::query!(
+            r#"
+                UPDATE group_quests
+                SET number_of_workouts_completed = $2, progress = $3, status = $4::quest_status,
+                    completed_at = CASE WHEN $4::quest_status = 'complete' THEN CURRENT_TIMESTAMP END
+                WHERE id = $1
+            "#,
+            quest.id,
+            number_of_workouts_completed,
+            progress,
+            status as QuestStatus,
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        let _query = sqlx::query!(
+            r#"
+                UPDATE group_quest_members
+                SET number_of_workouts_completed = number_of_workouts_completed + 1,
+                    progress = progress + $3
+                WHERE group_quest_id = $1 AND user_id = $2
+            "#,
+            quest.id,
+            user_id,
+            amount,
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        let _query = sqlx::query!(
+            r#"
+                INSERT INTO group_quest_progress (group_quest_id, history_id, user_id, delta)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            quest.id,
+            history_id,
+            user_id,
+            amount,
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        if complete {
+            completed.push(quest);
+        }
+    }
+
+    reward_members(&mut tx, completed).await;
+    tx.commit().await.unwrap();
+}
+
+// Every joined member gets the rewards of a completed group quest
+async fn reward_members(conn: &mut PgConnection, completed: Vec<GroupQuestRow>) {
+    for quest in completed {
+        let member_ids = sqlx::query_scalar!(
+            r#"
+                SELECT user_id
+                FROM group_quest_members
+                WHERE group_quest_id = $1 AND status = 'joined'
+            "#,
+            quest.id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+
+        let difficulty = quest.difficulty;
+        for member_id in member_ids {
+            let member = AuthenticatedUser { id: member_id };
+            add_exp(&member, conn, difficulty.exp()).await.unwrap();
+            add_coins(&member, conn, difficulty.coins()).await.unwrap();
+            grant_chest(&member, conn, difficulty.chest(), ChestSource::Quest)
+                .await
+                .unwrap();
+        }
+    }
+}
+
+// Re-evaluates a member's edited or deleted (no context) workout against the group quests it counted towards.
+// Completed group quests are settled, their rewards already went to every member.
+pub async fn reconcile_group_quest_progress(
+    conn: &mut PgConnection,
+    user_id: i32,
+    history_id: i32,
+    context: Option<&WorkoutContext>,
+) {
+    let mut tx = conn.begin().await.unwrap();
+    expire_group_quests(&mut tx).await;
+
+    let quests = sqlx::query!(
+        r#"
+            SELECT q.id,
+                q.number_of_workouts_needed,
+                q.number_of_workouts_completed,
+                q.requirements as "requirements: Json<Vec<QuestRequirement>>",
+                q.goal as "goal: Json<QuestGoal>",
+                q.progress,
+                p.delta
+            FROM group_quest_progress p
+            JOIN group_quests q ON q.id = p.group_quest_id
+            WHERE p.history_id = $1 AND p.user_id = $2 AND q.status = 'incomplete'
+            ORDER BY q.id
+            FOR UPDATE OF q
+        "#,
+        history_id,
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
+
+    let mut completed = Vec::new();
+    for quest in quests {
+        let contribution = context
+            .filter(|context| {
+                quest
+                    .requirements
+                    .iter()
+                    .all(|requirement| requirement.is_met(context))
+            })
+            .map(|context| match &quest.goal {
+                Some(goal) => goal.measure(context),
+                None => 0.0,
+            })
+            .filter(|delta| quest.goal.is_none() || *delta > 0.0);
+
+        // Change to the quest's and the member's counters
+        let (workouts, amount) = match contribution {
+            Some(delta) => (0, delta - quest.delta),
+            None => (-1, -quest.delta),
+        };
+        let number_of_workouts_completed = quest.number_of_workouts_completed + workouts;
+        let progress = (quest.progress + amount).max(0.0);
+
+        let complete = match &quest.goal {
+            Some(goal) => progress >= goal.target,
+            None => number_of_workouts_completed >= quest.number_of_workouts_needed,
+        };
+        let status = if complete {
+            QuestStatus::Complete
+        } else {
+            QuestStatus::Incomplete
+        };
+
+        let quest = sqlx::query_as!(
+            GroupQuestRow,
+            r#"
+                UPDATE group_quests
+                SET number_of_workouts_completed = $2, progress = $3, status = $4::quest_status,
+                    completed_at = CASE WHEN $4::quest_status = 'complete' THEN CURRENT_TIMESTAMP END
+                WHERE id = $1
+                RETURNING id, owner_id, name,
+                    difficulty as "difficulty: QuestDifficulty",
+                    status as "status: QuestStatus",
+                    number_of_workouts_needed,
+                    number_of_workouts_completed,
+                    requirements as "requirements: Json<Vec<QuestRequirement>>",
+                    goal as "goal: Json<QuestGoal>",
+                    progress, created_at, expires_at, completed_at, seed
+            "#,
+            quest.id,
+            number_of_workouts_completed,
+            progress,
+            status as QuestStatus,
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+
+        let _query = sqlx::query!(
+            r#"
+                UPDATE group_quest_members
+                SET number_of_workouts_completed = GREATEST(number_of_workouts_completed + $3, 0),
+                    progress = GREATEST(progress + $4, 0)
+                WHERE group_quest_id = $1 AND user_id = $2
+            "#,
+            quest.id,
+            user_id,
+            workouts,
+            amount,
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        match contribution {
+            Some(delta) => sqlx::query!(
+                r#"
+                    UPDATE group_quest_progress
+                    SET delta = $3
+                    WHERE group_quest_id = $1 AND history_id = $2
+                "#,
+                quest.id,
+                history_id,
+                delta
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap(),
+            None => sqlx::query!(
+                r#"
+                    DELETE FROM group_quest_progress
+                    WHERE group_quest_id = $1 AND history_id = $2
+                "#,
+                quest.id,
+                history_id
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap(),
+        };
+
+        if complete {
+            completed.push(quest);
+        }
+    }
+
+    reward_members(&mut tx, completed).await;
+    tx.commit().await.unwrap();
+}
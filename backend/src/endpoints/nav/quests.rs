@@ -1,12 +1,44 @@
 use {
-    super::workouts::history::CreateHistoryRequest,
+    super::{
+        campaigns::advance_campaigns,
+        group_quests::{
+            apply_workout_to_group_quests,
+            reconcile_group_quest_progress,
+        },
+    },
     crate::utils::{
+        chests::grant_chest,
         coins::add_coins,
         jwt::AuthenticatedUser,
         level::add_exp,
+        baseline::{
+            QuestTuning,
+            TrainingBaseline,
+        },
+        generator::{
+            GeneratedQuest,
+            QuestInputs,
+            Words,
+            adaptive_goal_quest,
+            adaptive_quest,
+            named_quest,
+            new_seed,
+        },
+        idempotency::idempotency,
+        requirements::{
+            QuestGoal,
+            QuestMetric,
+            QuestRequirement,
+            Requirement,
+            WorkoutContext,
+        },
+        schedule::is_scheduled,
         schemas::{
+            ChestSource,
             ExerciseCategory,
             ExerciseMuscle,
+            History,
+            QuestBoard,
             QuestDifficulty,
             QuestRow,
             QuestStatus,
@@ -16,29 +48,60 @@ use {
         self,
         HttpResponse,
         Result,
+        error::{
+            ErrorBadRequest,
+            ErrorNotFound,
+        },
         get,
+        middleware::from_fn,
         post,
+        rt::time::interval,
         web,
     },
-    rand::{
-        distr::{
-            Alphanumeric,
-            SampleString,
-        },
-        seq::{
-            IndexedRandom,
-            SliceRandom,
-        },
+    chrono::{
+        Datelike,
+        NaiveDate,
+        NaiveDateTime,
+        Utc,
     },
     serde::{
         Deserialize,
         Serialize,
     },
-    sqlx::PgPool,
-    strum::VariantArray,
+    sqlx::{
+        Acquire,
+        PgConnection,
+        PgPool,
+        types::Json,
+    },
+    std::time::Duration,
 };
 
+// Max number of quests a user can reroll per day, in the timezone of their settings
+const DAILY_REROLLS: i32 = 2;
+
+// Incomplete quests past their expiry date can no longer be progressed
+pub async fn expire_quests(conn: &mut PgConnection, user_id: i32) {
+    let _query = sqlx::query!(
+        r#"
+            UPDATE quests
+            SET status = 'expired'
+            WHERE user_id = $1 AND status = 'incomplete' AND expires_at < NOW()
+        "#,
+        user_id,
+    )
+    .execute(conn)
+    .await
+    .unwrap();
+}
+
+// Quests that are in progress or waiting to be claimed
 pub async fn _read_quests(user: &AuthenticatedUser, pool: &web::Data<PgPool>) -> Vec<QuestRow> {
+    generate_quest_boards(pool, user.id).await;
+
+    let mut conn = pool.acquire().await.unwrap();
+    expire_quests(&mut conn, user.id).await;
+
     let query: Vec<QuestRow> = sqlx::query_as!(
         QuestRow,
         r#"
@@ -47,21 +110,184 @@ pub async fn _read_quests(user: &AuthenticatedUser, pool: &web::Data<PgPool>) ->
                 status as "status: QuestStatus",
                 number_of_workouts_needed, 
                 number_of_workouts_completed, 
-                workout_duration,
-                exercise_category as "exercise_category: ExerciseCategory",
-                exercise_muscle as "exercise_muscle: ExerciseMuscle"
+                requirements as "requirements: Json<Vec<QuestRequirement>>",
+                goal as "goal: Json<QuestGoal>",
+                progress,
+                created_at, expires_at, claimed_at,
+                board as "board: QuestBoard",
+                campaign_step_id,
+                seed,
+                quest_requirement(requirements, 'workout_duration', 'minutes')::int as workout_duration,
+                quest_requirement(requirements, 'exercise_category', 'category')::exercise_category
+                    as "exercise_category: ExerciseCategory",
+                quest_requirement(requirements, 'exercise_muscle', 'muscle')::exercise_muscle
+                    as "exercise_muscle: ExerciseMuscle"
             FROM quests
-            WHERE user_id = $1
+            WHERE user_id = $1 AND status IN ('incomplete', 'complete')
+            ORDER BY expires_at NULLS LAST
         "#,
         user.id,
     )
-    .fetch_all(pool.get_ref())
+    .fetch_all(&mut *conn)
     .await
     .unwrap();
 
     return query;
 }
 
+// Issues the daily and weekly boards of the current period in the user's timezone, if not issued yet.
+// Daily boards are only issued on scheduled workout days, weekly quests are sized by the number of scheduled days.
+pub async fn generate_quest_boards(pool: &PgPool, user_id: i32) {
+    let mut tx = pool.begin().await.unwrap();
+
+    // Period boundaries are computed in the user's timezone and stored in UTC like every other timestamp
+    let period = sqlx::query!(
+        r#"
+            SELECT
+                (NOW() AT TIME ZONE timezone)::date as "today!",
+                date_trunc('week', NOW() AT TIME ZONE timezone)::date as "week_start!",
+                (date_trunc('day', NOW() AT TIME ZONE timezone) + INTERVAL '1 day') AT TIME ZONE timezone AT TIME ZONE 'UTC' as "day_end!",
+                (date_trunc('week', NOW() AT TIME ZONE timezone) + INTERVAL '1 week') AT TIME ZONE timezone AT TIME ZONE 'UTC' as "week_end!",
+                workout_schedule
+            FROM settings
+            WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    // Users that have not finished onboarding have no schedule yet
+    let Some(period) = period else {
+        return;
+    };
+
+    let scheduled_today = is_scheduled(&period.workout_schedule, period.today);
+
+    // Weekly quests only count the scheduled days left, so boards issued mid-week stay achievable
+    let days_left = 7 - period.today.weekday().num_days_from_monday() as i64;
+    let scheduled_days = (0..days_left)
+        .filter(|&offset| {
+            is_scheduled(&period.workout_schedule, period.today + chrono::Duration::days(offset))
+        })
+        .count() as i32;
+
+    let issue_daily =
+        claim_board(&mut tx, user_id, QuestBoard::Daily, period.today).await && scheduled_today;
+    let issue_weekly = claim_board(&mut tx, user_id, QuestBoard::Weekly, period.week_start).await
+        && scheduled_days > 0;
+
+    if !issue_daily && !issue_weekly {
+        tx.commit().await.unwrap();
+        return;
+    }
+
+    let inputs = quest_inputs(&mut tx, user_id).await;
+
+    if issue_daily {
+        for _ in 0..3 {
+            let quest = NewQuest::generated(
+                adaptive_quest(new_seed(), QuestDifficulty::Easy, &inputs),
+                QuestDifficulty::Easy,
+                Some(QuestBoard::Daily),
+                Some(period.day_end),
+            );
+            insert_quest(&mut tx, user_id, quest).await;
+        }
+    }
+
+    if issue_weekly {
+        let board = Some(QuestBoard::Weekly);
+
+        let medium = adaptive_quest(new_seed(), QuestDifficulty::Medium, &inputs);
+        let medium =
+            NewQuest::generated(medium, QuestDifficulty::Medium, board, Some(period.week_end));
+        let needed = medium.number_of_workouts_needed.min(scheduled_days);
+        let medium = medium.sized(needed);
+
+        // e.g. run 20 km this week
+        let goal =
+            adaptive_goal_quest(new_seed(), QuestDifficulty::Medium, scheduled_days, &inputs);
+        let mut weekly = vec![
+            medium,
+            NewQuest::generated(goal, QuestDifficulty::Medium, board, Some(period.week_end)),
+        ];
+
+        // A hard quest asks for a workout on every scheduled day left
+        if scheduled_days >= 3 {
+            let hard = adaptive_quest(new_seed(), QuestDifficulty::Hard, &inputs);
+            let hard =
+                NewQuest::generated(hard, QuestDifficulty::Hard, board, Some(period.week_end));
+            weekly.push(hard.sized(scheduled_days));
+        }
+
+        for quest in weekly {
+            insert_quest(&mut tx, user_id, quest).await;
+        }
+    }
+
+    tx.commit().await.unwrap();
+}
+
+// Words, recent training and preferences quests of the user are generated from
+async fn quest_inputs(conn: &mut PgConnection, user_id: i32) -> QuestInputs<Words> {
+    return QuestInputs {
+        words: Words::load(conn).await.unwrap(),
+        baseline: TrainingBaseline::load(conn, user_id).await.unwrap(),
+        tuning: QuestTuning::load(conn, user_id).await.unwrap(),
+    };
+}
+
+// Records that a board was issued for the period, returns false if it already was
+async fn claim_board(
+    conn: &mut PgConnection,
+    user_id: i32,
+    board: QuestBoard,
+    starts_on: NaiveDate,
+) -> bool {
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO quest_boards (user_id, board, starts_on)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+        "#,
+        user_id,
+        board as QuestBoard,
+        starts_on,
+    )
+    .fetch_optional(conn)
+    .await
+    .unwrap();
+
+    return query.is_some();
+}
+
+// Runs for the lifetime of the server, issuing new boards as periods roll over in each user's timezone
+pub async fn refresh_quest_boards(pool: PgPool) {
+    let mut ticks = interval(Duration::from_secs(15 * 60));
+
+    loop {
+        ticks.tick().await;
+
+        let user_ids = match sqlx::query_scalar!("SELECT user_id FROM settings")
+            .fetch_all(&pool)
+            .await
+        {
+            Ok(user_ids) => user_ids,
+            Err(e) => {
+                log::error!("Failed to fetch users for quest boards: {}", e);
+                continue;
+            }
+        };
+
+        for user_id in user_ids {
+            generate_quest_boards(&pool, user_id).await;
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct ReadQuestsResponse {
     pub quests: Vec<QuestRow>,
@@ -76,215 +302,693 @@ pub async fn read_quests(
     return Ok(HttpResponse::Ok().json(ReadQuestsResponse { quests }));
 }
 
+// Workout that advanced a quest
+#[derive(Serialize)]
+pub struct QuestContribution {
+    pub history_id: i32,
+    pub name: String,
+    pub date: NaiveDate,
+    pub delta: f32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct QuestDetails {
+    #[serde(flatten)]
+    pub quest: QuestRow,
+    pub contributions: Vec<QuestContribution>,
+}
+
+// Any quest of the user with the workouts that counted towards it
+#[get("/quests/{id}")]
+pub async fn read_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    quest_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let quest_id = quest_id.into_inner();
+    let mut conn = pool.acquire().await.unwrap();
+    expire_quests(&mut conn, user.id).await;
+
+    let quest = sqlx::query_as!(
+        QuestRow,
+        r#"
+            SELECT id, user_id, name,
+                difficulty as "difficulty: QuestDifficulty",
+                status as "status: QuestStatus",
+                number_of_workouts_needed,
+                number_of_workouts_completed,
+                requirements as "requirements: Json<Vec<QuestRequirement>>",
+                goal as "goal: Json<QuestGoal>",
+                progress,
+                created_at, expires_at, claimed_at,
+                board as "board: QuestBoard",
+                campaign_step_id,
+                seed,
+                quest_requirement(requirements, 'workout_duration', 'minutes')::int as workout_duration,
+                quest_requirement(requirements, 'exercise_category', 'category')::exercise_category
+                    as "exercise_category: ExerciseCategory",
+                quest_requirement(requirements, 'exercise_muscle', 'muscle')::exercise_muscle
+                    as "exercise_muscle: ExerciseMuscle"
+            FROM quests
+            WHERE id = $1 AND user_id = $2
+        "#,
+        quest_id,
+        user.id,
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .unwrap();
+
+    let Some(quest) = quest else {
+        return Err(ErrorNotFound("Quest not found"));
+    };
+
+    let contributions = sqlx::query_as!(
+        QuestContribution,
+        r#"
+            SELECT p.history_id, h.name, h.date, p.delta, p.created_at
+            FROM quest_progress p
+            JOIN history h ON h.id = p.history_id
+            WHERE p.quest_id = $1
+            ORDER BY p.created_at, p.history_id
+        "#,
+        quest_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    return Ok(HttpResponse::Ok().json(QuestDetails {
+        quest,
+        contributions,
+    }));
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateQuestGoal {
+    pub metric: QuestMetric,
+    pub target: f32,
+    pub exercise_id: Option<String>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct CreateQuestRequest {
     pub difficulty: QuestDifficulty,
+    // makes a progress-based quest, e.g. 5000 lbs of squats
+    #[serde(default)]
+    pub goal: Option<CreateQuestGoal>,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct CreateQuestResponse {
+// Everything about a quest to be issued
+pub struct NewQuest {
     pub name: String,
     pub difficulty: QuestDifficulty,
-    pub status: QuestStatus,
+    pub board: Option<QuestBoard>,
+    pub campaign_step_id: Option<i32>,
     pub number_of_workouts_needed: i32,
-    pub number_of_workouts_completed: i32,
-    // possible requirements
-    pub workout_duration: Option<i32>,
-    pub exercise_category: Option<ExerciseCategory>,
-    pub exercise_muscle: Option<ExerciseMuscle>,
+    pub requirements: Vec<QuestRequirement>,
+    pub goal: Option<QuestGoal>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub seed: Option<i64>,
 }
 
-#[post("/quests")]
+impl NewQuest {
+    pub fn generated(
+        quest: GeneratedQuest,
+        difficulty: QuestDifficulty,
+        board: Option<QuestBoard>,
+        expires_at: Option<NaiveDateTime>,
+    ) -> NewQuest {
+        return NewQuest {
+            name: quest.name,
+            difficulty,
+            board,
+            campaign_step_id: None,
+            number_of_workouts_needed: quest.number_of_workouts_needed,
+            requirements: quest.requirements,
+            goal: quest.goal,
+            expires_at,
+            seed: quest.seed,
+        };
+    }
+
+    // The seed no longer reproduces a quest sized apart from the generator
+    pub fn sized(mut self, number_of_workouts_needed: i32) -> NewQuest {
+        if number_of_workouts_needed != self.number_of_workouts_needed {
+            self.number_of_workouts_needed = number_of_workouts_needed;
+            self.seed = None;
+        }
+        return self;
+    }
+}
+
+// Stores a new quest for the user
+pub async fn insert_quest(conn: &mut PgConnection, user_id: i32, quest: NewQuest) -> QuestRow {
+    let quest = sqlx::query_as!(
+        QuestRow,
+        r#"
+            INSERT INTO quests (user_id, name, difficulty, status, number_of_workouts_needed, number_of_workouts_completed, requirements, goal, expires_at, board, campaign_step_id, seed)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id, user_id, name,
+                difficulty as "difficulty: QuestDifficulty",
+                status as "status: QuestStatus",
+                number_of_workouts_needed,
+                number_of_workouts_completed,
+                requirements as "requirements: Json<Vec<QuestRequirement>>",
+                goal as "goal: Json<QuestGoal>",
+                progress,
+                created_at, expires_at, claimed_at,
+                board as "board: QuestBoard",
+                campaign_step_id,
+                seed,
+                quest_requirement(requirements, 'workout_duration', 'minutes')::int as workout_duration,
+                quest_requirement(requirements, 'exercise_category', 'category')::exercise_category
+                    as "exercise_category: ExerciseCategory",
+                quest_requirement(requirements, 'exercise_muscle', 'muscle')::exercise_muscle
+                    as "exercise_muscle: ExerciseMuscle"
+        "#,
+        user_id,
+        quest.name,
+        quest.difficulty as QuestDifficulty,
+        QuestStatus::Incomplete as QuestStatus,
+        quest.number_of_workouts_needed,
+        0,
+        Json(quest.requirements) as Json<Vec<QuestRequirement>>,
+        quest.goal.map(Json) as Option<Json<QuestGoal>>,
+        quest.expires_at,
+        quest.board as Option<QuestBoard>,
+        quest.campaign_step_id,
+        quest.seed,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+
+    return quest;
+}
+
+#[post("/quests", wrap = "from_fn(idempotency)")]
 pub async fn create_quest(
     user: AuthenticatedUser,
     pool: web::Data<PgPool>,
     request: web::Json<CreateQuestRequest>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let mut rng = rand::rng();
-    let mut fields = vec!["workout_duration", "exercise_category", "exercise_muscle"];
-    fields.shuffle(&mut rng);
-
-    let mut workout_duration: Option<i32> = None;
-    let mut exercise_category: Option<ExerciseCategory> = None;
-    let mut exercise_muscle: Option<ExerciseMuscle> = None;
-
-    for field in fields.into_iter().take(request.difficulty.requirements()) {
-        match field {
-            "workout_duration" => workout_duration = Some(request.difficulty.workout_duration()),
-            "exercise_category" => {
-                exercise_category = Some(*ExerciseCategory::VARIANTS.choose(&mut rng).unwrap())
-            }
-            "exercise_muscle" => {
-                exercise_muscle = Some(*ExerciseMuscle::VARIANTS.choose(&mut rng).unwrap())
+    let mut tx = pool.begin().await.unwrap();
+    expire_quests(&mut tx, user.id).await;
+
+    let active = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) as "count!"
+            FROM quests
+            WHERE user_id = $1 AND difficulty = $2 AND board IS NULL AND campaign_step_id IS NULL AND status IN ('incomplete', 'complete')
+        "#,
+        user.id,
+        request.difficulty as QuestDifficulty,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    if active >= request.difficulty.max_active() {
+        return Err(ErrorBadRequest(
+            "Too many active quests of this difficulty, finish or abandon one first",
+        ));
+    }
+
+    let request = request.into_inner();
+    let difficulty = request.difficulty;
+    let expires_at = Some(Utc::now().naive_utc() + difficulty.lifetime());
+
+    let inputs = quest_inputs(&mut tx, user.id).await;
+
+    let generated = match request.goal {
+        Some(goal) => {
+            if goal.target <= 0.0 {
+                return Err(ErrorBadRequest("Goal target must be positive"));
             }
-            _ => unreachable!(),
+
+            let goal = QuestGoal::new(goal.metric, goal.target, goal.exercise_id);
+            named_quest(new_seed(), goal, &inputs.words)
         }
+        None => adaptive_quest(new_seed(), difficulty, &inputs),
+    };
+    let new_quest = NewQuest::generated(generated, difficulty, None, expires_at);
+    let quest = insert_quest(&mut tx, user.id, new_quest).await;
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(quest));
+}
+
+#[post("/quests/{id}/abandon")]
+pub async fn abandon_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    quest_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut conn = pool.acquire().await.unwrap();
+    expire_quests(&mut conn, user.id).await;
+
+    let query = sqlx::query!(
+        r#"
+            UPDATE quests
+            SET status = 'abandoned'
+            WHERE id = $1 AND user_id = $2 AND status = 'incomplete'
+            RETURNING id
+        "#,
+        quest_id.into_inner(),
+        user.id,
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .unwrap();
+
+    if query.is_none() {
+        return Err(ErrorNotFound("No quest in progress with this id"));
     }
 
-    let words = sqlx::query_scalar!(
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quest abandoned"
+    })));
+}
+
+// Swaps a quest without progress for a new one of the same difficulty, limited per day
+#[post("/quests/{id}/reroll")]
+pub async fn reroll_quest(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    quest_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut tx = pool.begin().await.unwrap();
+    expire_quests(&mut tx, user.id).await;
+
+    let quest = sqlx::query!(
         r#"
-            SELECT DISTINCT ON (category::text)
-                word
-            FROM words
-            WHERE category IN ('adjective', 'noun')
-            ORDER BY category::text, random();
-        "#
+            UPDATE quests
+            SET status = 'abandoned'
+            WHERE id = $1 AND user_id = $2 AND status = 'incomplete'
+            RETURNING difficulty as "difficulty: QuestDifficulty",
+                board as "board: QuestBoard",
+                number_of_workouts_needed,
+                number_of_workouts_completed,
+                goal as "goal: Json<QuestGoal>",
+                progress,
+                expires_at,
+                campaign_step_id
+        "#,
+        quest_id.into_inner(),
+        user.id,
     )
-    .fetch_all(pool.get_ref())
+    .fetch_optional(&mut *tx)
     .await
     .unwrap();
 
-    let adjective = &words[0];
-    let noun = &words[1];
-
-    let mut name = String::new();
-    name.push_str(adjective);
-    name.push(' ');
-    name.push_str(noun);
-
-    let response = CreateQuestResponse {
-        name: name,
-        difficulty: request.difficulty,
-        status: QuestStatus::Incomplete,
-        number_of_workouts_needed: request.difficulty.number_of_workouts_needed(),
-        number_of_workouts_completed: 0,
-        workout_duration,
-        exercise_category,
-        exercise_muscle,
+    let Some(quest) = quest else {
+        return Err(ErrorNotFound("No quest in progress with this id"));
     };
 
-    let _query = sqlx::query!(
+    if quest.campaign_step_id.is_some() {
+        return Err(ErrorBadRequest("Campaign quests cannot be rerolled"));
+    }
+
+    if quest.number_of_workouts_completed > 0 || quest.progress > 0.0 {
+        return Err(ErrorBadRequest("Quests with progress cannot be rerolled"));
+    }
+
+    let rerolls = sqlx::query_scalar!(
         r#"
-            INSERT INTO quests (user_id, name, difficulty, status, number_of_workouts_needed, number_of_workouts_completed, workout_duration, exercise_category, exercise_muscle)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO quest_rerolls (user_id, day, count)
+            SELECT $1, (NOW() AT TIME ZONE timezone)::date, 1
+            FROM settings
+            WHERE user_id = $1
+            ON CONFLICT (user_id, day) DO UPDATE
+            SET count = quest_rerolls.count + 1
+            WHERE quest_rerolls.count < $2
+            RETURNING count
         "#,
         user.id,
-        response.name,
-        response.difficulty as QuestDifficulty,
-        response.status as QuestStatus,
-        response.number_of_workouts_needed,
-        response.number_of_workouts_completed,
-        response.workout_duration,
-        response.exercise_category as Option<ExerciseCategory>,
-        response.exercise_muscle as Option<ExerciseMuscle>,
+        DAILY_REROLLS,
     )
-    .fetch_all(pool.get_ref())
+    .fetch_optional(&mut *tx)
     .await
     .unwrap();
 
-    return Ok(HttpResponse::Ok().json(response));
+    let Some(rerolls) = rerolls else {
+        return Err(ErrorBadRequest("No rerolls left today"));
+    };
+
+    // The replacement stays on the same board with the same deadline and size
+    let inputs = quest_inputs(&mut tx, user.id).await;
+    let generated = match &quest.goal {
+        Some(goal) => adaptive_goal_quest(new_seed(), quest.difficulty, goal.days(), &inputs),
+        None => adaptive_quest(new_seed(), quest.difficulty, &inputs),
+    };
+    let mut new_quest =
+        NewQuest::generated(generated, quest.difficulty, quest.board, quest.expires_at);
+    if quest.goal.is_none() {
+        new_quest = new_quest.sized(quest.number_of_workouts_needed);
+    }
+    let new_quest = insert_quest(&mut tx, user.id, new_quest).await;
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "quest": new_quest,
+        "rerolls_left": DAILY_REROLLS - rerolls
+    })));
 }
 
-pub async fn apply_workout_to_quests(
+// Grants the rewards of a completed quest
+#[post("/quests/{id}/claim")]
+pub async fn claim_quest(
     user: AuthenticatedUser,
     pool: web::Data<PgPool>,
-    workout: &CreateHistoryRequest,
+    quest_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Marking the quest claimed in the same transaction as the rewards grants them exactly once
+    let mut tx = pool.begin().await.unwrap();
+    let quest = sqlx::query!(
+        r#"
+            UPDATE quests
+            SET status = 'claimed', claimed_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND status = 'complete'
+            RETURNING id, difficulty as "difficulty: QuestDifficulty"
+        "#,
+        quest_id.into_inner(),
+        user.id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    let Some(quest) = quest else {
+        return Err(ErrorNotFound("No completed quest with this id"));
+    };
+
+    let difficulty = quest.difficulty;
+    add_exp(&user, &mut tx, difficulty.exp()).await?;
+    add_coins(&user, &mut tx, difficulty.coins()).await?;
+    let user_chest_id = grant_chest(&user, &mut tx, difficulty.chest(), ChestSource::Quest)
+        .await
+        .unwrap();
+
+    // Kept so the chest can be taken back if an edited workout no longer completes the quest
+    let _query = sqlx::query!(
+        r#"
+            UPDATE quests
+            SET user_chest_id = $2
+            WHERE id = $1
+        "#,
+        quest.id,
+        user_chest_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quest rewards claimed",
+        "exp": difficulty.exp(),
+        "coins": difficulty.coins(),
+        "chest": difficulty.chest()
+    })));
+}
+
+// Advances the user's quests with a logged workout and records which quests it counted towards.
+// Edited workouts only advance the quests they did not count towards yet. Runs in the transaction that
+// writes the workout, which generates the quest boards before it begins.
+pub async fn apply_workout_to_quests(
+    user: &AuthenticatedUser,
+    conn: &mut PgConnection,
+    workout: &History,
+    context: &WorkoutContext,
 ) {
-    let quests = _read_quests(&user, &pool).await;
-    for quest in quests
-        .iter()
-        .filter(|quest| quest.status != QuestStatus::Complete)
-    {
-        if workout_applies_to_quest(&pool, quest, workout).await {
-            let number_of_workouts_completed = quest.number_of_workouts_completed + 1;
+    let mut tx = conn.begin().await.unwrap();
 
-            if number_of_workouts_completed == quest.number_of_workouts_needed {
-                // Quest has been completed
-                let _query = sqlx::query!(
-                    r#"
-                        UPDATE quests
-                        SET number_of_workouts_completed = $2, status = $3
-                        WHERE id = $1
-                    "#,
-                    quest.id,
-                    number_of_workouts_completed,
-                    QuestStatus::Complete as QuestStatus
-                )
-                .execute(pool.get_ref())
-                .await
-                .unwrap();
-
-                add_exp(&user, &pool, quest.difficulty.exp()).await.unwrap();
-                add_coins(&user, &pool, quest.difficulty.coins())
-                    .await
-                    .unwrap();
-            } else {
-                // Quest is still incomplete
-                let _query = sqlx::query!(
-                    r#"
-                        UPDATE quests
-                        SET number_of_workouts_completed = $2
-                        WHERE id = $1
-                    "#,
-                    quest.id,
-                    number_of_workouts_completed,
+    // The workout can be deleted until it is locked, its progress is then already taken back
+    let exists = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM history
+            WHERE id = $1
+            FOR SHARE
+        "#,
+        workout.id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if exists.is_none() {
+        return;
+    }
+    expire_quests(&mut tx, user.id).await;
+
+    // Workouts logged, edited or deleted at the same time must not overwrite each other's progress
+    let quests = sqlx::query!(
+        r#"
+            SELECT q.id,
+                q.number_of_workouts_needed,
+                q.number_of_workouts_completed,
+                q.requirements as "requirements: Json<Vec<QuestRequirement>>",
+                q.goal as "goal: Json<QuestGoal>",
+                q.progress,
+                q.campaign_step_id
+            FROM quests q
+            WHERE q.user_id = $1 AND q.status = 'incomplete'
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM quest_progress p
+                    WHERE p.quest_id = q.id AND p.history_id = $2
                 )
-                .execute(pool.get_ref())
-                .await
-                .unwrap();
+            ORDER BY q.id
+            FOR UPDATE OF q
+        "#,
+        user.id,
+        workout.id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
+
+    let mut campaign_step_completed = false;
+    for quest in quests {
+        if quest
+            .requirements
+            .iter()
+            .all(|requirement| requirement.is_met(context))
+        {
+            let delta = match &quest.goal {
+                Some(goal) => goal.measure(context),
+                None => 0.0,
+            };
+            // Goal quests only count workouts that add to the goal
+            if quest.goal.is_some() && delta <= 0.0 {
+                continue;
             }
+            let number_of_workouts_completed = quest.number_of_workouts_completed + 1;
+            let progress = quest.progress + delta;
+
+            let complete = match &quest.goal {
+                Some(goal) => progress >= goal.target,
+                None => number_of_workouts_completed >= quest.number_of_workouts_needed,
+            };
+
+            campaign_step_completed |= complete && quest.campaign_step_id.is_some();
+
+            // Rewards of completed quests are granted once they are claimed
+            let status = if complete {
+                QuestStatus::Complete
+            } else {
+                QuestStatus::Incomplete
+            };
+
+            let _query = sqlx::query!(
+                r#"
+                    UPDATE quests
+                    SET number_of_workouts_completed = $2, progress = $3, status = $4
+                    WHERE id = $1
+                "#,
+                quest.id,
+                number_of_workouts_completed,
+                progress,
+                status as QuestStatus
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+            let _query = sqlx::query!(
+                r#"
+                    INSERT INTO quest_progress (quest_id, history_id, delta)
+                    VALUES ($1, $2, $3)
+                "#,
+                quest.id,
+                workout.id,
+                delta
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap();
         }
     }
-}
+    apply_workout_to_group_quests(&mut tx, user.id, workout.id, context).await;
 
-async fn workout_applies_to_quest(
-    pool: &web::Data<PgPool>,
-    quest: &QuestRow,
-    workout: &CreateHistoryRequest,
-) -> bool {
-    let mut flag = true;
-    if let Some(workout_duration) = quest.workout_duration {
-        flag &= workout.duration >= workout_duration;
+    // Unlock the next steps of the campaign
+    if campaign_step_completed {
+        advance_campaigns(&mut tx, user.id, None).await.unwrap();
     }
-    if let Some(exercise_category) = &quest.exercise_category {
-        let ids: Vec<String> = workout
-            .exercises
-            .0
-            .iter()
-            .map(|exercise| exercise.id.clone())
-            .collect();
+    tx.commit().await.unwrap();
+}
 
-        let categories: Vec<ExerciseCategory> = sqlx::query_scalar!(
-            r#"
-                SELECT category as "category: ExerciseCategory"
-                FROM exercises
-                WHERE id = ANY($1);
-            "#,
-            &ids
-        )
-        .fetch_all(pool.get_ref())
-        .await
-        .unwrap();
+// Claimed quest that is no longer complete
+pub struct RevokedQuest {
+    pub difficulty: QuestDifficulty,
+    pub user_chest_id: Option<i32>, // chest granted on claim
+}
 
-        flag &= categories.contains(exercise_category);
-    }
-    if let Some(exercise_muscle) = &quest.exercise_muscle {
-        let ids: Vec<String> = workout
-            .exercises
-            .0
-            .iter()
-            .map(|exercise| exercise.id.clone())
-            .collect();
+// Re-evaluates the quests a workout counted towards once it is edited, or takes its progress back when it is
+// deleted (no context). Quests the edited workout now counts towards are advanced by apply_workout_to_quests.
+// Returns the claimed quests that are no longer complete, taking their rewards back is up to the caller.
+pub async fn reconcile_quest_progress(
+    user: &AuthenticatedUser,
+    conn: &mut PgConnection,
+    history_id: i32,
+    context: Option<&WorkoutContext>,
+) -> Vec<RevokedQuest> {
+    let mut tx = conn.begin().await.unwrap();
+    expire_quests(&mut tx, user.id).await;
 
-        let query = sqlx::query!(
+    let quests = sqlx::query!(
+        r#"
+            SELECT q.id,
+                q.difficulty as "difficulty: QuestDifficulty",
+                q.status as "status: QuestStatus",
+                q.number_of_workouts_needed,
+                q.number_of_workouts_completed,
+                q.requirements as "requirements: Json<Vec<QuestRequirement>>",
+                q.goal as "goal: Json<QuestGoal>",
+                q.progress,
+                q.campaign_step_id,
+                q.user_chest_id,
+                COALESCE(q.expires_at < NOW(), FALSE) as "expired!",
+                p.delta
+            FROM quest_progress p
+            JOIN quests q ON q.id = p.quest_id
+            WHERE p.history_id = $1 AND q.user_id = $2
+            ORDER BY q.id
+            FOR UPDATE OF q
+        "#,
+        history_id,
+        user.id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
+
+    let mut revoked = Vec::new();
+    let mut campaign_step_completed = false;
+    for quest in quests {
+        // What the workout adds now, None when it no longer counts towards the quest
+        let contribution = context
+            .filter(|context| {
+                quest
+                    .requirements
+                    .iter()
+                    .all(|requirement| requirement.is_met(context))
+            })
+            .map(|context| match &quest.goal {
+                Some(goal) => goal.measure(context),
+                None => 0.0,
+            })
+            .filter(|delta| quest.goal.is_none() || *delta > 0.0);
+
+        let (number_of_workouts_completed, progress) = match contribution {
+            Some(delta) => (
+                quest.number_of_workouts_completed,
+                quest.progress - quest.delta + delta,
+            ),
+            None => (
+                quest.number_of_workouts_completed - 1,
+                quest.progress - quest.delta,
+            ),
+        };
+        let progress = progress.max(0.0);
+
+        let complete = match &quest.goal {
+            Some(goal) => progress >= goal.target,
+            None => number_of_workouts_completed >= quest.number_of_workouts_needed,
+        };
+
+        // Abandoned quests and quests that expired before completing stay that way
+        let status = match (quest.status, complete) {
+            (QuestStatus::Incomplete, true) => QuestStatus::Complete,
+            (QuestStatus::Complete | QuestStatus::Claimed, false) if quest.expired => {
+                QuestStatus::Expired
+            }
+            (QuestStatus::Complete | QuestStatus::Claimed, false) => QuestStatus::Incomplete,
+            (status, _) => status,
+        };
+
+        if quest.status == QuestStatus::Claimed && status != QuestStatus::Claimed {
+            revoked.push(RevokedQuest {
+                difficulty: quest.difficulty,
+                user_chest_id: quest.user_chest_id,
+            });
+        }
+        campaign_step_completed |=
+            status == QuestStatus::Complete && quest.campaign_step_id.is_some();
+
+        let _query = sqlx::query!(
             r#"
-                SELECT primary_muscles as "primary_muscles: Vec<ExerciseMuscle>", secondary_muscles as "secondary_muscles: Vec<ExerciseMuscle>"
-                FROM exercises
-                WHERE id = ANY($1);
+                UPDATE quests
+                SET number_of_workouts_completed = $2, progress = $3, status = $4::quest_status,
+                    claimed_at = CASE WHEN $4::quest_status = 'claimed' THEN claimed_at END,
+                    user_chest_id = CASE WHEN $4::quest_status = 'claimed' THEN user_chest_id END
+                WHERE id = $1
             "#,
-            &ids
+            quest.id,
+            number_of_workouts_completed,
+            progress,
+            status as QuestStatus
         )
-        .fetch_all(pool.get_ref())
+        .execute(&mut *tx)
         .await
         .unwrap();
 
-        let primary_muscles = query.iter().flat_map(|record| &record.primary_muscles);
-        let secondary_muscles = query.iter().flat_map(|record| &record.secondary_muscles);
+        match contribution {
+            Some(delta) => sqlx::query!(
+                r#"
+                    UPDATE quest_progress
+                    SET delta = $3
+                    WHERE quest_id = $1 AND history_id = $2
+                "#,
+                quest.id,
+                history_id,
+                delta
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap(),
+            None => sqlx::query!(
+                r#"
+                    DELETE FROM quest_progress
+                    WHERE quest_id = $1 AND history_id = $2
+                "#,
+                quest.id,
+                history_id
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap(),
+        };
+    }
 
-        flag &= primary_muscles
-            .chain(secondary_muscles)
-            .collect::<Vec<&ExerciseMuscle>>()
-            .contains(&exercise_muscle);
+    if campaign_step_completed {
+        advance_campaigns(&mut tx, user.id, None).await.unwrap();
     }
-    return flag;
+    reconcile_group_quest_progress(&mut tx, user.id, history_id, context).await;
+    tx.commit().await.unwrap();
+
+    return revoked;
 }
@@ -0,0 +1,553 @@
+use {
+    crate::utils::{
+        inventory::{
+            Holdings,
+            TransferItem,
+            fetch_transfer_items,
+            lock_holdings_pair,
+            save_holdings,
+            transfer,
+        },
+        jwt::AuthenticatedUser,
+        schemas::TradeStatus,
+    },
+    actix_web::{
+        HttpResponse,
+        delete,
+        get,
+        post,
+        web,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::{
+        PgConnection,
+        PgPool,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct SendGiftRequest {
+    pub recipient_id: i32,
+    #[serde(default)]
+    pub item_ids: Vec<i32>,
+    #[serde(default)]
+    pub coins: i32,
+}
+
+#[derive(Deserialize)]
+pub struct ProposeTradeRequest {
+    pub recipient_id: i32,
+    #[serde(default)]
+    pub offered_items: Vec<i32>,
+    #[serde(default)]
+    pub offered_coins: i32,
+    #[serde(default)]
+    pub requested_items: Vec<i32>,
+    #[serde(default)]
+    pub requested_coins: i32,
+}
+
+#[derive(Deserialize)]
+pub struct RespondToTradeRequest {
+    pub trade_id: i32,
+    pub accept: bool,
+}
+
+#[derive(Serialize)]
+pub struct TradeOffer {
+    pub trade_id: i32,
+    pub user_id: i32, // User ID of the other side of the trade
+    pub username: String,
+    pub offered_items: Vec<i32>, // Given by the sender
+    pub offered_coins: i32,
+    pub requested_items: Vec<i32>, // Given by the recipient
+    pub requested_coins: i32,
+    pub created: chrono::NaiveDateTime,
+}
+
+pub async fn are_friends(conn: &mut PgConnection, user_id: i32, friend_id: i32) -> Result<bool, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM characters
+            WHERE user_id = $1 AND $2 = ANY(friends)
+        ) as "is_friend!"
+        "#,
+        user_id,
+        friend_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(query.is_friend)
+}
+
+// Looks up the items being transferred, rejecting unknown or repeated ids
+async fn resolve_items(
+    conn: &mut PgConnection,
+    item_ids: &[i32],
+) -> Result<Vec<TransferItem>, HttpResponse> {
+    let mut unique_ids = item_ids.to_vec();
+    unique_ids.sort_unstable();
+    unique_ids.dedup();
+
+    if unique_ids.len() != item_ids.len() {
+        return Err(HttpResponse::BadRequest().body("Items can only be listed once"));
+    }
+
+    match fetch_transfer_items(conn, item_ids).await {
+        Ok(items) if items.len() == item_ids.len() => Ok(items),
+        Ok(_) => Err(HttpResponse::NotFound().body("Item not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Failed to fetch items")),
+    }
+}
+
+// Applies both sides of a trade, the sender's offer first and then the recipient's side
+fn apply_trade(
+    sender: &mut Holdings,
+    recipient: &mut Holdings,
+    offered_items: &[TransferItem],
+    offered_coins: i32,
+    requested_items: &[TransferItem],
+    requested_coins: i32,
+) -> Result<(), &'static str> {
+    transfer(sender, recipient, offered_items, offered_coins)?;
+    transfer(recipient, sender, requested_items, requested_coins)?;
+    Ok(())
+}
+
+// Give items and/or coins to a friend
+#[post("/social/gifts")]
+pub async fn send_gift(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    req: web::Json<SendGiftRequest>,
+) -> HttpResponse {
+    let recipient_id = req.recipient_id;
+
+    if recipient_id == user.id {
+        return HttpResponse::BadRequest().body("Cannot send a gift to yourself");
+    }
+
+    if req.item_ids.is_empty() && req.coins == 0 {
+        return HttpResponse::BadRequest().body("A gift must contain items or coins");
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to start transaction"),
+    };
+
+    match are_friends(&mut tx, user.id, recipient_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::Forbidden().body("User is not in your friends list");
+        }
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to verify friendship");
+        }
+    }
+
+    let items = match resolve_items(&mut tx, &req.item_ids).await {
+        Ok(items) => items,
+        Err(response) => {
+            let _ = tx.rollback().await;
+            return response;
+        }
+    };
+
+    let (mut sender, mut recipient) = match lock_holdings_pair(&mut tx, user.id, recipient_id).await {
+        Ok(pair) => pair,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch user data");
+        }
+    };
+
+    if let Err(error) = transfer(&mut sender, &mut recipient, &items, req.coins) {
+        let _ = tx.rollback().await;
+        return HttpResponse::BadRequest().body(error);
+    }
+
+    if save_holdings(&mut tx, &sender).await.is_err()
+        || save_holdings(&mut tx, &recipient).await.is_err()
+    {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update user data");
+    }
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().body("Failed to commit transaction");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Gift sent",
+        "item_ids": req.item_ids,
+        "coins": req.coins,
+        "remaining_coins": sender.coins
+    }))
+}
+
+// Propose a two-sided trade to a friend
+#[post("/social/trades")]
+pub async fn propose_trade(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    req: web::Json<ProposeTradeRequest>,
+) -> HttpResponse {
+    let recipient_id = req.recipient_id;
+
+    if recipient_id == user.id {
+        return HttpResponse::BadRequest().body("Cannot trade with yourself");
+    }
+
+    if req.offered_items.is_empty()
+        && req.offered_coins == 0
+        && req.requested_items.is_empty()
+        && req.requested_coins == 0
+    {
+        return HttpResponse::BadRequest().body("A trade must contain items or coins");
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to start transaction"),
+    };
+
+    match are_friends(&mut tx, user.id, recipient_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::Forbidden().body("User is not in your friends list");
+        }
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to verify friendship");
+        }
+    }
+
+    let offered_items = match resolve_items(&mut tx, &req.offered_items).await {
+        Ok(items) => items,
+        Err(response) => {
+            let _ = tx.rollback().await;
+            return response;
+        }
+    };
+
+    let requested_items = match resolve_items(&mut tx, &req.requested_items).await {
+        Ok(items) => items,
+        Err(response) => {
+            let _ = tx.rollback().await;
+            return response;
+        }
+    };
+
+    let (mut sender, mut recipient) = match lock_holdings_pair(&mut tx, user.id, recipient_id).await {
+        Ok(pair) => pair,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch user data");
+        }
+    };
+
+    // Dry run so that impossible trades are rejected up front, it is validated again on acceptance
+    if let Err(error) = apply_trade(
+        &mut sender,
+        &mut recipient,
+        &offered_items,
+        req.offered_coins,
+        &requested_items,
+        req.requested_coins,
+    ) {
+        let _ = tx.rollback().await;
+        return HttpResponse::BadRequest().body(error);
+    }
+
+    let insert_result = sqlx::query!(
+        r#"
+        INSERT INTO trades (sender_id, recipient_id, offered_items, offered_coins, requested_items, requested_coins)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        user.id,
+        recipient_id,
+        &req.offered_items,
+        req.offered_coins,
+        &req.requested_items,
+        req.requested_coins
+    )
+    .fetch_one(&mut *tx)
+    .await;
+
+    let trade_id = match insert_result {
+        Ok(query) => query.id,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to create trade");
+        }
+    };
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().body("Failed to commit transaction");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Trade proposed",
+        "trade_id": trade_id
+    }))
+}
+
+// Get pending trades waiting on the user's answer
+#[get("/social/trades/incoming")]
+pub async fn get_incoming_trades(user: AuthenticatedUser, pool: web::Data<PgPool>) -> HttpResponse {
+    let trades_result = sqlx::query!(
+        r#"
+        SELECT t.id, t.sender_id, t.offered_items, t.offered_coins,
+               t.requested_items, t.requested_coins, t.created, c.username
+        FROM trades t
+        JOIN characters c ON t.sender_id = c.user_id
+        WHERE t.recipient_id = $1 AND t.status = 'pending'
+        ORDER BY t.created DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match trades_result {
+        Ok(queries) => {
+            let trades: Vec<TradeOffer> = queries
+                .into_iter()
+                .map(|q| TradeOffer {
+                    trade_id: q.id,
+                    user_id: q.sender_id,
+                    username: q.username,
+                    offered_items: q.offered_items,
+                    offered_coins: q.offered_coins,
+                    requested_items: q.requested_items,
+                    requested_coins: q.requested_coins,
+                    created: q.created,
+                })
+                .collect();
+            HttpResponse::Ok().json(trades)
+        }
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch trades"),
+    }
+}
+
+// Get pending trades the user has proposed
+#[get("/social/trades/outgoing")]
+pub async fn get_outgoing_trades(user: AuthenticatedUser, pool: web::Data<PgPool>) -> HttpResponse {
+    let trades_result = sqlx::query!(
+        r#"
+        SELECT t.id, t.recipient_id, t.offered_items, t.offered_coins,
+               t.requested_items, t.requested_coins, t.created, c.username
+        FROM trades t
+        JOIN characters c ON t.recipient_id = c.user_id
+        WHERE t.sender_id = $1 AND t.status = 'pending'
+        ORDER BY t.created DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match trades_result {
+        Ok(queries) => {
+            let trades: Vec<TradeOffer> = queries
+                .into_iter()
+                .map(|q| TradeOffer {
+                    trade_id: q.id,
+                    user_id: q.recipient_id,
+                    username: q.username,
+                    offered_items: q.offered_items,
+                    offered_coins: q.offered_coins,
+                    requested_items: q.requested_items,
+                    requested_coins: q.requested_coins,
+                    created: q.created,
+                })
+                .collect();
+            HttpResponse::Ok().json(trades)
+        }
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch trades"),
+    }
+}
+
+// Accept or reject a trade, accepting swaps both sides in a single transaction
+#[post("/social/trades/respond")]
+pub async fn respond_to_trade(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    req: web::Json<RespondToTradeRequest>,
+) -> HttpResponse {
+    let trade_id = req.trade_id;
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to start transaction"),
+    };
+
+    // Lock the trade so it can only be resolved once
+    let trade_result = sqlx::query!(
+        r#"
+        SELECT sender_id, recipient_id, offered_items, offered_coins, requested_items, requested_coins
+        FROM trades
+        WHERE id = $1 AND status = 'pending'
+        FOR UPDATE
+        "#,
+        trade_id
+    )
+    .fetch_optional(&mut *tx)
+    .await;
+
+    let trade = match trade_result {
+        Ok(Some(trade)) => {
+            if trade.recipient_id != user.id {
+                let _ = tx.rollback().await;
+                return HttpResponse::Forbidden().body("This trade is not for you");
+            }
+            trade
+        }
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("Trade not found");
+        }
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch trade");
+        }
+    };
+
+    let status = if req.accept {
+        TradeStatus::Accepted
+    } else {
+        TradeStatus::Rejected
+    };
+
+    if req.accept {
+        match are_friends(&mut tx, trade.sender_id, trade.recipient_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                let _ = tx.rollback().await;
+                return HttpResponse::Forbidden().body("User is not in your friends list");
+            }
+            Err(_) => {
+                let _ = tx.rollback().await;
+                return HttpResponse::InternalServerError().body("Failed to verify friendship");
+            }
+        }
+
+        let offered_items = match resolve_items(&mut tx, &trade.offered_items).await {
+            Ok(items) => items,
+            Err(response) => {
+                let _ = tx.rollback().await;
+                return response;
+            }
+        };
+
+        let requested_items = match resolve_items(&mut tx, &trade.requested_items).await {
+            Ok(items) => items,
+            Err(response) => {
+                let _ = tx.rollback().await;
+                return response;
+            }
+        };
+
+        let (mut sender, mut recipient) =
+            match lock_holdings_pair(&mut tx, trade.sender_id, trade.recipient_id).await {
+                Ok(pair) => pair,
+                Err(_) => {
+                    let _ = tx.rollback().await;
+                    return HttpResponse::InternalServerError().body("Failed to fetch user data");
+                }
+            };
+
+        // Inventories may have changed since the trade was proposed
+        if let Err(error) = apply_trade(
+            &mut sender,
+            &mut recipient,
+            &offered_items,
+            trade.offered_coins,
+            &requested_items,
+            trade.requested_coins,
+        ) {
+            let _ = tx.rollback().await;
+            return HttpResponse::BadRequest().body(error);
+        }
+
+        if save_holdings(&mut tx, &sender).await.is_err()
+            || save_holdings(&mut tx, &recipient).await.is_err()
+        {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to update user data");
+        }
+    }
+
+    let update_result = sqlx::query!(
+        r#"
+        UPDATE trades
+        SET status = $2, resolved = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+        trade_id,
+        status as TradeStatus
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if update_result.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update trade");
+    }
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().body("Failed to commit transaction");
+    }
+
+    let message = if req.accept {
+        "Trade accepted"
+    } else {
+        "Trade rejected"
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": message
+    }))
+}
+
+// Cancel a trade the user proposed
+#[delete("/social/trades/{id}")]
+pub async fn cancel_trade(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    trade_id: web::Path<i32>,
+) -> HttpResponse {
+    let trade_id = trade_id.into_inner();
+
+    let update_result = sqlx::query!(
+        r#"
+        UPDATE trades
+        SET status = 'cancelled', resolved = CURRENT_TIMESTAMP
+        WHERE id = $1 AND sender_id = $2 AND status = 'pending'
+        "#,
+        trade_id,
+        user.id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match update_result {
+        Ok(result) if result.rows_affected() == 0 => {
+            HttpResponse::NotFound().body("Trade not found")
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Trade cancelled"
+        })),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to cancel trade"),
+    }
+}
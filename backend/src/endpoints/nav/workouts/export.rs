@@ -0,0 +1,532 @@
+use {
+    crate::utils::{
+        jwt::AuthenticatedUser,
+        schedule::is_scheduled,
+        schemas::{
+            Exercise,
+            SetType,
+        },
+    },
+    actix_web::{
+        HttpResponse,
+        Result,
+        get,
+        http::header::{
+            ContentDisposition,
+            DispositionParam,
+            DispositionType,
+        },
+        web,
+    },
+    chrono::{
+        DateTime,
+        NaiveDate,
+        NaiveDateTime,
+        TimeDelta,
+        Utc,
+    },
+    csv::Writer,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::{
+        PgPool,
+        types::Json,
+    },
+    std::collections::{
+        HashMap,
+        HashSet,
+    },
+};
+
+// Bumped when the JSON export changes in a way older imports can't read
+pub const EXPORT_VERSION: i32 = 1;
+
+// Lines of iCalendar files are folded at 75 octets
+const ICS_LINE_LENGTH: usize = 75;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,  // one row per set, in the generic format of the CSV import
+    Json, // everything, imported back with POST /workouts/history/import/json
+    Ics,  // one calendar event per workout
+}
+
+#[derive(Deserialize)]
+pub struct ExportHistoryQuery {
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub missed: bool, // adds the scheduled days without a workout to the calendar
+}
+
+// A workout of the history as it was logged
+#[derive(Deserialize, Serialize)]
+pub struct ExportedWorkout {
+    pub name: String,
+    pub exercises: Json<Vec<Exercise>>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub timezone: String,
+    pub points: i32, // exp
+    pub coins: i32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ExportedRoutine {
+    pub name: String,
+    pub exercises: Json<Vec<Exercise>>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HistoryExport {
+    pub version: i32,
+    pub exported_at: DateTime<Utc>,
+    pub history: Vec<ExportedWorkout>,
+    pub routines: Vec<ExportedRoutine>,
+}
+
+struct ExportRow {
+    id: i32,
+    workout: ExportedWorkout,
+    date: NaiveDate,
+    // In the timezone of the user's settings, the CSV import reads times in it
+    local_started_at: NaiveDateTime,
+    local_ended_at: NaiveDateTime,
+}
+
+#[get("/workouts/history/export")]
+pub async fn export_history(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<ExportHistoryQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let rows: Vec<ExportRow> = sqlx::query!(
+        r#"
+            SELECT h.id, h.name, h.exercises as "exercises: Json<Vec<Exercise>>",
+                h.started_at, h.ended_at, h.timezone, h.date, h.points, h.coins,
+                h.started_at AT TIME ZONE s.timezone as "local_started_at!",
+                h.ended_at AT TIME ZONE s.timezone as "local_ended_at!"
+            FROM history h
+            JOIN settings s ON s.user_id = h.user_id
+            WHERE h.user_id = $1
+            ORDER BY h.started_at, h.id
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|row| ExportRow {
+        id: row.id,
+        workout: ExportedWorkout {
+            name: row.name,
+            exercises: row.exercises,
+            started_at: row.started_at,
+            ended_at: row.ended_at,
+            timezone: row.timezone,
+            points: row.points,
+            coins: row.coins,
+        },
+        date: row.date,
+        local_started_at: row.local_started_at,
+        local_ended_at: row.local_ended_at,
+    })
+    .collect();
+
+    let (body, content_type, extension) = match query.format {
+        ExportFormat::Csv => (export_csv(&rows), "text/csv; charset=utf-8", "csv"),
+        ExportFormat::Json => {
+            let routines = sqlx::query_as!(
+                ExportedRoutine,
+                r#"
+                    SELECT name, exercises as "exercises: Json<Vec<Exercise>>"
+                    FROM routines
+                    WHERE user_id = $1
+                    ORDER BY id
+                "#,
+                user.id
+            )
+            .fetch_all(pool.get_ref())
+            .await
+            .unwrap();
+
+            let export = HistoryExport {
+                version: EXPORT_VERSION,
+                exported_at: Utc::now(),
+                history: rows.into_iter().map(|row| row.workout).collect(),
+                routines,
+            };
+            (serde_json::to_string(&export).unwrap(), "application/json", "json")
+        }
+        ExportFormat::Ics => {
+            let missed = match query.missed {
+                true => missed_days(&pool, user.id, &rows).await,
+                false => Vec::new(),
+            };
+            let names = exercise_names(&pool, &rows).await;
+            (export_ics(&rows, &missed, &names), "text/calendar; charset=utf-8", "ics")
+        }
+    };
+
+    return Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("history.{}", extension))],
+        })
+        .body(body));
+}
+
+// Same columns as the generic CSV import so the file can be imported back. The cardio details of
+// activity files are only kept in the JSON export.
+fn export_csv(rows: &[ExportRow]) -> String {
+    let mut writer = Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            "workout", "started_at", "ended_at", "exercise", "set_type", "reps", "weight",
+            "distance", "duration", "rpe", "rest",
+        ])
+        .unwrap();
+
+    let time = |time: &NaiveDateTime| time.format("%Y-%m-%d %H:%M:%S").to_string();
+    for row in rows {
+        for exercise in row.workout.exercises.iter() {
+            for set in &exercise.sets {
+                let set_type = match set.set_type {
+                    SetType::Warmup => "warmup",
+                    SetType::Working => "working",
+                    SetType::Drop => "drop",
+                    SetType::Failure => "failure",
+                };
+                writer
+                    .write_record([
+                        row.workout.name.clone(),
+                        time(&row.local_started_at),
+                        time(&row.local_ended_at),
+                        exercise.id.clone(),
+                        set_type.to_string(),
+                        set.reps.to_string(),
+                        set.weight.to_string(),
+                        set.distance.to_string(),
+                        set.duration.to_string(),
+                        set.rpe.map_or(String::new(), |rpe| rpe.to_string()),
+                        set.rest.map_or(String::new(), |rest| rest.to_string()),
+                    ])
+                    .unwrap();
+            }
+        }
+    }
+
+    return String::from_utf8(writer.into_inner().unwrap()).unwrap();
+}
+
+async fn missed_days(pool: &PgPool, user_id: i32, rows: &[ExportRow]) -> Vec<NaiveDate> {
+    let settings = sqlx::query!(
+        r#"
+            SELECT (NOW() AT TIME ZONE timezone)::date as "today!", workout_schedule
+            FROM settings
+            WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    let dates: Vec<NaiveDate> = rows.iter().map(|row| row.date).collect();
+    return missed(&dates, settings.today, &settings.workout_schedule);
+}
+
+// Scheduled days from the first workout until yesterday without a workout, today is not over yet
+fn missed(dates: &[NaiveDate], today: NaiveDate, workout_schedule: &[bool]) -> Vec<NaiveDate> {
+    let Some(first) = dates.iter().min() else {
+        return Vec::new();
+    };
+    let workout_days: HashSet<&NaiveDate> = dates.iter().collect();
+
+    return first
+        .iter_days()
+        .take_while(|day| *day < today)
+        .filter(|day| is_scheduled(workout_schedule, *day) && !workout_days.contains(day))
+        .collect();
+}
+
+async fn exercise_names(pool: &PgPool, rows: &[ExportRow]) -> HashMap<String, String> {
+    let ids: Vec<String> = rows
+        .iter()
+        .flat_map(|row| row.workout.exercises.iter())
+        .map(|exercise| exercise.id.clone())
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect();
+
+    return sqlx::query!(
+        r#"
+            SELECT id, name
+            FROM exercises
+            WHERE id = ANY($1)
+        "#,
+        &ids
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|exercise| (exercise.id, exercise.name))
+    .collect();
+}
+
+fn export_ics(
+    rows: &[ExportRow],
+    missed: &[NaiveDate],
+    names: &HashMap<String, String>,
+) -> String {
+    let stamp = ics_time(&Utc::now());
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//Gainz//Workout history//EN"),
+        String::from("CALSCALE:GREGORIAN"),
+        String::from("X-WR-CALNAME:Workouts"),
+    ];
+
+    for row in rows {
+        let description: Vec<String> = row
+            .workout
+            .exercises
+            .iter()
+            .map(|exercise| {
+                let name = names.get(&exercise.id).unwrap_or(&exercise.id);
+                match (exercise.distance() > 0.0, exercise.working_sets().count()) {
+                    (true, _) => format!("{}: {:.2} km", name, exercise.distance()),
+                    (false, 1) => format!("{}: 1 set", name),
+                    (false, sets) => format!("{}: {} sets", name, sets),
+                }
+            })
+            .collect();
+
+        lines.extend([
+            String::from("BEGIN:VEVENT"),
+            format!("UID:workout-{}@gainz", row.id),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART:{}", ics_time(&row.workout.started_at)),
+            format!("DTEND:{}", ics_time(&row.workout.ended_at)),
+            format!("SUMMARY:{}", ics_text(&row.workout.name)),
+            format!("DESCRIPTION:{}", ics_text(&description.join("\n"))),
+            String::from("CATEGORIES:Workout"),
+            String::from("END:VEVENT"),
+        ]);
+    }
+
+    // All day events that don't block the calendar
+    for day in missed {
+        lines.extend([
+            String::from("BEGIN:VEVENT"),
+            format!("UID:missed-{}@gainz", day.format("%Y%m%d")),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", (*day + TimeDelta::days(1)).format("%Y%m%d")),
+            String::from("SUMMARY:Missed workout"),
+            String::from("TRANSP:TRANSPARENT"),
+            String::from("CATEGORIES:Missed workout"),
+            String::from("END:VEVENT"),
+        ]);
+    }
+
+    lines.push(String::from("END:VCALENDAR"));
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold(&line));
+        ics.push_str("\r\n");
+    }
+    return ics;
+}
+
+fn ics_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Long lines continue on the next one after a space, without splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > ICS_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    return folded;
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            endpoints::nav::workouts::import::{
+                Columns,
+                ImportFormat,
+                read_set,
+            },
+            utils::schemas::{
+                ExerciseSet,
+                WeightUnit,
+            },
+        },
+        csv::{
+            ReaderBuilder,
+            Trim,
+        },
+    };
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn unfold(folded: &str) -> String {
+        folded.replace("\r\n ", "")
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets_between_characters() {
+        let line = format!("DESCRIPTION:{}", "Développé couché 💪, ".repeat(10));
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").count() > 1);
+        assert!(
+            folded
+                .split("\r\n")
+                .all(|part| part.len() <= ICS_LINE_LENGTH)
+        );
+        assert!(
+            folded
+                .split("\r\n")
+                .skip(1)
+                .all(|part| part.starts_with(' '))
+        );
+        assert_eq!(unfold(&folded), line);
+    }
+
+    #[test]
+    fn short_lines_are_left_as_they_are() {
+        let line = "X".repeat(ICS_LINE_LENGTH);
+        assert_eq!(fold(&line), line);
+        assert_eq!(fold(&format!("{}é", line)), format!("{}\r\n é", line));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            ics_text("Push, pull; legs\\arms\nCore"),
+            r"Push\, pull\; legs\\arms\nCore"
+        );
+    }
+
+    // Sunday first, like the settings
+    const WEEKDAYS: [bool; 7] = [false, true, false, true, false, true, false];
+
+    #[test]
+    fn missed_days_are_scheduled_days_without_a_workout_until_yesterday() {
+        // Mondays, Wednesdays and Fridays: 5, 7, 9, 12 and 14
+        assert_eq!(
+            missed(&[day(7), day(8)], day(14), &WEEKDAYS),
+            [day(9), day(12)]
+        );
+        assert_eq!(missed(&[day(7), day(12)], day(12), &WEEKDAYS), [day(9)]);
+        assert_eq!(missed(&[], day(14), &WEEKDAYS), []);
+    }
+
+    #[test]
+    fn csv_exports_are_read_back_by_the_generic_import() {
+        let sets = vec![
+            ExerciseSet {
+                reps: 10,
+                weight: 95.5,
+                set_type: SetType::Warmup,
+                ..ExerciseSet::default()
+            },
+            ExerciseSet {
+                reps: 5,
+                weight: 225.0,
+                rpe: Some(8.5),
+                rest: Some(180),
+                ..ExerciseSet::default()
+            },
+            ExerciseSet {
+                distance: 5.25,
+                duration: 1800,
+                set_type: SetType::Failure,
+                ..ExerciseSet::default()
+            },
+        ];
+        let started_at = day(5).and_hms_opt(7, 30, 0).unwrap();
+        let ended_at = day(5).and_hms_opt(8, 45, 0).unwrap();
+        let row = ExportRow {
+            id: 1,
+            workout: ExportedWorkout {
+                name: String::from("Legs, \"heavy\""),
+                exercises: Json(vec![Exercise {
+                    id: String::from("Barbell_Squat"),
+                    sets: sets.clone(),
+                }]),
+                started_at: started_at.and_utc(),
+                ended_at: ended_at.and_utc(),
+                timezone: String::from("UTC"),
+                points: 0,
+                coins: 0,
+            },
+            date: day(5),
+            local_started_at: started_at,
+            local_ended_at: ended_at,
+        };
+        let csv = export_csv(&[row]);
+
+        let mut reader = ReaderBuilder::new()
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(csv.as_bytes());
+        let columns = Columns::new(reader.headers().unwrap());
+        let imported: Vec<_> = reader
+            .records()
+            .map(|record| {
+                read_set(
+                    ImportFormat::Generic,
+                    WeightUnit::Lbs,
+                    &columns,
+                    &record.unwrap(),
+                )
+                .unwrap()
+                .unwrap()
+            })
+            .collect();
+
+        assert_eq!(imported.len(), sets.len());
+        for (imported, set) in imported.iter().zip(&sets) {
+            assert_eq!(imported.workout, "Legs, \"heavy\"");
+            assert_eq!(imported.started_at, started_at);
+            assert_eq!(imported.ended_at, Some(ended_at));
+            assert_eq!(imported.exercise, "Barbell_Squat");
+            assert_eq!(imported.set.reps, set.reps);
+            assert_eq!(imported.set.weight, set.weight);
+            assert_eq!(imported.set.distance, set.distance);
+            assert_eq!(imported.set.duration, set.duration);
+            assert_eq!(imported.set.rpe, set.rpe);
+            assert_eq!(imported.set.set_type, set.set_type);
+            assert_eq!(imported.set.rest, set.rest);
+        }
+    }
+}
@@ -1,33 +1,59 @@
 use {
     crate::{
-        endpoints::nav::quests::apply_workout_to_quests,
+        endpoints::nav::quests::{
+            RevokedQuest,
+            apply_workout_to_quests,
+            generate_quest_boards,
+            reconcile_quest_progress,
+        },
         utils::{
             coins::add_coins,
+            idempotency::idempotency,
             jwt::AuthenticatedUser,
             level::add_exp,
+            requirements::WorkoutContext,
+            rollups::{
+                lock_rollups,
+                update_rollups,
+            },
             schemas::{
                 Exercise,
+                ExerciseCategory,
+                ExerciseMuscle,
                 History,
             },
+            streak::update_streak,
         },
     },
     actix_web::{
         HttpResponse,
         Result,
+        delete,
+        error::{
+            ErrorBadRequest,
+            ErrorNotFound,
+        },
         get,
+        middleware::from_fn,
         post,
+        put,
         web,
     },
+    chrono::{
+        DateTime,
+        NaiveDate,
+        SecondsFormat,
+        TimeDelta,
+        Utc,
+    },
     serde::{
         Deserialize,
         Serialize,
     },
     sqlx::{
+        PgConnection,
         PgPool,
-        types::{
-            Json,
-            chrono::NaiveDate,
-        },
+        types::Json,
     },
 };
 
@@ -35,66 +61,437 @@ use {
 pub struct CreateHistoryRequest {
     pub name: String,
     pub exercises: Json<Vec<Exercise>>,
-    pub date: NaiveDate,
-    // pub time: NaiveTime,
-    pub duration: i32,
-    pub points: i32, // exp
+    pub started_at: Option<DateTime<Utc>>, // with an offset, e.g. 2026-10-18T07:30:00-04:00
+    pub ended_at: Option<DateTime<Utc>>,
+    pub date: Option<NaiveDate>, // older clients only send the day and the duration
+    pub duration: Option<i32>,   // minutes, ignored when ended_at is given
+    pub points: i32,             // exp
     pub coins: i32,
 }
 
-#[post("/workouts/history")]
+impl CreateHistoryRequest {
+    // When the workout started and ended, once its sets are valid. Workouts logged with only a day end now if
+    // the day is today in the user's timezone, and are placed at noon otherwise.
+    async fn session(
+        &self,
+        pool: &PgPool,
+        user_id: i32,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>), actix_web::Error> {
+        for exercise in self.exercises.iter() {
+            exercise.validate().map_err(ErrorBadRequest)?;
+        }
+        if self.duration.is_some_and(|duration| duration < 0) {
+            return Err(ErrorBadRequest("Duration can't be negative"));
+        }
+
+        let started_at = match (self.started_at, self.date, self.duration) {
+            (Some(started_at), _, _) => started_at,
+            (None, Some(date), Some(duration)) => sqlx::query_scalar!(
+                r#"
+                    SELECT CASE
+                        WHEN $2 = (NOW() AT TIME ZONE timezone)::date
+                            THEN GREATEST(NOW() - make_interval(mins => $3), $2::timestamp AT TIME ZONE timezone)
+                        ELSE ($2 + TIME '12:00') AT TIME ZONE timezone
+                    END as "started_at!"
+                    FROM settings
+                    WHERE user_id = $1
+                "#,
+                user_id,
+                date,
+                duration,
+            )
+            .fetch_one(pool)
+            .await
+            .unwrap(),
+            _ => return Err(ErrorBadRequest("Either started_at or date and duration are required")),
+        };
+
+        let ended_at = match (self.ended_at, self.duration) {
+            (Some(ended_at), _) => ended_at,
+            (None, Some(duration)) => started_at + TimeDelta::minutes(duration as i64),
+            (None, None) => return Err(ErrorBadRequest("Either ended_at or duration is required")),
+        };
+
+        if ended_at < started_at {
+            return Err(ErrorBadRequest("A workout can't end before it starts"));
+        }
+
+        return Ok((started_at, ended_at));
+    }
+}
+
+// Logs a workout and gives everything it earns
+pub async fn log_workout(
+    user: AuthenticatedUser,
+    pool: &web::Data<PgPool>,
+    request: &CreateHistoryRequest,
+) -> Result<History, actix_web::Error> {
+    let (started_at, ended_at) = request.session(pool, user.id).await?;
+    generate_quest_boards(pool, user.id).await;
+
+    // A workout is only kept together with everything it gives, so a failed request can be retried
+    let mut tx = pool.begin().await.unwrap();
+    let history = insert_history(&mut tx, user.id, request, started_at, ended_at)
+        .await
+        .unwrap();
+    reward_workout(&user, &mut tx, &history).await;
+    tx.commit().await.unwrap();
+
+    return Ok(history);
+}
+
+// Stores a workout in the history without giving anything for it
+pub async fn insert_history(
+    conn: &mut PgConnection,
+    user_id: i32,
+    request: &CreateHistoryRequest,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+) -> Result<History, sqlx::Error> {
+    // The day and duration are derived from the timestamps by the database
+    let history = sqlx::query_as!(
+        History,
+        r#"
+            INSERT INTO history (user_id, name, exercises, started_at, ended_at, timezone, points, coins)
+            SELECT $1, $2, $3, $4, $5, timezone, $6, $7
+            FROM settings
+            WHERE user_id = $1
+            RETURNING id, name, exercises as "exercises: Json<Vec<Exercise>>", started_at, ended_at, timezone,
+                date, duration, points, coins
+        "#,
+        user_id,
+        request.name,
+        serde_json::to_value(&request.exercises.0).unwrap(),
+        started_at,
+        ended_at,
+        request.points,
+        request.coins,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    update_rollups(conn, user_id, &[history.date]).await?;
+
+    return Ok(history);
+}
+
+// Gives everything a logged workout earns: its rewards, quest progress and the streak. Runs in the
+// transaction that inserted the workout, after the quest boards were generated.
+pub async fn reward_workout(user: &AuthenticatedUser, conn: &mut PgConnection, history: &History) {
+    let context = WorkoutContext::load(conn, user.id, history).await.unwrap();
+    apply_workout_to_quests(user, conn, history, &context).await;
+    add_exp(user, conn, history.points).await.unwrap();
+    add_coins(user, conn, history.coins).await.unwrap();
+    update_streak(conn, user.id).await.unwrap();
+}
+
+#[post("/workouts/history", wrap = "from_fn(idempotency)")]
 pub async fn create_history(
     user: AuthenticatedUser,
     pool: web::Data<PgPool>,
     request: web::Json<CreateHistoryRequest>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    log_workout(user, &pool, &request).await?;
+
+    return Ok(HttpResponse::Ok().finish());
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct ReadHistoryQuery {
+    pub cursor: Option<String>, // next_cursor of the previous page
+    pub limit: Option<i64>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>, // inclusive
+    pub name: Option<String>,  // part of the routine name, case insensitive
+    pub exercise_id: Option<String>,
+    pub category: Option<ExerciseCategory>,
+    pub muscle: Option<ExerciseMuscle>, // primary or secondary
+    #[serde(default)]
+    pub summary: bool, // leaves out the exercises
+}
+
+// Workout in the history, without exercises in summary mode
+#[derive(Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub id: i32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exercises: Option<Json<Vec<Exercise>>>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub timezone: String,
+    pub date: NaiveDate,
+    pub duration: i32,
+    pub points: i32,
+    pub coins: i32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ReadHistoryResponse {
+    history: Vec<HistoryEntry>,
+    next_cursor: Option<String>, // None on the last page
+}
+
+// Cursors point at the last workout of a page as "started_at_id", pages are ordered newest first
+fn parse_cursor(cursor: &str) -> Option<(DateTime<Utc>, i32)> {
+    let (started_at, id) = cursor.rsplit_once('_')?;
+    return Some((started_at.parse().ok()?, id.parse().ok()?));
+}
+
+#[get("/workouts/history")]
+pub async fn read_history(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<ReadHistoryQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let cursor = match &query.cursor {
+        Some(cursor) => match parse_cursor(cursor) {
+            Some(cursor) => Some(cursor),
+            None => return Err(ErrorBadRequest("Invalid cursor")),
+        },
+        None => None,
+    };
+    let (cursor_started_at, cursor_id) = cursor.unzip();
+
+    // One extra row tells whether there is a next page
+    let mut history: Vec<HistoryEntry> = sqlx::query_as!(
+        HistoryEntry,
+        r#"
+            SELECT id, name,
+                CASE WHEN $2 THEN NULL ELSE exercises END as "exercises: Json<Vec<Exercise>>",
+                started_at, ended_at, timezone, date, duration, points, coins
+            FROM history h
+            WHERE user_id = $1
+                AND ($3::TIMESTAMPTZ IS NULL OR (started_at, id) < ($3, $4))
+                AND ($5::DATE IS NULL OR date >= $5)
+                AND ($6::DATE IS NULL OR date <= $6)
+                AND ($7::TEXT IS NULL OR POSITION(LOWER($7) IN LOWER(name)) > 0)
+                AND ($8::TEXT IS NULL OR exercises @> jsonb_build_array(jsonb_build_object('id', $8)))
+                AND (($9::exercise_category IS NULL AND $10::exercise_muscle IS NULL) OR EXISTS (
+                    SELECT 1
+                    FROM jsonb_array_elements(h.exercises) AS logged
+                    JOIN exercises e ON e.id = logged->>'id'
+                    WHERE ($9 IS NULL OR e.category = $9)
+                        AND ($10 IS NULL OR $10 = ANY(e.primary_muscles || e.secondary_muscles))
+                ))
+            ORDER BY started_at DESC, id DESC
+            LIMIT $11
+        "#,
+        user.id,
+        query.summary,
+        cursor_started_at,
+        cursor_id,
+        query.from,
+        query.to,
+        query.name,
+        query.exercise_id,
+        query.category as Option<ExerciseCategory>,
+        query.muscle as Option<ExerciseMuscle>,
+        limit + 1,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    let next_cursor = if history.len() as i64 > limit {
+        history.truncate(limit as usize);
+        history
+            .last()
+            .map(|entry| {
+                let started_at = entry
+                    .started_at
+                    .to_rfc3339_opts(SecondsFormat::Micros, true);
+                format!("{}_{}", started_at, entry.id)
+            })
+    } else {
+        None
+    };
+
+    return Ok(HttpResponse::Ok().json(ReadHistoryResponse {
+        history,
+        next_cursor,
+    }));
+}
+
+// Gives the change in a workout's own rewards and takes back the rewards of quests it no longer completes.
+// Chests of those quests are only taken back while unopened.
+async fn reconcile_rewards(
+    user: &AuthenticatedUser,
+    conn: &mut PgConnection,
+    exp: i32,
+    coins: i32,
+    revoked: &[RevokedQuest],
+) {
+    let exp = exp - revoked.iter().map(|quest| quest.difficulty.exp()).sum::<i32>();
+    let coins = coins - revoked.iter().map(|quest| quest.difficulty.coins()).sum::<i32>();
+
+    // One call so a net change never levels down and back up
+    add_exp(user, conn, exp).await.unwrap();
+    add_coins(user, conn, coins).await.unwrap();
+
+    let user_chest_ids: Vec<i32> = revoked
+        .iter()
+        .filter_map(|quest| quest.user_chest_id)
+        .collect();
     let _query = sqlx::query!(
         r#"
-            INSERT INTO history (user_id, name, exercises, date, duration, points, coins)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            DELETE FROM user_chests
+            WHERE id = ANY($1) AND user_id = $2 AND opened_at IS NULL
         "#,
+        &user_chest_ids,
+        user.id,
+    )
+    .execute(conn)
+    .await
+    .unwrap();
+}
+
+// Replaces a logged workout, rewards, quest progress and streak are reconciled with the new values
+#[put("/workouts/history/{id}")]
+pub async fn update_history(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    history_id: web::Path<i32>,
+    request: web::Json<CreateHistoryRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let history_id = history_id.into_inner();
+    let (started_at, ended_at) = request.session(&pool, user.id).await?;
+    generate_quest_boards(&pool, user.id).await;
+
+    // The workout stays locked until its quest progress and rewards are reconciled, so a concurrent edit or
+    // delete waits for this one to finish
+    let mut tx = pool.begin().await.unwrap();
+
+    // Returns the rewards the workout gave before the update, the workout keeps the timezone it was logged in
+    let updated = sqlx::query!(
+        r#"
+            UPDATE history h
+            SET name = $3, exercises = $4, started_at = $5, ended_at = $6, points = $7, coins = $8
+            FROM (
+                SELECT id, date, points, coins
+                FROM history
+                WHERE id = $1 AND user_id = $2
+                FOR UPDATE
+            ) previous
+            WHERE h.id = previous.id
+            RETURNING h.timezone, h.date, h.duration, previous.date as previous_date,
+                previous.points, previous.coins
+        "#,
+        history_id,
         user.id,
         request.name,
         serde_json::to_value(&request.exercises.0).unwrap(),
-        request.date,
-        request.duration,
+        started_at,
+        ended_at,
         request.points,
         request.coins,
     )
-    .execute(pool.get_ref())
+    .fetch_optional(&mut *tx)
     .await
     .unwrap();
 
-    add_exp(&user, &pool, request.points).await.unwrap();
-    add_coins(&user, &pool, request.coins).await.unwrap();
-    apply_workout_to_quests(user, pool, &request.0).await;
+    let Some(updated) = updated else {
+        return Err(ErrorNotFound("Workout not found"));
+    };
 
-    return Ok(HttpResponse::Ok().finish());
-}
+    let request = request.into_inner();
+    let history = History {
+        id: history_id,
+        name: request.name,
+        exercises: request.exercises,
+        started_at,
+        ended_at,
+        timezone: updated.timezone,
+        date: updated.date,
+        duration: updated.duration,
+        points: request.points,
+        coins: request.coins,
+    };
 
-#[derive(Deserialize, Serialize)]
-pub struct ReadHistoryResponse {
-    history: Vec<History>,
+    update_rollups(&mut tx, user.id, &[updated.previous_date, history.date])
+        .await
+        .unwrap();
+
+    let context = WorkoutContext::load(&mut tx, user.id, &history).await.unwrap();
+    let revoked = reconcile_quest_progress(&user, &mut tx, history_id, Some(&context)).await;
+
+    reconcile_rewards(
+        &user,
+        &mut tx,
+        history.points - updated.points,
+        history.coins - updated.coins,
+        &revoked,
+    )
+    .await;
+
+    // Quests the edited workout meets now
+    apply_workout_to_quests(&user, &mut tx, &history, &context).await;
+    update_streak(&mut tx, user.id).await.unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(history));
 }
 
-#[get("/workouts/history")]
-pub async fn read_history(
+// Deletes a logged workout and takes back everything it gave
+#[delete("/workouts/history/{id}")]
+pub async fn delete_history(
     user: AuthenticatedUser,
     pool: web::Data<PgPool>,
+    history_id: web::Path<i32>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let history: Vec<History> = sqlx::query_as!(
-        History,
+    let history_id = history_id.into_inner();
+
+    // A concurrent delete waits for this one and then no longer finds the workout, so the rewards are only
+    // taken back once
+    let mut tx = pool.begin().await.unwrap();
+    let exists = sqlx::query_scalar!(
         r#"
-            SELECT id, name, exercises as "exercises: Json<Vec<Exercise>>", date, duration, points, coins
+            SELECT id
             FROM history
-            WHERE user_id = $1
-            ORDER BY date DESC
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
         "#,
-        user.id
+        history_id,
+        user.id,
     )
-    .fetch_all(pool.get_ref())
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if exists.is_none() {
+        return Err(ErrorNotFound("Workout not found"));
+    }
+    lock_rollups(&mut tx, user.id).await.unwrap();
+
+    // Quest progress has to be taken back before its links to the workout are deleted with it
+    let revoked = reconcile_quest_progress(&user, &mut tx, history_id, None).await;
+
+    let deleted = sqlx::query!(
+        r#"
+            DELETE FROM history
+            WHERE id = $1
+            RETURNING date, points, coins
+        "#,
+        history_id,
+    )
+    .fetch_one(&mut *tx)
     .await
     .unwrap();
 
-    return Ok(HttpResponse::Ok().json(ReadHistoryResponse { history }));
+    reconcile_rewards(&user, &mut tx, -deleted.points, -deleted.coins, &revoked).await;
+    update_rollups(&mut tx, user.id, &[deleted.date]).await.unwrap();
+    update_streak(&mut tx, user.id).await.unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Workout deleted"
+    })));
 }
@@ -0,0 +1,989 @@
+use {
+    crate::{
+        endpoints::nav::{
+            quests::generate_quest_boards,
+            workouts::{
+                export::{
+                    EXPORT_VERSION,
+                    HistoryExport,
+                },
+                history::{
+                    CreateHistoryRequest,
+                    insert_history,
+                    reward_workout,
+                },
+            },
+        },
+        utils::{
+            activity::{
+                ActivityFormat,
+                MAX_DURATION,
+                Sport,
+                parse_activity,
+            },
+            coins::add_coins,
+            jwt::AuthenticatedUser,
+            level::add_exp,
+            matcher::{
+                ExerciseMatch,
+                ExerciseMatcher,
+                MATCH_THRESHOLD,
+            },
+            rollups::{
+                lock_rollups,
+                update_rollups,
+            },
+            schemas::{
+                Exercise,
+                ExerciseCategory,
+                ExerciseSet,
+                SetType,
+                WeightUnit,
+            },
+            streak::update_streak,
+        },
+    },
+    actix_web::{
+        HttpResponse,
+        Result,
+        error::{
+            ErrorBadRequest,
+            ErrorPayloadTooLarge,
+        },
+        post,
+        web,
+    },
+    chrono::{
+        DateTime,
+        NaiveDate,
+        NaiveDateTime,
+        TimeDelta,
+        Utc,
+    },
+    csv::{
+        ReaderBuilder,
+        StringRecord,
+        Trim,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::{
+        PgPool,
+        types::Json,
+    },
+    std::collections::{
+        HashMap,
+        HashSet,
+    },
+};
+
+// Exports can span years of training
+const MAX_IMPORT_BYTES: usize = 16 * 1024 * 1024;
+
+// Suggestions listed for every exercise of the mapping report
+const CANDIDATES: usize = 3;
+
+const KM_PER_MILE: f32 = 1.609_344;
+
+// Times in exports are local to the user's timezone
+const TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%d %b %Y, %H:%M", // Hevy
+    "%d %b %Y %H:%M",
+];
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Strong,
+    Hevy,
+    // One row per set, with a header row:
+    // workout,started_at,ended_at,exercise,set_type,reps,weight,distance,duration,rpe,rest
+    // Times are local to the user's timezone, e.g. 2026-10-18 07:30, ended_at can be left empty.
+    // exercise is a name or an exercise id. set_type is warmup, working, drop or failure and
+    // defaults to working. Weight is in the weight_unit of the request, distance in km, duration
+    // and rest in seconds. Empty numbers count as 0.
+    Generic,
+}
+
+impl ImportFormat {
+    fn detect(columns: &Columns) -> ImportFormat {
+        if columns.has("exercise name") && columns.has("set order") {
+            return ImportFormat::Strong;
+        }
+        if columns.has("exercise_title") {
+            return ImportFormat::Hevy;
+        }
+        return ImportFormat::Generic;
+    }
+
+    fn required_columns(self) -> &'static [&'static str] {
+        match self {
+            ImportFormat::Strong => &["date", "workout name", "exercise name", "set order"],
+            ImportFormat::Hevy => &["title", "start_time", "exercise_title"],
+            ImportFormat::Generic => &["workout", "started_at", "exercise"],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportHistoryRequest {
+    pub format: Option<ImportFormat>, // detected from the header when left out
+    pub csv: String,
+    // name in the export to exercise id, overrides the matcher. None leaves the exercise out.
+    #[serde(default)]
+    pub mappings: HashMap<String, Option<String>>,
+    // of the weights of Strong and generic exports, Hevy exports name it in their columns
+    #[serde(default)]
+    pub weight_unit: WeightUnit,
+    #[serde(default)]
+    pub dry_run: bool, // only reports what would be imported
+    #[serde(default)]
+    pub skip_rewards: bool, // imports without giving exp and coins
+}
+
+// How an exercise of the export is imported
+#[derive(Serialize)]
+pub struct ExerciseMapping {
+    pub source: String,
+    pub exercise_id: Option<String>, // None leaves the exercise out
+    pub confirmed: bool,             // given in the request rather than matched
+    pub candidates: Vec<ExerciseMatch>,
+    pub sets: usize,
+}
+
+// A row that could not be read, it is left out
+#[derive(Serialize)]
+pub struct ImportError {
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    pub workouts: usize,   // imported, or to be imported on a dry run
+    pub duplicates: usize, // already in the history
+    pub empty: usize,      // none of their exercises are mapped
+    pub points: i32,
+    pub coins: i32,
+    pub mappings: Vec<ExerciseMapping>,
+    pub errors: Vec<ImportError>,
+}
+
+// Columns of the export by their header
+pub struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    pub fn new(headers: &StringRecord) -> Columns {
+        return Columns(
+            headers
+                .iter()
+                .enumerate()
+                .map(|(index, header)| (header.trim().to_lowercase(), index))
+                .collect(),
+        );
+    }
+
+    fn has(&self, column: &str) -> bool {
+        self.0.contains_key(column)
+    }
+
+    // Empty when the row has no such column
+    fn get<'a>(&self, record: &'a StringRecord, column: &str) -> &'a str {
+        self.0
+            .get(column)
+            .and_then(|&index| record.get(index))
+            .unwrap_or("")
+    }
+
+    // Amounts can't be negative, NaN or infinite
+    fn number(&self, record: &StringRecord, column: &str) -> Result<f32, String> {
+        let value = self.get(record, column);
+        if value.is_empty() {
+            return Ok(0.0);
+        }
+        return value
+            .parse::<f32>()
+            .ok()
+            .filter(|number| number.is_finite() && *number >= 0.0)
+            .ok_or_else(|| format!("Invalid {}: {}", column, value));
+    }
+
+    fn optional_number(&self, record: &StringRecord, column: &str) -> Result<Option<f32>, String> {
+        if self.get(record, column).is_empty() {
+            return Ok(None);
+        }
+        return self.number(record, column).map(Some);
+    }
+
+    fn time(&self, record: &StringRecord, column: &str) -> Result<NaiveDateTime, String> {
+        let value = self.get(record, column);
+        return TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .ok_or_else(|| format!("Invalid {}: {}", column, value));
+    }
+
+    fn optional_time(
+        &self,
+        record: &StringRecord,
+        column: &str,
+    ) -> Result<Option<NaiveDateTime>, String> {
+        if self.get(record, column).is_empty() {
+            return Ok(None);
+        }
+        return self.time(record, column).map(Some);
+    }
+}
+
+// A set as read from a row of the export
+pub struct ImportedSet {
+    pub workout: String,
+    pub started_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+    pub exercise: String,
+    pub set: ExerciseSet,
+}
+
+// Strong writes durations as "1h 5m"
+fn strong_duration(value: &str) -> Option<TimeDelta> {
+    let mut duration = TimeDelta::zero();
+    for part in value.split_whitespace() {
+        let (amount, unit) = part.split_at(part.len() - part.chars().last()?.len_utf8());
+        let amount: i64 = amount.parse().ok()?;
+        let part = match unit {
+            "h" => TimeDelta::try_hours(amount)?,
+            "m" => TimeDelta::try_minutes(amount)?,
+            "s" => TimeDelta::try_seconds(amount)?,
+            _ => return None,
+        };
+        duration = duration.checked_add(&part)?;
+    }
+    return Some(duration);
+}
+
+// Rows that are not sets, like Strong's rest timers, are skipped
+pub fn read_set(
+    format: ImportFormat,
+    weight_unit: WeightUnit,
+    columns: &Columns,
+    record: &StringRecord,
+) -> Result<Option<ImportedSet>, String> {
+    let imported = match format {
+        ImportFormat::Strong => {
+            let set_type = match columns.get(record, "set order") {
+                "W" => SetType::Warmup,
+                "D" => SetType::Drop,
+                "F" => SetType::Failure,
+                order if order.parse::<i32>().is_ok() => SetType::Working,
+                _ => return Ok(None),
+            };
+            let started_at = columns.time(record, "date")?;
+            let ended_at = match columns.get(record, "duration") {
+                "" => None,
+                duration => Some(
+                    strong_duration(duration)
+                        .and_then(|duration| started_at.checked_add_signed(duration))
+                        .ok_or_else(|| format!("Invalid duration: {}", duration))?,
+                ),
+            };
+
+            ImportedSet {
+                workout: columns.get(record, "workout name").to_string(),
+                started_at,
+                ended_at,
+                exercise: columns.get(record, "exercise name").to_string(),
+                set: ExerciseSet {
+                    reps: columns.number(record, "reps")? as i32,
+                    weight: weight_unit.to_lbs(columns.number(record, "weight")?),
+                    distance: columns.number(record, "distance")?,
+                    duration: columns.number(record, "seconds")? as i32,
+                    rpe: columns.optional_number(record, "rpe")?,
+                    set_type,
+                    rest: None,
+                    ..ExerciseSet::default()
+                },
+            }
+        }
+        ImportFormat::Hevy => {
+            let set_type = match columns.get(record, "set_type") {
+                "warmup" => SetType::Warmup,
+                "dropset" => SetType::Drop,
+                "failure" => SetType::Failure,
+                _ => SetType::Working,
+            };
+            let weight = match columns.has("weight_kg") {
+                true => WeightUnit::Kg.to_lbs(columns.number(record, "weight_kg")?),
+                false => columns.number(record, "weight_lbs")?,
+            };
+            let distance = match columns.has("distance_km") {
+                true => columns.number(record, "distance_km")?,
+                false => columns.number(record, "distance_miles")? * KM_PER_MILE,
+            };
+
+            ImportedSet {
+                workout: columns.get(record, "title").to_string(),
+                started_at: columns.time(record, "start_time")?,
+                ended_at: columns.optional_time(record, "end_time")?,
+                exercise: columns.get(record, "exercise_title").to_string(),
+                set: ExerciseSet {
+                    reps: columns.number(record, "reps")? as i32,
+                    weight,
+                    distance,
+                    duration: columns.number(record, "duration_seconds")? as i32,
+                    rpe: columns.optional_number(record, "rpe")?,
+                    set_type,
+                    rest: None,
+                    ..ExerciseSet::default()
+                },
+            }
+        }
+        ImportFormat::Generic => {
+            let set_type = match columns.get(record, "set_type") {
+                "warmup" => SetType::Warmup,
+                "" | "working" => SetType::Working,
+                "drop" => SetType::Drop,
+                "failure" => SetType::Failure,
+                other => return Err(format!("Invalid set_type: {}", other)),
+            };
+
+            ImportedSet {
+                workout: columns.get(record, "workout").to_string(),
+                started_at: columns.time(record, "started_at")?,
+                ended_at: columns.optional_time(record, "ended_at")?,
+                exercise: columns.get(record, "exercise").to_string(),
+                set: ExerciseSet {
+                    reps: columns.number(record, "reps")? as i32,
+                    weight: weight_unit.to_lbs(columns.number(record, "weight")?),
+                    distance: columns.number(record, "distance")?,
+                    duration: columns.number(record, "duration")? as i32,
+                    rpe: columns.optional_number(record, "rpe")?,
+                    set_type,
+                    rest: columns
+                        .optional_number(record, "rest")?
+                        .map(|rest| rest as i32),
+                    ..ExerciseSet::default()
+                },
+            }
+        }
+    };
+
+    if imported.exercise.is_empty() {
+        return Err(String::from("Missing exercise"));
+    }
+    imported.set.validate()?;
+
+    // The duration of the workout has to fit the history
+    let too_long = imported
+        .ended_at
+        .is_some_and(|ended_at| (ended_at - imported.started_at).num_seconds() > MAX_DURATION);
+    if too_long {
+        return Err(format!("A workout can't last over {} hours", MAX_DURATION / 3600));
+    }
+
+    return Ok(Some(imported));
+}
+
+// A workout of the export, its exercises are still named as in the export
+struct ImportedWorkout {
+    name: String,
+    started_at: NaiveDateTime,
+    ended_at: NaiveDateTime,
+    exercises: Vec<(String, Vec<ExerciseSet>)>,
+}
+
+// Groups the sets of the export into workouts by their name and start time
+fn read_workouts(
+    request: &ImportHistoryRequest,
+) -> Result<(ImportFormat, Vec<ImportedWorkout>, Vec<ImportError>), actix_web::Error> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(request.csv.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| ErrorBadRequest(format!("Failed to read the header: {}", e)))?;
+    let columns = Columns::new(headers);
+
+    let format = request
+        .format
+        .unwrap_or_else(|| ImportFormat::detect(&columns));
+    if let Some(missing) = format
+        .required_columns()
+        .iter()
+        .find(|column| !columns.has(column))
+    {
+        return Err(ErrorBadRequest(format!("Missing column: {}", missing)));
+    }
+
+    // Workouts in the order they first appear, indexed by their name and start time
+    let mut workouts: Vec<ImportedWorkout> = Vec::new();
+    let mut indexes: HashMap<(String, NaiveDateTime), usize> = HashMap::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(ImportError {
+                    line: e.position().map_or(0, |position| position.line()),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let imported = match read_set(format, request.weight_unit, &columns, &record) {
+            Ok(Some(imported)) => imported,
+            Ok(None) => continue,
+            Err(message) => {
+                errors.push(ImportError {
+                    line: record.position().map_or(0, |position| position.line()),
+                    message,
+                });
+                continue;
+            }
+        };
+
+        let index = *indexes
+            .entry((imported.workout.clone(), imported.started_at))
+            .or_insert_with(|| {
+                workouts.push(ImportedWorkout {
+                    name: imported.workout,
+                    started_at: imported.started_at,
+                    ended_at: imported.started_at,
+                    exercises: Vec::new(),
+                });
+                workouts.len() - 1
+            });
+
+        let workout = &mut workouts[index];
+        if let Some(ended_at) = imported.ended_at {
+            workout.ended_at = workout.ended_at.max(ended_at);
+        }
+        match workout
+            .exercises
+            .iter_mut()
+            .find(|(exercise, _)| *exercise == imported.exercise)
+        {
+            Some((_, sets)) => sets.push(imported.set),
+            None => workout
+                .exercises
+                .push((imported.exercise, vec![imported.set])),
+        }
+    }
+
+    return Ok((format, workouts, errors));
+}
+
+// Same base the app gives for a logged workout, without the bonus of stats and streak
+fn rewards(minutes: i64) -> (i32, i32) {
+    let minutes = minutes.clamp(0, MAX_DURATION / 60);
+    let points = 50 + minutes as i32;
+    let coins = (25.0 + minutes as f32 / 2.0).round() as i32;
+    return (points, coins);
+}
+
+// Imports the history of another app from its CSV export. Exercises are matched to the catalog by
+// name, a dry run returns the mapping report so the user can confirm or correct it before importing.
+// Imported workouts are in the past, they don't count towards quests. Workouts that start at the
+// same time as one in the history are skipped, so an export can be imported again after adding to it.
+#[post("/workouts/history/import")]
+pub async fn import_history(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    payload: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let body = match payload.to_bytes_limited(MAX_IMPORT_BYTES).await {
+        Ok(body) => body?,
+        Err(_) => return Err(ErrorPayloadTooLarge("Imports can be up to 16 MB")),
+    };
+    let request: ImportHistoryRequest = serde_json::from_slice(&body)
+        .map_err(|e| ErrorBadRequest(format!("Invalid import: {}", e)))?;
+
+    let (format, workouts, errors) = read_workouts(&request)?;
+    let matcher = ExerciseMatcher::load(&pool).await.unwrap();
+
+    if let Some(unknown) = request
+        .mappings
+        .values()
+        .flatten()
+        .find(|exercise_id| !matcher.contains(exercise_id))
+    {
+        return Err(ErrorBadRequest(format!("Unknown exercise: {}", unknown)));
+    }
+
+    // Every exercise of the export in the order it first appears
+    let mut mappings: Vec<ExerciseMapping> = Vec::new();
+    let mut indexes: HashMap<&str, usize> = HashMap::new();
+    for (source, sets) in workouts.iter().flat_map(|workout| workout.exercises.iter()) {
+        if let Some(&index) = indexes.get(source.as_str()) {
+            mappings[index].sets += sets.len();
+            continue;
+        }
+        indexes.insert(source, mappings.len());
+
+        let candidates = matcher.candidates(source, CANDIDATES);
+        let (exercise_id, confirmed) = match request.mappings.get(source) {
+            Some(exercise_id) => (exercise_id.clone(), true),
+            None => (
+                candidates
+                    .first()
+                    .filter(|candidate| candidate.score >= MATCH_THRESHOLD)
+                    .map(|candidate| candidate.exercise_id.clone()),
+                false,
+            ),
+        };
+        mappings.push(ExerciseMapping {
+            source: source.clone(),
+            exercise_id,
+            confirmed,
+            candidates,
+            sets: sets.len(),
+        });
+    }
+    let exercise_ids: HashMap<&str, &str> = mappings
+        .iter()
+        .filter_map(|mapping| {
+            let exercise_id = mapping.exercise_id.as_deref()?;
+            Some((mapping.source.as_str(), exercise_id))
+        })
+        .collect();
+
+    // Local times are placed in the user's timezone, starts first and ends after them
+    let local: Vec<NaiveDateTime> = workouts
+        .iter()
+        .map(|workout| workout.started_at)
+        .chain(workouts.iter().map(|workout| workout.ended_at))
+        .collect();
+    let times: Vec<DateTime<Utc>> = sqlx::query_scalar!(
+        r#"
+            SELECT times.local AT TIME ZONE s.timezone as "time!"
+            FROM UNNEST($2::TIMESTAMP[]) WITH ORDINALITY AS times (local, position)
+            CROSS JOIN settings s
+            WHERE s.user_id = $1
+            ORDER BY times.position
+        "#,
+        user.id,
+        &local,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+    let (starts, ends) = times.split_at(workouts.len());
+
+    // Imports of the same export at once wait for each other, the later one finds its workouts logged
+    let mut tx = pool.begin().await.unwrap();
+    lock_rollups(&mut tx, user.id).await.unwrap();
+
+    let logged: HashSet<DateTime<Utc>> = sqlx::query_scalar!(
+        r#"
+            SELECT started_at
+            FROM history
+            WHERE user_id = $1 AND started_at = ANY($2)
+        "#,
+        user.id,
+        starts,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap()
+    .into_iter()
+    .collect();
+
+    let mut report = ImportReport {
+        format,
+        dry_run: request.dry_run,
+        workouts: 0,
+        duplicates: 0,
+        empty: 0,
+        points: 0,
+        coins: 0,
+        mappings: Vec::new(),
+        errors,
+    };
+
+    // Days of the imported workouts
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    for ((workout, &started_at), &ended_at) in workouts.iter().zip(starts).zip(ends) {
+        if logged.contains(&started_at) {
+            report.duplicates += 1;
+            continue;
+        }
+
+        // Exercises mapped to the same id are merged
+        let mut exercises: Vec<Exercise> = Vec::new();
+        for (source, sets) in &workout.exercises {
+            let Some(&exercise_id) = exercise_ids.get(source.as_str()) else {
+                continue;
+            };
+            match exercises
+                .iter_mut()
+                .find(|exercise| exercise.id == exercise_id)
+            {
+                Some(exercise) => exercise.sets.extend(sets.iter().cloned()),
+                None => exercises.push(Exercise {
+                    id: exercise_id.to_string(),
+                    sets: sets.clone(),
+                }),
+            }
+        }
+
+        if exercises.is_empty() {
+            report.empty += 1;
+            continue;
+        }
+
+        let (points, coins) = match request.skip_rewards {
+            true => (0, 0),
+            false => rewards((ended_at - started_at).num_minutes()),
+        };
+        report.workouts += 1;
+        report.points += points;
+        report.coins += coins;
+
+        if request.dry_run {
+            continue;
+        }
+
+        let name = match workout.name.is_empty() {
+            true => "Workout",
+            false => &workout.name,
+        };
+        let date = sqlx::query_scalar!(
+            r#"
+                INSERT INTO history (user_id, name, exercises, started_at, ended_at, timezone, points, coins)
+                SELECT $1, $2, $3, $4, $5, timezone, $6, $7
+                FROM settings
+                WHERE user_id = $1
+                RETURNING date
+            "#,
+            user.id,
+            name,
+            serde_json::to_value(&exercises).unwrap(),
+            started_at,
+            ended_at,
+            points,
+            coins,
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        dates.push(date);
+    }
+    update_rollups(&mut tx, user.id, &dates).await.unwrap();
+
+    if !request.dry_run {
+        add_exp(&user, &mut tx, report.points).await.unwrap();
+        add_coins(&user, &mut tx, report.coins).await.unwrap();
+        update_streak(&mut tx, user.id).await.unwrap();
+    }
+    tx.commit().await.unwrap();
+
+    report.mappings = mappings;
+    return Ok(HttpResponse::Ok().json(report));
+}
+
+// Cardio exercises of the catalog the sports of activity files are logged as
+fn sport_exercise(sport: Sport) -> Option<&'static str> {
+    match sport {
+        Sport::Running => Some("Running_Treadmill"),
+        Sport::Cycling => Some("Bicycling"),
+        Sport::Walking => Some("Walking_Treadmill"),
+        Sport::Hiking => Some("Trail_Running_Walking"),
+        Sport::Rowing => Some("Rowing_Stationary"),
+        Sport::Skating => Some("Skating"),
+        Sport::Other => None,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportActivityQuery {
+    pub format: Option<ActivityFormat>, // detected from the file when left out
+    pub exercise_id: Option<String>,    // a cardio exercise, overrides the one of the sport
+    pub name: Option<String>,
+    #[serde(default)]
+    pub skip_rewards: bool,
+}
+
+// Logs a GPX, TCX or FIT file recorded by a watch or an app as a cardio workout, with its distance,
+// duration, pace, elevation gain and average heart rate. The file is sent as the body of the request.
+// Unlike CSV imports the activity counts towards quests. An activity that starts at the same time as a
+// workout of the history is rejected, so uploading a file again does not log it twice.
+#[post("/workouts/history/import/activity")]
+pub async fn import_activity(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<ImportActivityQuery>,
+    payload: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let body = match payload.to_bytes_limited(MAX_IMPORT_BYTES).await {
+        Ok(body) => body?,
+        Err(_) => return Err(ErrorPayloadTooLarge("Imports can be up to 16 MB")),
+    };
+    let activity = parse_activity(&body, query.format).map_err(ErrorBadRequest)?;
+
+    let exercise_id = match &query.exercise_id {
+        Some(exercise_id) => exercise_id.as_str(),
+        None => sport_exercise(activity.sport).ok_or_else(|| {
+            ErrorBadRequest("Unknown sport, choose the exercise of the activity with exercise_id")
+        })?,
+    };
+
+    let exercise = sqlx::query!(
+        r#"
+            SELECT name, category as "category: ExerciseCategory"
+            FROM exercises
+            WHERE id = $1
+        "#,
+        exercise_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap()
+    .ok_or_else(|| ErrorBadRequest(format!("Unknown exercise: {}", exercise_id)))?;
+
+    if exercise.category != ExerciseCategory::cardio {
+        return Err(ErrorBadRequest(format!("{} is not a cardio exercise", exercise.name)));
+    }
+
+    generate_quest_boards(&pool, user.id).await;
+    let mut tx = pool.begin().await.unwrap();
+
+    // The same file uploaded twice at once waits for the first upload, which it then finds in the history
+    lock_rollups(&mut tx, user.id).await.unwrap();
+    let logged = sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM history
+                WHERE user_id = $1 AND started_at = $2
+            ) as "exists!"
+        "#,
+        user.id,
+        activity.started_at,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    if logged {
+        return Err(ErrorBadRequest("This activity is already in the history"));
+    }
+
+    let (points, coins) = match query.skip_rewards {
+        true => (0, 0),
+        false => rewards(activity.duration().num_minutes()),
+    };
+    let name = query
+        .name
+        .clone()
+        .or_else(|| activity.name.clone())
+        .unwrap_or(exercise.name);
+
+    let request = CreateHistoryRequest {
+        name,
+        exercises: Json(vec![Exercise {
+            id: exercise_id.to_string(),
+            sets: vec![ExerciseSet {
+                distance: activity.distance,
+                duration: activity.duration().num_seconds() as i32,
+                pace: activity.pace(),
+                elevation_gain: activity.elevation_gain,
+                heart_rate: activity.heart_rate,
+                ..ExerciseSet::default()
+            }],
+        }]),
+        started_at: Some(activity.started_at),
+        ended_at: Some(activity.ended_at),
+        date: None,
+        duration: None,
+        points,
+        coins,
+    };
+    let history = insert_history(
+        &mut tx,
+        user.id,
+        &request,
+        activity.started_at,
+        activity.ended_at,
+    )
+    .await
+    .unwrap();
+    reward_workout(&user, &mut tx, &history).await;
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(history));
+}
+
+#[derive(Serialize)]
+pub struct RestoreReport {
+    pub workouts: usize,
+    pub duplicates: usize, // already in the history
+    pub routines: usize,
+}
+
+// Imports a JSON export back as it was, e.g. into a new account. Workouts keep their times and
+// timezone but not their rewards, the user got them in the account they were exported from and
+// deleting a restored workout must not take them back. Workouts already in the history and routines
+// with the name of one the user has are skipped.
+#[post("/workouts/history/import/json")]
+pub async fn import_json(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    payload: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let body = match payload.to_bytes_limited(MAX_IMPORT_BYTES).await {
+        Ok(body) => body?,
+        Err(_) => return Err(ErrorPayloadTooLarge("Imports can be up to 16 MB")),
+    };
+    let export: HistoryExport = serde_json::from_slice(&body)
+        .map_err(|e| ErrorBadRequest(format!("Invalid export: {}", e)))?;
+
+    if export.version > EXPORT_VERSION {
+        return Err(ErrorBadRequest("The export was made by a newer version of the app"));
+    }
+    if let Some(workout) = export
+        .history
+        .iter()
+        .find(|workout| workout.ended_at < workout.started_at)
+    {
+        return Err(ErrorBadRequest(format!("{} ends before it starts", workout.name)));
+    }
+    if let Some(workout) = export
+        .history
+        .iter()
+        .find(|workout| (workout.ended_at - workout.started_at).num_seconds() > MAX_DURATION)
+    {
+        return Err(ErrorBadRequest(format!(
+            "{} lasts over {} hours",
+            workout.name,
+            MAX_DURATION / 3600
+        )));
+    }
+    if let Some(invalid) = export
+        .history
+        .iter()
+        .flat_map(|workout| workout.exercises.iter())
+        .chain(export.routines.iter().flat_map(|routine| routine.exercises.iter()))
+        .find_map(|exercise| exercise.validate().err())
+    {
+        return Err(ErrorBadRequest(invalid));
+    }
+
+    let mut timezones: Vec<&str> = export
+        .history
+        .iter()
+        .map(|workout| workout.timezone.as_str())
+        .collect();
+    timezones.sort_unstable();
+    timezones.dedup();
+    // Only IANA names known to the database, the way settings accept them
+    let unknown_timezone = sqlx::query_scalar!(
+        r#"
+            SELECT timezone as "timezone!"
+            FROM UNNEST($1::text[]) AS timezone
+            WHERE timezone NOT IN (
+                SELECT name
+                FROM pg_timezone_names
+            )
+            LIMIT 1
+        "#,
+        &timezones as &[&str],
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    if let Some(timezone) = unknown_timezone {
+        return Err(ErrorBadRequest(format!("Invalid timezone: {}", timezone)));
+    }
+
+    let matcher = ExerciseMatcher::load(&pool).await.unwrap();
+    if let Some(unknown) = export
+        .history
+        .iter()
+        .flat_map(|workout| workout.exercises.iter())
+        .chain(export.routines.iter().flat_map(|routine| routine.exercises.iter()))
+        .find(|exercise| !matcher.contains(&exercise.id))
+    {
+        return Err(ErrorBadRequest(format!("Unknown exercise: {}", unknown.id)));
+    }
+
+    let mut report = RestoreReport {
+        workouts: 0,
+        duplicates: 0,
+        routines: 0,
+    };
+
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    let mut tx = pool.begin().await.unwrap();
+    // A restore sent twice at once waits for the first one, which then holds every workout it restored
+    lock_rollups(&mut tx, user.id).await.unwrap();
+    for workout in &export.history {
+        let inserted = sqlx::query_scalar!(
+            r#"
+                INSERT INTO history (user_id, name, exercises, started_at, ended_at, timezone, points, coins)
+                SELECT $1, $2, $3, $4, $5, $6, 0, 0
+                WHERE NOT EXISTS (
+                    SELECT 1
+                    FROM history
+                    WHERE user_id = $1 AND started_at = $4
+                )
+                RETURNING date
+            "#,
+            user.id,
+            workout.name,
+            serde_json::to_value(&workout.exercises.0).unwrap(),
+            workout.started_at,
+            workout.ended_at,
+            workout.timezone,
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .unwrap();
+
+        match inserted {
+            Some(date) => {
+                report.workouts += 1;
+                dates.push(date);
+            }
+            None => report.duplicates += 1,
+        }
+    }
+    update_rollups(&mut tx, user.id, &dates).await.unwrap();
+
+    for routine in &export.routines {
+        report.routines += sqlx::query!(
+            r#"
+                INSERT INTO routines (user_id, name, exercises)
+                SELECT $1, $2, $3
+                WHERE NOT EXISTS (
+                    SELECT 1
+                    FROM routines
+                    WHERE user_id = $1 AND name = $2
+                )
+            "#,
+            user.id,
+            routine.name,
+            serde_json::to_value(&routine.exercises.0).unwrap(),
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap()
+        .rows_affected() as usize;
+    }
+    tx.commit().await.unwrap();
+
+    let mut conn = pool.acquire().await.unwrap();
+    update_streak(&mut conn, user.id).await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(report));
+}
@@ -1 +1,383 @@
+use {
+    crate::utils::{
+        jwt::AuthenticatedUser,
+        schemas::Exercise,
+        strength::{
+            OneRepMaxFormula,
+            STRENGTH_STANDARDS,
+            StrengthStandard,
+            strength_thresholds,
+        },
+    },
+    actix_web::{
+        HttpResponse,
+        Result,
+        error::{
+            ErrorBadRequest,
+            ErrorNotFound,
+        },
+        get,
+        web,
+    },
+    chrono::{
+        DateTime,
+        NaiveDate,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::{
+        PgPool,
+        types::Json,
+    },
+    std::collections::{
+        BTreeMap,
+        HashMap,
+    },
+};
 
+// Best of every exercise the user logged
+#[derive(Default, Serialize)]
+pub struct ExerciseRecord {
+    pub exercise_id: String,
+    pub name: Option<String>,
+    pub heaviest_weight: f32,  // in one working set
+    pub most_reps: i32,        // in one working set
+    pub most_volume: f32,      // reps × weight of the working sets in one workout
+    pub longest_distance: f32, // in one workout
+    pub longest_duration: i32, // seconds, in one set
+    pub workouts: i64,
+}
+
+impl ExerciseRecord {
+    fn add(&mut self, exercise: &Exercise) {
+        for set in exercise.working_sets() {
+            self.heaviest_weight = self.heaviest_weight.max(set.weight);
+            self.most_reps = self.most_reps.max(set.reps);
+        }
+        for set in &exercise.sets {
+            self.longest_duration = self.longest_duration.max(set.duration);
+        }
+        self.most_volume = self.most_volume.max(exercise.volume());
+        self.longest_distance = self.longest_distance.max(exercise.distance());
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReadRecordsResponse {
+    records: Vec<ExerciseRecord>,
+}
+
+// Records are derived from the history every time, so edited and deleted workouts are always accounted for
+#[get("/workouts/records")]
+pub async fn read_records(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let history = sqlx::query_scalar!(
+        r#"
+            SELECT exercises as "exercises: Json<Vec<Exercise>>"
+            FROM history
+            WHERE user_id = $1
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    let mut records: BTreeMap<String, ExerciseRecord> = BTreeMap::new();
+    for exercises in &history {
+        // An exercise logged twice in a workout is one workout and one volume
+        let mut workout: HashMap<&str, Exercise> = HashMap::new();
+        for exercise in exercises.iter() {
+            workout
+                .entry(&exercise.id)
+                .or_insert_with(|| Exercise {
+                    id: exercise.id.clone(),
+                    sets: Vec::new(),
+                })
+                .sets
+                .extend(exercise.sets.iter().cloned());
+        }
+
+        for (id, exercise) in workout {
+            let record = records
+                .entry(id.to_string())
+                .or_insert_with(|| ExerciseRecord {
+                    exercise_id: id.to_string(),
+                    ..ExerciseRecord::default()
+                });
+            record.add(&exercise);
+            record.workouts += 1;
+        }
+    }
+
+    let ids: Vec<String> = records.keys().cloned().collect();
+    let names = sqlx::query!(
+        r#"
+            SELECT id, name
+            FROM exercises
+            WHERE id = ANY($1)
+        "#,
+        &ids
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    for exercise in names {
+        if let Some(record) = records.get_mut(&exercise.id) {
+            record.name = Some(exercise.name);
+        }
+    }
+
+    return Ok(HttpResponse::Ok().json(ReadRecordsResponse {
+        records: records.into_values().collect(),
+    }));
+}
+
+#[derive(Deserialize)]
+pub struct ReadOneRepMaxQuery {
+    #[serde(default)]
+    pub formula: OneRepMaxFormula,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+pub struct EstimatedSet {
+    pub weight: f32,
+    pub reps: i32,
+    pub e1rm: Option<f32>, // None past the reps formulas are reliable for
+}
+
+// Best estimate of a workout, with every working set of the lift
+#[derive(Serialize)]
+pub struct OneRepMaxPoint {
+    pub history_id: i32,
+    pub started_at: DateTime<Utc>,
+    pub date: NaiveDate,
+    pub e1rm: f32,
+    pub sets: Vec<EstimatedSet>,
+}
+
+#[derive(Serialize)]
+pub struct ReadOneRepMaxResponse {
+    pub exercise_id: String,
+    pub name: String,
+    pub formula: OneRepMaxFormula,
+    pub points: Vec<OneRepMaxPoint>, // oldest first
+    pub best: Option<f32>,
+    pub standard: Option<StrengthStandard>, // for lifts with standards, from the best e1RM
+}
+
+async fn read_bodyweight(pool: &PgPool, user_id: i32) -> Option<f32> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT bodyweight
+            FROM settings
+            WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+// Best e1RM of the working sets of a lift in a workout, the lift can be logged more than once
+fn best_estimate(
+    exercises: &[Exercise],
+    exercise_id: &str,
+    formula: OneRepMaxFormula,
+) -> Option<f32> {
+    exercises
+        .iter()
+        .filter(|exercise| exercise.id == exercise_id)
+        .flat_map(|exercise| exercise.working_sets())
+        .filter_map(|set| formula.estimate(set.weight, set.reps))
+        .max_by(f32::total_cmp)
+}
+
+// Strength progression of a lift, one point per workout it was logged in
+#[get("/workouts/records/{exercise_id}/e1rm")]
+pub async fn read_one_rep_max(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<ReadOneRepMaxQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let exercise_id = path.into_inner();
+
+    let name = sqlx::query_scalar!(
+        r#"
+            SELECT name
+            FROM exercises
+            WHERE id = $1
+        "#,
+        exercise_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap()
+    .ok_or_else(|| ErrorNotFound("Exercise not found"))?;
+
+    if query.from.zip(query.to).is_some_and(|(from, to)| from > to) {
+        return Err(ErrorBadRequest("from is after to"));
+    }
+
+    let history = sqlx::query!(
+        r#"
+            SELECT id, started_at, date, exercises as "exercises: Json<Vec<Exercise>>"
+            FROM history
+            WHERE user_id = $1
+                AND exercises @> jsonb_build_array(jsonb_build_object('id', $2::TEXT))
+                AND ($3::DATE IS NULL OR date >= $3)
+                AND ($4::DATE IS NULL OR date <= $4)
+            ORDER BY started_at, id
+        "#,
+        user.id,
+        exercise_id,
+        query.from,
+        query.to,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    let formula = query.formula;
+    let points: Vec<OneRepMaxPoint> = history
+        .into_iter()
+        .filter_map(|workout| {
+            let e1rm = best_estimate(&workout.exercises, &exercise_id, formula)?;
+            let sets = workout
+                .exercises
+                .iter()
+                .filter(|exercise| exercise.id == exercise_id)
+                .flat_map(|exercise| exercise.working_sets())
+                .map(|set| EstimatedSet {
+                    weight: set.weight,
+                    reps: set.reps,
+                    e1rm: formula.estimate(set.weight, set.reps),
+                })
+                .collect();
+            Some(OneRepMaxPoint {
+                history_id: workout.id,
+                started_at: workout.started_at,
+                date: workout.date,
+                e1rm,
+                sets,
+            })
+        })
+        .collect();
+
+    let best = points
+        .iter()
+        .map(|point| point.e1rm)
+        .max_by(f32::total_cmp);
+    let standard = match strength_thresholds(&exercise_id) {
+        Some(thresholds) => Some(StrengthStandard::new(
+            thresholds,
+            best,
+            read_bodyweight(&pool, user.id).await,
+        )),
+        None => None,
+    };
+
+    return Ok(HttpResponse::Ok().json(ReadOneRepMaxResponse {
+        exercise_id,
+        name,
+        formula,
+        points,
+        best,
+        standard,
+    }));
+}
+
+#[derive(Deserialize)]
+pub struct ReadStrengthStandardsQuery {
+    #[serde(default)]
+    pub formula: OneRepMaxFormula,
+}
+
+#[derive(Serialize)]
+pub struct LiftStandard {
+    pub exercise_id: String,
+    pub name: String,
+    pub standard: StrengthStandard,
+}
+
+#[derive(Serialize)]
+pub struct ReadStrengthStandardsResponse {
+    pub formula: OneRepMaxFormula,
+    pub lifts: Vec<LiftStandard>,
+}
+
+// Best e1RM of every lift with standards against novice, intermediate and advanced levels
+#[get("/workouts/records/standards")]
+pub async fn read_strength_standards(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<ReadStrengthStandardsQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ids: Vec<String> = STRENGTH_STANDARDS
+        .iter()
+        .map(|(id, _)| id.to_string())
+        .collect();
+
+    let history = sqlx::query_scalar!(
+        r#"
+            SELECT exercises as "exercises: Json<Vec<Exercise>>"
+            FROM history
+            WHERE user_id = $1 AND EXISTS (
+                SELECT 1
+                FROM jsonb_array_elements(exercises) AS logged
+                WHERE logged->>'id' = ANY($2)
+            )
+        "#,
+        user.id,
+        &ids
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    let names: HashMap<String, String> = sqlx::query!(
+        r#"
+            SELECT id, name
+            FROM exercises
+            WHERE id = ANY($1)
+        "#,
+        &ids
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|exercise| (exercise.id, exercise.name))
+    .collect();
+
+    let bodyweight = read_bodyweight(&pool, user.id).await;
+    let lifts = STRENGTH_STANDARDS
+        .iter()
+        .filter_map(|&(exercise_id, thresholds)| {
+            let best = history
+                .iter()
+                .filter_map(|exercises| best_estimate(exercises, exercise_id, query.formula))
+                .max_by(f32::total_cmp);
+            Some(LiftStandard {
+                exercise_id: exercise_id.to_string(),
+                name: names.get(exercise_id)?.clone(),
+                standard: StrengthStandard::new(thresholds, best, bodyweight),
+            })
+        })
+        .collect();
+
+    return Ok(HttpResponse::Ok().json(ReadStrengthStandardsResponse {
+        formula: query.formula,
+        lifts,
+    }));
+}
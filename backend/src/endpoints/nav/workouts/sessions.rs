@@ -0,0 +1,653 @@
+use {
+    super::history::{
+        CreateHistoryRequest,
+        insert_history,
+        reward_workout,
+    },
+    crate::{
+        endpoints::nav::quests::generate_quest_boards,
+        utils::{
+            jwt::AuthenticatedUser,
+            schemas::{
+                Exercise,
+                ExerciseSet,
+                History,
+                WorkoutSessionStatus,
+            },
+            streak::update_streak,
+        },
+    },
+    actix_web::{
+        HttpResponse,
+        Result,
+        delete,
+        error::{
+            ErrorBadRequest,
+            ErrorNotFound,
+        },
+        get,
+        post,
+        rt::time::interval,
+        web,
+    },
+    chrono::{
+        DateTime,
+        TimeDelta,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::{
+        PgConnection,
+        PgPool,
+        types::Json,
+    },
+    std::time::Duration,
+};
+
+// Sessions without any activity for this long are closed
+const STALE_SESSION_HOURS: i32 = 12;
+
+#[derive(Deserialize, Serialize)]
+pub struct WorkoutSession {
+    pub id: i32,
+    pub routine_id: Option<i32>,
+    pub name: String,
+    pub planned: Json<Vec<Exercise>>, // exercises of the routine
+    pub exercises: Json<Vec<Exercise>>, // sets logged so far
+    pub status: WorkoutSessionStatus,
+    pub started_at: DateTime<Utc>,
+    pub paused_at: Option<DateTime<Utc>>,
+    pub paused_seconds: i32, // total of the pauses that are over
+    pub last_activity_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub history_id: Option<i32>, // once finished
+}
+
+// Logs a session that is over to the history with the given rewards
+async fn log_session(
+    conn: &mut PgConnection,
+    user_id: i32,
+    session: &WorkoutSession,
+    points: i32,
+    coins: i32,
+) -> Result<History, sqlx::Error> {
+    // The history entry starts when the session started and lasts as long as the session was not paused
+    let paused = TimeDelta::seconds(session.paused_seconds as i64);
+    let active = (session.ended_at.unwrap() - session.started_at - paused).max(TimeDelta::zero());
+    let ended_at = session.started_at + active;
+
+    let request = CreateHistoryRequest {
+        name: session.name.clone(),
+        exercises: session.exercises.clone(),
+        started_at: Some(session.started_at),
+        ended_at: Some(ended_at),
+        date: None,
+        duration: None,
+        points,
+        coins,
+    };
+    let history = insert_history(&mut *conn, user_id, &request, session.started_at, ended_at).await?;
+
+    sqlx::query!(
+        r#"
+            UPDATE workout_sessions
+            SET history_id = $2
+            WHERE id = $1
+        "#,
+        session.id,
+        history.id,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    return Ok(history);
+}
+
+// Closes the sessions that went stale, of every user or only of one. Sessions with logged sets are kept in the
+// history without rewards, ending at their last activity, the others are abandoned.
+async fn close_stale(pool: &PgPool, user_id: Option<i32>) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let stale = sqlx::query!(
+        r#"
+            UPDATE workout_sessions
+            SET status = CASE WHEN exercises = '[]' THEN 'abandoned' ELSE 'finished' END::workout_session_status,
+                paused_seconds = paused_seconds
+                    + COALESCE(EXTRACT(EPOCH FROM last_activity_at - paused_at)::INTEGER, 0),
+                paused_at = NULL,
+                ended_at = last_activity_at
+            WHERE status IN ('active', 'paused')
+                AND last_activity_at < NOW() - make_interval(hours => $1)
+                AND ($2::INTEGER IS NULL OR user_id = $2)
+            RETURNING user_id, id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+        "#,
+        STALE_SESSION_HOURS,
+        user_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut user_ids = Vec::new();
+    for row in &stale {
+        if row.status != WorkoutSessionStatus::Finished {
+            continue;
+        }
+
+        let session = WorkoutSession {
+            id: row.id,
+            routine_id: row.routine_id,
+            name: row.name.clone(),
+            planned: row.planned.clone(),
+            exercises: row.exercises.clone(),
+            status: row.status,
+            started_at: row.started_at,
+            paused_at: row.paused_at,
+            paused_seconds: row.paused_seconds,
+            last_activity_at: row.last_activity_at,
+            ended_at: row.ended_at,
+            history_id: row.history_id,
+        };
+        log_session(&mut tx, row.user_id, &session, 0, 0).await?;
+        user_ids.push(row.user_id);
+    }
+
+    // The workouts still count towards the streak
+    user_ids.dedup();
+    for user_id in user_ids {
+        update_streak(&mut tx, user_id).await?;
+    }
+    tx.commit().await?;
+
+    return Ok(stale.len() as u64);
+}
+
+// Runs for the lifetime of the server, closing sessions that were never finished
+pub async fn close_stale_sessions(pool: PgPool) {
+    let mut ticks = interval(Duration::from_secs(15 * 60));
+
+    loop {
+        ticks.tick().await;
+
+        if let Err(e) = close_stale(&pool, None).await {
+            log::error!("Failed to close stale workout sessions: {}", e);
+        }
+    }
+}
+
+// Locks a session of the user until the transaction ends
+async fn lock_session(
+    conn: &mut PgConnection,
+    session_id: i32,
+    user_id: i32,
+) -> Result<Option<WorkoutSession>, sqlx::Error> {
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            SELECT id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+            FROM workout_sessions
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+        "#,
+        session_id,
+        user_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    return Ok(session);
+}
+
+// Saves the logged sets of a session, which counts as activity
+async fn save_exercises(
+    conn: &mut PgConnection,
+    session_id: i32,
+    exercises: &[Exercise],
+) -> Result<WorkoutSession, sqlx::Error> {
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            UPDATE workout_sessions
+            SET exercises = $2, last_activity_at = NOW()
+            WHERE id = $1
+            RETURNING id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+        "#,
+        session_id,
+        serde_json::to_value(exercises).unwrap(),
+    )
+    .fetch_one(conn)
+    .await?;
+
+    return Ok(session);
+}
+
+#[derive(Deserialize)]
+pub struct StartSessionRequest {
+    pub routine_id: Option<i32>, // None to start from scratch
+    pub name: Option<String>,    // defaults to the name of the routine
+}
+
+// Starts a workout session, only one session can be in progress at a time
+#[post("/workouts/sessions")]
+pub async fn start_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    request: web::Json<StartSessionRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    close_stale(&pool, Some(user.id)).await.unwrap();
+
+    let (name, planned) = match request.routine_id {
+        Some(routine_id) => {
+            let routine = sqlx::query!(
+                r#"
+                    SELECT name, exercises as "exercises: Json<Vec<Exercise>>"
+                    FROM routines
+                    WHERE id = $1 AND user_id = $2
+                "#,
+                routine_id,
+                user.id,
+            )
+            .fetch_optional(pool.get_ref())
+            .await
+            .unwrap();
+
+            let Some(routine) = routine else {
+                return Err(ErrorNotFound("Routine not found"));
+            };
+            (routine.name, routine.exercises.0)
+        }
+        None => (String::from("Workout"), Vec::new()),
+    };
+    let name = request.name.clone().unwrap_or(name);
+
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            INSERT INTO workout_sessions (user_id, routine_id, name, planned)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id) WHERE status IN ('active', 'paused') DO NOTHING
+            RETURNING id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+        "#,
+        user.id,
+        request.routine_id,
+        name,
+        serde_json::to_value(&planned).unwrap(),
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    let Some(session) = session else {
+        return Err(ErrorBadRequest("A workout session is already in progress"));
+    };
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+// Session in progress, for picking a workout back up after the app was closed
+#[get("/workouts/sessions/current")]
+pub async fn read_current_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    close_stale(&pool, Some(user.id)).await.unwrap();
+
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            SELECT id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+            FROM workout_sessions
+            WHERE user_id = $1 AND status IN ('active', 'paused')
+        "#,
+        user.id,
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    let Some(session) = session else {
+        return Err(ErrorNotFound("No workout session in progress"));
+    };
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+#[get("/workouts/sessions/{id}")]
+pub async fn read_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    session_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    close_stale(&pool, Some(user.id)).await.unwrap();
+
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            SELECT id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+            FROM workout_sessions
+            WHERE id = $1 AND user_id = $2
+        "#,
+        session_id.into_inner(),
+        user.id,
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    let Some(session) = session else {
+        return Err(ErrorNotFound("Workout session not found"));
+    };
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+#[derive(Deserialize)]
+pub struct LogSetRequest {
+    pub exercise_id: String,
+    #[serde(flatten)]
+    pub set: ExerciseSet,
+}
+
+// Adds a set to an exercise of the session, the exercise is added after the others on its first set
+#[post("/workouts/sessions/{id}/sets")]
+pub async fn log_set(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    session_id: web::Path<i32>,
+    request: web::Json<LogSetRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let request = request.into_inner();
+    request.set.validate().map_err(ErrorBadRequest)?;
+
+    let known = sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM exercises
+                WHERE id = $1
+            ) as "exists!"
+        "#,
+        request.exercise_id,
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .unwrap();
+
+    if !known {
+        return Err(ErrorBadRequest(format!(
+            "Unknown exercise: {}",
+            request.exercise_id
+        )));
+    }
+
+    let mut tx = pool.begin().await.unwrap();
+
+    let session = lock_session(&mut tx, session_id.into_inner(), user.id)
+        .await
+        .unwrap();
+    let Some(session) = session else {
+        return Err(ErrorNotFound("Workout session not found"));
+    };
+
+    match session.status {
+        WorkoutSessionStatus::Active => {}
+        WorkoutSessionStatus::Paused => {
+            return Err(ErrorBadRequest("Resume the workout session to log sets"));
+        }
+        _ => return Err(ErrorBadRequest("This workout session is over")),
+    }
+
+    let mut exercises = session.exercises.0;
+    match exercises
+        .iter_mut()
+        .find(|exercise| exercise.id == request.exercise_id)
+    {
+        Some(exercise) => exercise.sets.push(request.set),
+        None => exercises.push(Exercise {
+            id: request.exercise_id,
+            sets: vec![request.set],
+        }),
+    }
+
+    let session = save_exercises(&mut tx, session.id, &exercises)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+// Removes a logged set by its position, exercises left without sets are removed too
+#[delete("/workouts/sessions/{id}/exercises/{exercise}/sets/{set}")]
+pub async fn delete_set(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, usize, usize)>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (session_id, exercise_index, set_index) = path.into_inner();
+    let mut tx = pool.begin().await.unwrap();
+
+    let session = lock_session(&mut tx, session_id, user.id).await.unwrap();
+    let Some(session) = session else {
+        return Err(ErrorNotFound("Workout session not found"));
+    };
+
+    if !matches!(
+        session.status,
+        WorkoutSessionStatus::Active | WorkoutSessionStatus::Paused
+    ) {
+        return Err(ErrorBadRequest("This workout session is over"));
+    }
+
+    let mut exercises = session.exercises.0;
+    let Some(exercise) = exercises.get_mut(exercise_index) else {
+        return Err(ErrorNotFound("Set not found"));
+    };
+    if set_index >= exercise.sets.len() {
+        return Err(ErrorNotFound("Set not found"));
+    }
+
+    exercise.sets.remove(set_index);
+    if exercise.sets.is_empty() {
+        exercises.remove(exercise_index);
+    }
+
+    let session = save_exercises(&mut tx, session.id, &exercises)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+#[post("/workouts/sessions/{id}/pause")]
+pub async fn pause_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    session_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            UPDATE workout_sessions
+            SET status = 'paused', paused_at = NOW(), last_activity_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND status = 'active'
+            RETURNING id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+        "#,
+        session_id.into_inner(),
+        user.id,
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    let Some(session) = session else {
+        return Err(ErrorBadRequest("No active workout session to pause"));
+    };
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+#[post("/workouts/sessions/{id}/resume")]
+pub async fn resume_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    session_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            UPDATE workout_sessions
+            SET status = 'active',
+                paused_seconds = paused_seconds + EXTRACT(EPOCH FROM NOW() - paused_at)::INTEGER,
+                paused_at = NULL,
+                last_activity_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND status = 'paused'
+            RETURNING id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+        "#,
+        session_id.into_inner(),
+        user.id,
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    let Some(session) = session else {
+        return Err(ErrorBadRequest("No paused workout session to resume"));
+    };
+
+    return Ok(HttpResponse::Ok().json(session));
+}
+
+#[derive(Deserialize)]
+pub struct FinishSessionRequest {
+    pub points: i32, // exp
+    pub coins: i32,
+}
+
+// Logs the session to the history, which gives its rewards like any other logged workout
+#[post("/workouts/sessions/{id}/finish")]
+pub async fn finish_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    session_id: web::Path<i32>,
+    request: web::Json<FinishSessionRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    generate_quest_boards(&pool, user.id).await;
+    let mut tx = pool.begin().await.unwrap();
+
+    let session = lock_session(&mut tx, session_id.into_inner(), user.id)
+        .await
+        .unwrap();
+    let Some(session) = session else {
+        return Err(ErrorNotFound("Workout session not found"));
+    };
+
+    if !matches!(
+        session.status,
+        WorkoutSessionStatus::Active | WorkoutSessionStatus::Paused
+    ) {
+        return Err(ErrorBadRequest("This workout session is over"));
+    }
+
+    if session.exercises.is_empty() {
+        return Err(ErrorBadRequest("Log at least one set before finishing"));
+    }
+
+    // A pause that is still going is not part of the workout
+    let session = sqlx::query_as!(
+        WorkoutSession,
+        r#"
+            UPDATE workout_sessions
+            SET status = 'finished',
+                paused_seconds = paused_seconds + COALESCE(EXTRACT(EPOCH FROM NOW() - paused_at)::INTEGER, 0),
+                paused_at = NULL,
+                last_activity_at = NOW(),
+                ended_at = NOW()
+            WHERE id = $1
+            RETURNING id, routine_id, name,
+                planned as "planned: Json<Vec<Exercise>>",
+                exercises as "exercises: Json<Vec<Exercise>>",
+                status as "status: WorkoutSessionStatus",
+                started_at, paused_at, paused_seconds, last_activity_at, ended_at, history_id
+        "#,
+        session.id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    // The session is only finished together with its history entry
+    let request = request.into_inner();
+    let history = log_session(&mut tx, user.id, &session, request.points, request.coins)
+        .await
+        .unwrap();
+    reward_workout(&user, &mut tx, &history).await;
+    tx.commit().await.unwrap();
+
+    return Ok(HttpResponse::Ok().json(history));
+}
+
+// Discards a session in progress without logging it
+#[delete("/workouts/sessions/{id}")]
+pub async fn abandon_session(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    session_id: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let abandoned = sqlx::query_scalar!(
+        r#"
+            UPDATE workout_sessions
+            SET status = 'abandoned', paused_at = NULL, ended_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND status IN ('active', 'paused')
+            RETURNING id
+        "#,
+        session_id.into_inner(),
+        user.id,
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap();
+
+    if abandoned.is_none() {
+        return Err(ErrorNotFound("No workout session in progress"));
+    }
+
+    return Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Workout session abandoned"
+    })));
+}
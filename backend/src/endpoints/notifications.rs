@@ -0,0 +1,76 @@
+use {
+    crate::utils::{
+        jwt::AuthenticatedUser,
+        schemas::NotificationKind,
+    },
+    actix_web::{
+        HttpResponse,
+        get,
+        put,
+        web,
+    },
+    serde::Serialize,
+    sqlx::PgPool,
+};
+
+#[derive(Serialize)]
+pub struct Notification {
+    pub id: i32,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub item_id: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub read: bool,
+}
+
+// Notifications of the user, unread ones first
+#[get("/notifications")]
+pub async fn read_notifications(user: AuthenticatedUser, pool: web::Data<PgPool>) -> HttpResponse {
+    let notifications_result = sqlx::query_as!(
+        Notification,
+        r#"
+        SELECT id, kind as "kind: NotificationKind", message, item_id, created, read
+        FROM notifications
+        WHERE user_id = $1
+        ORDER BY read, created DESC
+        LIMIT 100
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match notifications_result {
+        Ok(notifications) => HttpResponse::Ok().json(notifications),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch notifications"),
+    }
+}
+
+#[put("/notifications/{id}/read")]
+pub async fn mark_notification_read(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    notification_id: web::Path<i32>,
+) -> HttpResponse {
+    let update_result = sqlx::query!(
+        r#"
+        UPDATE notifications
+        SET read = TRUE
+        WHERE id = $1 AND user_id = $2
+        "#,
+        notification_id.into_inner(),
+        user.id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match update_result {
+        Ok(result) if result.rows_affected() == 0 => {
+            HttpResponse::NotFound().body("Notification not found")
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Notification marked as read"
+        })),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to update notification"),
+    }
+}
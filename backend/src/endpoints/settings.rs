@@ -94,6 +94,24 @@ struct UpdateWorkoutScheduleRequest {
     workout_schedule: [bool; 7],
 }
 
+#[derive(Deserialize)]
+struct UpdateTimezoneRequest {
+    timezone: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateQuestTuningRequest {
+    quest_stretch: f32,
+    target_neglected_muscles: bool,
+}
+
+#[derive(Deserialize)]
+struct UpdateBodyweightRequest {
+    bodyweight: Option<f32>, // None clears it
+    #[serde(default)]
+    weight_unit: WeightUnit, // stored in lbs like the lifts it is compared to
+}
+
 #[derive(Deserialize)]
 struct UpdateEmailRequest {
     email: String,
@@ -216,6 +234,115 @@ async fn update_workout_schedule(
     })))
 }
 
+// Update timezone, quest boards roll over at midnight in this timezone
+#[post("/settings/timezone")]
+async fn update_timezone(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    request: web::Json<UpdateTimezoneRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Only accept IANA names known to the database, e.g. "America/Toronto"
+    let known_timezone = sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM pg_timezone_names
+                WHERE name = $1
+            ) as "exists!"
+        "#,
+        request.timezone
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .map_err(|e| ErrorBadRequest(format!("Database error: {}", e)))?;
+
+    if !known_timezone {
+        return Err(ErrorBadRequest("This timezone is invalid"));
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE settings
+            SET timezone = $1
+            WHERE user_id = $2
+        "#,
+        request.timezone,
+        user.id
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(|e| ErrorBadRequest(format!("Failed to update timezone: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Timezone updated successfully"
+    })))
+}
+
+// Update how far generated quests stretch above the user's recent training
+#[post("/settings/quests")]
+async fn update_quest_tuning(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    request: web::Json<UpdateQuestTuningRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // 0 keeps quests at the usual workload, 1 doubles it for every difficulty step
+    if !(0.0..=1.0).contains(&request.quest_stretch) {
+        return Err(ErrorBadRequest("Quest stretch must be between 0 and 1"));
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE settings
+            SET quest_stretch = $1, target_neglected_muscles = $2
+            WHERE user_id = $3
+        "#,
+        request.quest_stretch,
+        request.target_neglected_muscles,
+        user.id
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(|e| ErrorBadRequest(format!("Failed to update quest settings: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quest settings updated successfully"
+    })))
+}
+
+// Update bodyweight, lifts are compared to strength standards relative to it
+#[post("/settings/bodyweight")]
+async fn update_bodyweight(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    request: web::Json<UpdateBodyweightRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let bodyweight = request
+        .bodyweight
+        .map(|bodyweight| request.weight_unit.to_lbs(bodyweight));
+    if bodyweight.is_some_and(|bodyweight| {
+        !(20.0 * LBS_PER_KG..=500.0 * LBS_PER_KG).contains(&bodyweight)
+    }) {
+        return Err(ErrorBadRequest("Bodyweight must be between 20 and 500 kg (44 and 1102 lbs)"));
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE settings
+            SET bodyweight = $1
+            WHERE user_id = $2
+        "#,
+        bodyweight,
+        user.id
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(|e| ErrorBadRequest(format!("Failed to update bodyweight: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Bodyweight updated successfully"
+    })))
+}
+
 // Update email
 #[post("/settings/email")]
 async fn update_email(
@@ -1,5 +1,11 @@
 use {
     crate::utils::{
+        idempotency::idempotency,
+        inventory::{
+            fetch_transfer_items,
+            lock_holdings,
+            save_holdings,
+        },
         jwt::AuthenticatedUser,
         schemas::*,
     },
@@ -9,7 +15,10 @@ use {
         web,
         put,
         post,
+        delete,
+        middleware::from_fn,
     },
+    rand::Rng,
     std::collections::HashMap,
     serde::{Serialize, Deserialize},
     once_cell::sync::Lazy,
@@ -28,6 +37,32 @@ static RARITY_PRICES: Lazy<HashMap<ItemRarity, i32>> = Lazy::new(|| {
     map
 });
 
+// Fraction of the shop price that is refunded when an item is sold back
+static SELL_BACK_RATES: Lazy<HashMap<ItemRarity, f64>> = Lazy::new(|| {
+    let mut map = HashMap::new();
+    map.insert(ItemRarity::Default, 0.0);
+    map.insert(ItemRarity::Common, 0.5);
+    map.insert(ItemRarity::Uncommon, 0.5);
+    map.insert(ItemRarity::Rare, 0.4);
+    map.insert(ItemRarity::Epic, 0.3);
+    map.insert(ItemRarity::Legendary, 0.25);
+    map
+});
+
+pub fn item_price(rarity: ItemRarity) -> i32 {
+    *RARITY_PRICES.get(&rarity).unwrap_or(&100)
+}
+
+// Coins taken off the price of an item on sale
+pub fn sale_discount(price: i32, discount_percent: i32) -> i32 {
+    price * discount_percent / 100
+}
+
+pub fn sell_back_price(rarity: ItemRarity) -> i32 {
+    let rate = *SELL_BACK_RATES.get(&rarity).unwrap_or(&0.0);
+    (item_price(rarity) as f64 * rate).floor() as i32
+}
+
 #[derive(Serialize)]
 pub struct ShopItem {
     pub id: i32,
@@ -36,6 +71,9 @@ pub struct ShopItem {
     pub rarity: ItemRarity,
     pub path: String,
     pub price: i32,     // based on rarity
+    pub discount: i32,  // percentage off while on sale
+    pub sale_price: i32,
+    pub wishlisted: bool,
 }
 
 #[derive(Deserialize)]
@@ -43,20 +81,58 @@ pub struct BuyItemRequest {
     pub item_id: i32,
 }
 
+#[derive(Serialize)]
+pub struct Purchase {
+    pub purchase_id: i32,
+    pub item_id: i32,
+    pub name: String,
+    pub category: ItemCategory,
+    pub rarity: ItemRarity,
+    pub price: i32,    // shop price at the time of purchase
+    pub discount: i32, // coins taken off the shop price
+    pub price_paid: i32,
+    pub purchased_at: chrono::NaiveDateTime,
+    pub refunded_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct SellItemRequest {
+    pub item_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct WishlistRequest {
+    pub item_id: i32,
+}
+
+#[derive(Serialize)]
+pub struct WishlistItem {
+    pub id: i32,
+    pub name: String,
+    pub category: ItemCategory,
+    pub rarity: ItemRarity,
+    pub price: i32,
+    pub in_shop: bool,
+    pub added_at: chrono::NaiveDateTime,
+}
+
 #[get("/shop")]
 pub async fn get_shop(
-    _user: AuthenticatedUser,
+    user: AuthenticatedUser,
     pool: web::Data<PgPool>,
 ) -> HttpResponse {
     // matches items in shop with their details from items table
     let shop_items_result = sqlx::query!(
         r#"
         SELECT i.id, i.name, i.category as "category: ItemCategory", 
-               i.rarity as "rarity: ItemRarity", i.path
+               i.rarity as "rarity: ItemRarity", i.path, s.discount,
+               w.item_id IS NOT NULL as "wishlisted!"
         FROM shop s
         JOIN items i ON s.item_id = i.id
+        LEFT JOIN wishlist w ON w.item_id = i.id AND w.user_id = $1
         ORDER BY s.added_at DESC
-        "#
+        "#,
+        user.id
     )
     .fetch_all(pool.get_ref())
     .await;
@@ -65,13 +141,19 @@ pub async fn get_shop(
         Ok(queries) => {
             let items: Vec<ShopItem> = queries
                 .into_iter()
-                .map(|q| ShopItem {
-                    id: q.id,
-                    name: q.name,
-                    category: q.category,
-                    rarity: q.rarity.clone(),       
-                    path: q.path,
-                    price: *RARITY_PRICES.get(&q.rarity).unwrap_or(&100),  // default price if rarity not found (misspelled)
+                .map(|q| {
+                    let price = item_price(q.rarity);
+                    ShopItem {
+                        id: q.id,
+                        name: q.name,
+                        category: q.category,
+                        rarity: q.rarity,
+                        path: q.path,
+                        price,
+                        discount: q.discount,
+                        sale_price: price - sale_discount(price, q.discount),
+                        wishlisted: q.wishlisted,
+                    }
                 })
                 .collect();
             
@@ -124,14 +206,22 @@ pub async fn refresh_shop(
         }
     };
 
-    // Insert all 10 items into shop
+    // Insert all 10 items into shop, some of them on sale
     for item_id in &item_ids {
+        let mut rng = rand::rng();
+        let discount = if rng.random_bool(0.2) {
+            rng.random_range(1..=5) * 10
+        } else {
+            0
+        };
+
         let insert_result = sqlx::query!(
             r#"
-            INSERT INTO shop (item_id)
-            VALUES ($1)
+            INSERT INTO shop (item_id, discount)
+            VALUES ($1, $2)
             "#,
-            item_id
+            item_id,
+            discount
         )
         .execute(&mut *tx)
         .await;
@@ -142,6 +232,30 @@ pub async fn refresh_shop(
         }
     }
 
+    // Let users know when wishlisted items rotate into the shop or go on sale
+    let notify_result = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, kind, message, item_id)
+        SELECT w.user_id,
+               CASE WHEN s.discount > 0 THEN 'wishlist_on_sale' ELSE 'wishlist_in_shop' END::notification_kind,
+               CASE WHEN s.discount > 0
+                   THEN i.name || ' from your wishlist is on sale for ' || s.discount || '% off'
+                   ELSE i.name || ' from your wishlist is in the shop'
+               END,
+               i.id
+        FROM shop s
+        JOIN wishlist w ON w.item_id = s.item_id
+        JOIN items i ON i.id = s.item_id
+        "#
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if notify_result.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to notify users");
+    }
+
     // Commit transaction
     if tx.commit().await.is_err() {
         return HttpResponse::InternalServerError().body("Failed to commit transaction");
@@ -153,7 +267,7 @@ pub async fn refresh_shop(
     }))
 }
 
-#[post("/shop/buy")]
+#[post("/shop/buy", wrap = "from_fn(idempotency)")]
 pub async fn buy_item(
     user: AuthenticatedUser,
     pool: web::Data<PgPool>,
@@ -170,25 +284,27 @@ pub async fn buy_item(
     // Check if item is in shop
     let item_in_shop = sqlx::query!(
         r#"
-        SELECT EXISTS(SELECT 1 FROM shop WHERE item_id = $1) as "exists!"
+        SELECT discount
+        FROM shop
+        WHERE item_id = $1
+        LIMIT 1
         "#,
         item_id
     )
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await;
 
-    match item_in_shop {
-        Ok(query) => {
-            if !query.exists {
-                let _ = tx.rollback().await;
-                return HttpResponse::NotFound().body("Item is not available in shop");
-            }
+    let discount_percent = match item_in_shop {
+        Ok(Some(query)) => query.discount,
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("Item is not available in shop");
         }
         Err(_) => {
             let _ = tx.rollback().await;
             return HttpResponse::InternalServerError().body("Failed to verify item");
         }
-    }
+    };
 
     // Get item details (rarity and category) 
     let item_details = sqlx::query!(
@@ -211,7 +327,9 @@ pub async fn buy_item(
     };
 
     // Calculate price from rarity (if non default to 100)
-    let price = *RARITY_PRICES.get(&rarity).unwrap_or(&100);
+    let price = item_price(rarity);
+    let discount = sale_discount(price, discount_percent);
+    let price_paid = price - discount;
 
     // Get user's current coins and inventory
     let user_data = sqlx::query!(
@@ -234,44 +352,26 @@ pub async fn buy_item(
     };
 
     // Check if user has enough coins
-    if current_coins < price {
+    if current_coins < price_paid {
         let _ = tx.rollback().await;
         return HttpResponse::BadRequest().json(serde_json::json!({
             "error": "Not enough coins",
-            "required": price,
+            "required": price_paid,
             "available": current_coins
         }));
     }
 
     // Check if user already owns this item
-    let already_owned = match category {
-        ItemCategory::Arm => inventory.arms.contains(&item_id),
-        ItemCategory::Background => inventory.backgrounds.contains(&item_id),
-        ItemCategory::Body => inventory.bodies.contains(&item_id),
-        ItemCategory::Head => inventory.heads.contains(&item_id),
-        ItemCategory::Head_Accessory => inventory.head_accessories.contains(&item_id),
-        ItemCategory::Pet => inventory.pets.contains(&item_id),
-        ItemCategory::Weapon => inventory.weapons.contains(&item_id),
-    };
-
-    if already_owned {
+    if inventory.contains(category, item_id) {
         let _ = tx.rollback().await;
         return HttpResponse::BadRequest().body("You already own this item");
     }
 
     // Add item to appropriate inventory array
-    match category {
-        ItemCategory::Arm => inventory.arms.push(item_id),
-        ItemCategory::Background => inventory.backgrounds.push(item_id),
-        ItemCategory::Body => inventory.bodies.push(item_id),
-        ItemCategory::Head => inventory.heads.push(item_id),
-        ItemCategory::Head_Accessory => inventory.head_accessories.push(item_id),
-        ItemCategory::Pet => inventory.pets.push(item_id),
-        ItemCategory::Weapon => inventory.weapons.push(item_id),
-    }
+    inventory.items_mut(category).push(item_id);
 
     // Update user's coins and inventory
-    let new_coins = current_coins - price;
+    let new_coins = current_coins - price_paid;
     let update_result = sqlx::query!(
         r#"
         UPDATE characters
@@ -290,15 +390,425 @@ pub async fn buy_item(
         return HttpResponse::InternalServerError().body("Failed to update user data");
     }
 
+    // Keep a receipt of the purchase
+    let purchase_result = sqlx::query!(
+        r#"
+        INSERT INTO purchases (user_id, item_id, price, discount, price_paid)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        user.id,
+        item_id,
+        price,
+        discount,
+        price_paid
+    )
+    .fetch_one(&mut *tx)
+    .await;
+
+    let purchase_id = match purchase_result {
+        Ok(query) => query.id,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to record purchase");
+        }
+    };
+
+    // The item is owned now, no need to keep it wishlisted
+    let wishlist_result = sqlx::query!(
+        r#"
+        DELETE FROM wishlist
+        WHERE user_id = $1 AND item_id = $2
+        "#,
+        user.id,
+        item_id
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if wishlist_result.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update wishlist");
+    }
+
     if tx.commit().await.is_err() {
         return HttpResponse::InternalServerError().body("Failed to commit transaction");
     }
 
     HttpResponse::Ok().json(serde_json::json!({
         "message": "Item purchased successfully",
+        "purchase_id": purchase_id,
         "item_id": item_id,
         "price": price,
+        "discount": discount,
+        "price_paid": price_paid,
         "rarity": rarity,
         "remaining_coins": new_coins
     }))
-}
\ No newline at end of file
+}
+
+// Sells a non-default item back to the shop for a fraction of its price based on rarity
+#[post("/shop/sell")]
+pub async fn sell_item(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    req: web::Json<SellItemRequest>,
+) -> HttpResponse {
+    let item_id = req.item_id;
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to start transaction"),
+    };
+
+    let item = match fetch_transfer_items(&mut tx, &[item_id]).await {
+        Ok(items) => match items.first() {
+            Some(item) => *item,
+            None => {
+                let _ = tx.rollback().await;
+                return HttpResponse::NotFound().body("Item not found");
+            }
+        },
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch item");
+        }
+    };
+
+    let mut holdings = match lock_holdings(&mut tx, user.id).await {
+        Ok(holdings) => holdings,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch user data");
+        }
+    };
+
+    // Refuses default, equipped and unowned items
+    if let Err(error) = holdings.take_item(&item) {
+        let _ = tx.rollback().await;
+        return HttpResponse::BadRequest().body(error);
+    }
+
+    let price = sell_back_price(item.rarity);
+    holdings.coins += price;
+
+    if save_holdings(&mut tx, &holdings).await.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update user data");
+    }
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().body("Failed to commit transaction");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Item sold successfully",
+        "item_id": item_id,
+        "price": price,
+        "rarity": item.rarity,
+        "remaining_coins": holdings.coins
+    }))
+}
+
+// Purchase history of the user, most recent first
+#[get("/shop/purchases")]
+pub async fn read_purchases(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let purchases_result = sqlx::query_as!(
+        Purchase,
+        r#"
+        SELECT p.id as purchase_id, p.item_id, i.name, i.category as "category: ItemCategory",
+               i.rarity as "rarity: ItemRarity", p.price, p.discount, p.price_paid,
+               p.purchased_at, p.refunded_at
+        FROM purchases p
+        JOIN items i ON p.item_id = i.id
+        WHERE p.user_id = $1
+        ORDER BY p.purchased_at DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match purchases_result {
+        Ok(purchases) => HttpResponse::Ok().json(purchases),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch purchases"),
+    }
+}
+
+// Receipt of a single purchase
+#[get("/shop/purchases/{id}")]
+pub async fn read_purchase(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    purchase_id: web::Path<i32>,
+) -> HttpResponse {
+    let purchase_id = purchase_id.into_inner();
+
+    let purchase_result = sqlx::query_as!(
+        Purchase,
+        r#"
+        SELECT p.id as purchase_id, p.item_id, i.name, i.category as "category: ItemCategory",
+               i.rarity as "rarity: ItemRarity", p.price, p.discount, p.price_paid,
+               p.purchased_at, p.refunded_at
+        FROM purchases p
+        JOIN items i ON p.item_id = i.id
+        WHERE p.id = $1 AND p.user_id = $2
+        "#,
+        purchase_id,
+        user.id
+    )
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    match purchase_result {
+        Ok(Some(purchase)) => HttpResponse::Ok().json(purchase),
+        Ok(None) => HttpResponse::NotFound().body("Purchase not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch purchase"),
+    }
+}
+
+// Admin only, gives the coins paid back to the buyer and removes the item from their inventory
+#[post("/shop/purchases/{id}/refund")]
+pub async fn refund_purchase(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    purchase_id: web::Path<i32>,
+) -> HttpResponse {
+    let purchase_id = purchase_id.into_inner();
+
+    let is_admin = sqlx::query!(
+        r#"
+        SELECT is_admin
+        FROM users
+        WHERE id = $1
+        "#,
+        user.id
+    )
+    .fetch_one(pool.get_ref())
+    .await;
+
+    match is_admin {
+        Ok(query) => {
+            if !query.is_admin {
+                return HttpResponse::Forbidden().body("Only admins can refund purchases");
+            }
+        }
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to verify user"),
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to start transaction"),
+    };
+
+    // Lock the purchase so it can only be refunded once
+    let purchase_result = sqlx::query!(
+        r#"
+        SELECT user_id, item_id, price_paid, refunded_at
+        FROM purchases
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        purchase_id
+    )
+    .fetch_optional(&mut *tx)
+    .await;
+
+    let purchase = match purchase_result {
+        Ok(Some(purchase)) => {
+            if purchase.refunded_at.is_some() {
+                let _ = tx.rollback().await;
+                return HttpResponse::BadRequest().body("Purchase has already been refunded");
+            }
+            purchase
+        }
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("Purchase not found");
+        }
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch purchase");
+        }
+    };
+
+    let item = match fetch_transfer_items(&mut tx, &[purchase.item_id]).await {
+        Ok(items) if !items.is_empty() => items[0],
+        Ok(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("Item not found");
+        }
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch item");
+        }
+    };
+
+    let mut holdings = match lock_holdings(&mut tx, purchase.user_id).await {
+        Ok(holdings) => holdings,
+        Err(_) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to fetch user data");
+        }
+    };
+
+    // The buyer may have equipped, sold or traded the item since
+    if let Err(error) = holdings.take_item(&item) {
+        let _ = tx.rollback().await;
+        return HttpResponse::BadRequest().body(error);
+    }
+
+    holdings.coins += purchase.price_paid;
+
+    if save_holdings(&mut tx, &holdings).await.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update user data");
+    }
+
+    let update_result = sqlx::query!(
+        r#"
+        UPDATE purchases
+        SET refunded_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+        purchase_id
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if update_result.is_err() {
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update purchase");
+    }
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().body("Failed to commit transaction");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Purchase refunded",
+        "purchase_id": purchase_id,
+        "refunded_coins": purchase.price_paid
+    }))
+}
+
+// Items the user has wishlisted from the full catalog
+#[get("/shop/wishlist")]
+pub async fn read_wishlist(user: AuthenticatedUser, pool: web::Data<PgPool>) -> HttpResponse {
+    let wishlist_result = sqlx::query!(
+        r#"
+        SELECT i.id, i.name, i.category as "category: ItemCategory",
+               i.rarity as "rarity: ItemRarity", w.added_at,
+               EXISTS(SELECT 1 FROM shop s WHERE s.item_id = i.id) as "in_shop!"
+        FROM wishlist w
+        JOIN items i ON w.item_id = i.id
+        WHERE w.user_id = $1
+        ORDER BY w.added_at DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match wishlist_result {
+        Ok(queries) => {
+            let items: Vec<WishlistItem> = queries
+                .into_iter()
+                .map(|q| WishlistItem {
+                    id: q.id,
+                    name: q.name,
+                    category: q.category,
+                    rarity: q.rarity,
+                    price: item_price(q.rarity),
+                    in_shop: q.in_shop,
+                    added_at: q.added_at,
+                })
+                .collect();
+            HttpResponse::Ok().json(items)
+        }
+        Err(_) => HttpResponse::InternalServerError().body("Failed to fetch wishlist"),
+    }
+}
+
+#[post("/shop/wishlist")]
+pub async fn add_to_wishlist(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    req: web::Json<WishlistRequest>,
+) -> HttpResponse {
+    let item_id = req.item_id;
+
+    // Default items are never sold so they cannot be wishlisted
+    let item_result = sqlx::query!(
+        r#"
+        SELECT rarity as "rarity: ItemRarity"
+        FROM items
+        WHERE id = $1
+        "#,
+        item_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    match item_result {
+        Ok(Some(query)) => {
+            if query.rarity == ItemRarity::Default {
+                return HttpResponse::BadRequest().body("Default items cannot be wishlisted");
+            }
+        }
+        Ok(None) => return HttpResponse::NotFound().body("Item not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to verify item"),
+    }
+
+    let insert_result = sqlx::query!(
+        r#"
+        INSERT INTO wishlist (user_id, item_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, item_id) DO NOTHING
+        "#,
+        user.id,
+        item_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match insert_result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Item added to wishlist",
+            "item_id": item_id
+        })),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to update wishlist"),
+    }
+}
+
+#[delete("/shop/wishlist")]
+pub async fn remove_from_wishlist(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    req: web::Json<WishlistRequest>,
+) -> HttpResponse {
+    let delete_result = sqlx::query!(
+        r#"
+        DELETE FROM wishlist
+        WHERE user_id = $1 AND item_id = $2
+        "#,
+        user.id,
+        req.item_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match delete_result {
+        Ok(result) if result.rows_affected() == 0 => {
+            HttpResponse::NotFound().body("Item is not in your wishlist")
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Item removed from wishlist",
+            "item_id": req.item_id
+        })),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to update wishlist"),
+    }
+}
@@ -1,15 +1,37 @@
 use {
     crate::utils::{
         jwt::AuthenticatedUser,
-        schemas::Class,
+        rollups::{
+            Adherence,
+            AnalyticsPeriod,
+            DailyRollup,
+            Trends,
+            trends,
+        },
+        schemas::{
+            Class,
+            ExerciseCategory,
+            ExerciseMuscle,
+        },
     },
     actix_web::{
         HttpResponse,
+        error::ErrorBadRequest,
+        get,
         post,
         web,
     },
-    serde::Deserialize,
+    chrono::{
+        Datelike,
+        NaiveDate,
+        TimeDelta,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
     sqlx::PgPool,
+    std::collections::BTreeMap,
 };
 
 #[derive(Deserialize)]
@@ -71,3 +93,173 @@ pub async fn increase_stat(
         req.stat, req.amount, new_pending
     ))
 }
+
+// Analytics cover a year when no range is given
+const DEFAULT_ANALYTICS_DAYS: i64 = 365;
+const MAX_ANALYTICS_DAYS: i64 = 5 * 366;
+
+// Secondary muscles get this share of the volume and sets of an exercise
+const SECONDARY_MUSCLE_SHARE: f64 = 0.5;
+
+#[derive(Deserialize)]
+pub struct ReadAnalyticsQuery {
+    pub from: Option<NaiveDate>, // defaults to a year before to
+    pub to: Option<NaiveDate>,   // defaults to today in the user's timezone
+}
+
+#[derive(Serialize)]
+pub struct MuscleVolume {
+    pub muscle: ExerciseMuscle,
+    pub volume: f32,
+    pub sets: f32, // working sets, secondary muscles count for half of them
+}
+
+#[derive(Serialize)]
+pub struct CategoryVolume {
+    pub category: ExerciseCategory,
+    pub days: i32, // days the category was trained
+    pub sets: i32,
+    pub reps: i32,
+    pub volume: f32,
+    pub distance: f32,
+    pub duration: i32, // seconds
+}
+
+#[derive(Serialize)]
+pub struct ReadAnalyticsResponse {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub weekly: Vec<AnalyticsPeriod>,
+    pub monthly: Vec<AnalyticsPeriod>,
+    pub muscles: Vec<MuscleVolume>,
+    pub categories: Vec<CategoryVolume>,
+    pub adherence: Adherence,
+}
+
+// Trends of the history, read from the rollups every write to the history updates
+#[get("/stats/analytics")]
+pub async fn read_analytics(
+    user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<ReadAnalyticsQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let settings = sqlx::query!(
+        r#"
+            SELECT (NOW() AT TIME ZONE timezone)::date as "today!", workout_schedule
+            FROM settings
+            WHERE user_id = $1
+        "#,
+        user.id
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .unwrap();
+
+    let to = query.to.unwrap_or(settings.today);
+    // Dates before year 1 are out of the range of the database
+    let Some(from) = query
+        .from
+        .or(to.checked_sub_signed(TimeDelta::days(DEFAULT_ANALYTICS_DAYS - 1)))
+        .filter(|from| from.year() >= 1)
+    else {
+        return Err(ErrorBadRequest("Dates are out of range"));
+    };
+    if from > to {
+        return Err(ErrorBadRequest("from is after to"));
+    }
+    if (to - from).num_days() >= MAX_ANALYTICS_DAYS {
+        return Err(ErrorBadRequest("Analytics cover up to 5 years"));
+    }
+
+    let days = sqlx::query!(
+        r#"
+            SELECT date, sessions, minutes, volume, distance
+            FROM history_daily_rollups
+            WHERE user_id = $1 AND date BETWEEN $2 AND $3
+        "#,
+        user.id,
+        from,
+        to,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    let days: BTreeMap<NaiveDate, DailyRollup> = days
+        .into_iter()
+        .map(|day| {
+            let rollup = DailyRollup {
+                sessions: day.sessions,
+                minutes: day.minutes,
+                volume: day.volume,
+                distance: day.distance,
+            };
+            (day.date, rollup)
+        })
+        .collect();
+    let Trends {
+        weekly,
+        monthly,
+        adherence,
+    } = trends(from, to, settings.today, &settings.workout_schedule, &days);
+
+    let muscles = sqlx::query_as!(
+        MuscleVolume,
+        r#"
+            SELECT m.muscle as "muscle!: ExerciseMuscle",
+                SUM(r.volume * m.share)::REAL as "volume!",
+                SUM(r.sets * m.share)::REAL as "sets!"
+            FROM history_exercise_rollups r
+            JOIN exercises e ON e.id = r.exercise_id
+            CROSS JOIN LATERAL (
+                SELECT UNNEST(e.primary_muscles), 1.0::FLOAT8
+                UNION ALL
+                SELECT UNNEST(e.secondary_muscles), $4
+            ) AS m (muscle, share)
+            WHERE r.user_id = $1 AND r.date BETWEEN $2 AND $3
+            GROUP BY m.muscle
+            ORDER BY 2 DESC, 3 DESC
+        "#,
+        user.id,
+        from,
+        to,
+        SECONDARY_MUSCLE_SHARE,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    let categories = sqlx::query_as!(
+        CategoryVolume,
+        r#"
+            SELECT e.category as "category!: ExerciseCategory",
+                COUNT(DISTINCT r.date)::INTEGER as "days!",
+                SUM(r.sets)::INTEGER as "sets!",
+                SUM(r.reps)::INTEGER as "reps!",
+                SUM(r.volume)::REAL as "volume!",
+                SUM(r.distance)::REAL as "distance!",
+                SUM(r.duration)::INTEGER as "duration!"
+            FROM history_exercise_rollups r
+            JOIN exercises e ON e.id = r.exercise_id
+            WHERE r.user_id = $1 AND r.date BETWEEN $2 AND $3
+            GROUP BY e.category
+            ORDER BY 2 DESC, 5 DESC
+        "#,
+        user.id,
+        from,
+        to,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap();
+
+    return Ok(HttpResponse::Ok().json(ReadAnalyticsResponse {
+        from,
+        to,
+        weekly: weekly.into_values().collect(),
+        monthly: monthly.into_values().collect(),
+        muscles,
+        categories,
+        adherence,
+    }));
+}
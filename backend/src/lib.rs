@@ -1,30 +1,52 @@
 pub mod endpoints {
     pub mod nav {
+        pub mod campaigns;
         pub mod character;
+        pub mod group_quests;
         pub mod quests;
         pub mod social;
+        pub mod trades;
         pub mod workouts {
+            pub mod export;
             pub mod history;
+            pub mod import;
             pub mod library;
             pub mod records;
             pub mod routines;
+            pub mod sessions;
         }
     }
     pub mod constants {
         pub mod classes;
+        #[allow(clippy::module_inception)]
         pub mod constants;
         pub mod items;
     }
     pub mod auth;
+    pub mod catalog;
+    pub mod chests;
+    pub mod notifications;
     pub mod onboarding;
     pub mod settings;
     pub mod shop;
     pub mod stats;
 }
 pub mod utils {
+    pub mod activity;
+    pub mod baseline;
+    pub mod chests;
     pub mod coins;
     pub mod env;
+    pub mod generator;
+    pub mod idempotency;
+    pub mod inventory;
     pub mod jwt;
     pub mod level;
+    pub mod matcher;
+    pub mod requirements;
+    pub mod rollups;
+    pub mod schedule;
     pub mod schemas;
+    pub mod streak;
+    pub mod strength;
 }
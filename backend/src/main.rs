@@ -8,7 +8,10 @@ use {
     },
     capstone_project::{
         endpoints,
-        utils::env,
+        utils::{
+            env,
+            rollups::backfill_rollups,
+        },
     },
     env_logger::Env,
     rand::random_range,
@@ -36,6 +39,21 @@ async fn main() -> std::io::Result<()> {
 
     add_default_users(&pool).await;
 
+    // Daily and weekly quest boards
+    actix_web::rt::spawn(endpoints::nav::quests::refresh_quest_boards(
+        pool.clone(),
+    ));
+
+    // Workout sessions that were never finished
+    actix_web::rt::spawn(endpoints::nav::workouts::sessions::close_stale_sessions(
+        pool.clone(),
+    ));
+
+    // Rollups the analytics are read from, writes to the history keep them up to date
+    if let Err(e) = backfill_rollups(&pool).await {
+        log::error!("Error rolling up the history: {}", e);
+    }
+
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
@@ -49,6 +67,8 @@ async fn main() -> std::io::Result<()> {
             // Constants
             .service(endpoints::constants::classes::classes)
             .service(endpoints::constants::items::items)
+            // Catalog
+            .service(endpoints::catalog::browse_items)
             // Auth
             .service(endpoints::auth::sign_up)
             .service(endpoints::auth::login)
@@ -64,17 +84,54 @@ async fn main() -> std::io::Result<()> {
             .service(endpoints::nav::character::update_equipped)
             // Quests
             .service(endpoints::nav::quests::read_quests)
+            .service(endpoints::nav::quests::read_quest)
             .service(endpoints::nav::quests::create_quest)
+            .service(endpoints::nav::quests::abandon_quest)
+            .service(endpoints::nav::quests::reroll_quest)
+            .service(endpoints::nav::quests::claim_quest)
+            // Group quests
+            .service(endpoints::nav::group_quests::read_group_quests)
+            .service(endpoints::nav::group_quests::read_group_quest)
+            .service(endpoints::nav::group_quests::create_group_quest)
+            .service(endpoints::nav::group_quests::invite_to_group_quest)
+            .service(endpoints::nav::group_quests::respond_to_group_quest)
+            .service(endpoints::nav::group_quests::leave_group_quest)
+            // Campaigns
+            .service(endpoints::nav::campaigns::read_campaigns)
+            .service(endpoints::nav::campaigns::start_campaign)
             // Workouts
             .service(endpoints::nav::workouts::history::create_history)
             .service(endpoints::nav::workouts::history::read_history)
+            .service(endpoints::nav::workouts::import::import_history)
+            .service(endpoints::nav::workouts::import::import_activity)
+            .service(endpoints::nav::workouts::import::import_json)
+            .service(endpoints::nav::workouts::export::export_history)
+            .service(endpoints::nav::workouts::history::update_history)
+            .service(endpoints::nav::workouts::history::delete_history)
+            .service(endpoints::nav::workouts::records::read_records)
+            .service(endpoints::nav::workouts::records::read_strength_standards)
+            .service(endpoints::nav::workouts::records::read_one_rep_max)
+            .service(endpoints::nav::workouts::sessions::start_session)
+            .service(endpoints::nav::workouts::sessions::read_current_session)
+            .service(endpoints::nav::workouts::sessions::read_session)
+            .service(endpoints::nav::workouts::sessions::log_set)
+            .service(endpoints::nav::workouts::sessions::delete_set)
+            .service(endpoints::nav::workouts::sessions::pause_session)
+            .service(endpoints::nav::workouts::sessions::resume_session)
+            .service(endpoints::nav::workouts::sessions::finish_session)
+            .service(endpoints::nav::workouts::sessions::abandon_session)
             .service(endpoints::nav::workouts::library::library)
             .service(endpoints::nav::workouts::routines::create_rotuines)
             .service(endpoints::nav::workouts::routines::delete_rotuines)
             .service(endpoints::nav::workouts::routines::read_routines)
             .service(endpoints::nav::workouts::routines::update_rotuines)
+            // Chests
+            .service(endpoints::chests::read_chest_odds)
+            .service(endpoints::chests::read_chests)
+            .service(endpoints::chests::open_chest)
             // Stats
             .service(endpoints::stats::increase_stat)
+            .service(endpoints::stats::read_analytics)
             // Social
             .service(endpoints::nav::social::read_friends)
             .service(endpoints::nav::social::read_friend_detail)
@@ -86,10 +143,20 @@ async fn main() -> std::io::Result<()> {
             .service(endpoints::nav::social::get_outgoing_requests)
             .service(endpoints::nav::social::respond_to_request)
             .service(endpoints::nav::social::remove_friend)
+            // Trades
+            .service(endpoints::nav::trades::send_gift)
+            .service(endpoints::nav::trades::propose_trade)
+            .service(endpoints::nav::trades::get_incoming_trades)
+            .service(endpoints::nav::trades::get_outgoing_trades)
+            .service(endpoints::nav::trades::respond_to_trade)
+            .service(endpoints::nav::trades::cancel_trade)
             // Settings
             .service(endpoints::settings::update_username)
             .service(endpoints::settings::update_name)
             .service(endpoints::settings::update_workout_schedule)
+            .service(endpoints::settings::update_timezone)
+            .service(endpoints::settings::update_quest_tuning)
+            .service(endpoints::settings::update_bodyweight)
             .service(endpoints::settings::update_email)
             .service(endpoints::settings::update_class)
             .service(endpoints::settings::update_password)
@@ -97,6 +164,16 @@ async fn main() -> std::io::Result<()> {
             .service(endpoints::shop::buy_item)
             .service(endpoints::shop::get_shop)
             .service(endpoints::shop::refresh_shop)
+            .service(endpoints::shop::sell_item)
+            .service(endpoints::shop::read_purchases)
+            .service(endpoints::shop::read_purchase)
+            .service(endpoints::shop::refund_purchase)
+            .service(endpoints::shop::read_wishlist)
+            .service(endpoints::shop::add_to_wishlist)
+            .service(endpoints::shop::remove_from_wishlist)
+            // Notifications
+            .service(endpoints::notifications::read_notifications)
+            .service(endpoints::notifications::mark_notification_read)
     })
     .bind(format!("{actix_web_address}:{actix_web_port}"))?
     .run()
@@ -0,0 +1,726 @@
+use {
+    chrono::{
+        DateTime,
+        TimeDelta,
+        Utc,
+    },
+    roxmltree::{
+        Document,
+        Node,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+};
+
+// Climbs smaller than this are GPS noise
+const ELEVATION_HYSTERESIS: f64 = 3.0; // m
+
+const EARTH_RADIUS: f64 = 6_371_008.8; // m
+
+// Longer activities are a broken file or a watch left running
+pub const MAX_DURATION: i64 = 48 * 60 * 60; // seconds
+
+// FIT timestamps count seconds from 1989-12-31 00:00 UTC
+const FIT_EPOCH: i64 = 631_065_600;
+
+// Global FIT message numbers
+const FIT_SESSION: u16 = 18;
+const FIT_RECORD: u16 = 20;
+
+const FIT_TIMESTAMP: u8 = 253;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityFormat {
+    Gpx,
+    Tcx,
+    Fit,
+}
+
+impl ActivityFormat {
+    // FIT files carry ".FIT" in their header, GPX and TCX are told apart by their root element
+    pub fn detect(data: &[u8]) -> Option<ActivityFormat> {
+        if data.get(8..12) == Some(b".FIT".as_slice()) {
+            return Some(ActivityFormat::Fit);
+        }
+        let text = std::str::from_utf8(data).ok()?;
+        let document = Document::parse(text).ok()?;
+        match document.root_element().tag_name().name() {
+            "gpx" => Some(ActivityFormat::Gpx),
+            "TrainingCenterDatabase" => Some(ActivityFormat::Tcx),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sport {
+    Running,
+    Cycling,
+    Walking,
+    Hiking,
+    Rowing,
+    Skating,
+    Other,
+}
+
+impl Sport {
+    // Names used by GPX types and TCX sports, e.g. "running", "Biking" or "hiking"
+    fn from_name(name: &str) -> Sport {
+        let name = name.trim().to_lowercase();
+        match name.as_str() {
+            "running" | "run" | "trail_running" | "treadmill_running" => Sport::Running,
+            "cycling" | "biking" | "ride" | "road_biking" | "mountain_biking" => Sport::Cycling,
+            "walking" | "walk" => Sport::Walking,
+            "hiking" | "hike" => Sport::Hiking,
+            "rowing" | "row" => Sport::Rowing,
+            "skating" | "inline_skating" | "ice_skating" => Sport::Skating,
+            _ => Sport::Other,
+        }
+    }
+
+    fn from_fit(sport: u64) -> Sport {
+        match sport {
+            1 => Sport::Running,
+            2 => Sport::Cycling,
+            11 => Sport::Walking,
+            15 => Sport::Rowing,
+            17 => Sport::Hiking,
+            30 | 33 => Sport::Skating,
+            _ => Sport::Other,
+        }
+    }
+}
+
+// A recorded activity summed up the way it is logged
+#[derive(Clone, Debug, Serialize)]
+pub struct Activity {
+    pub name: Option<String>,
+    pub sport: Sport,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub distance: f32,               // km
+    pub elevation_gain: Option<f32>, // m
+    pub heart_rate: Option<i32>,     // average bpm
+}
+
+impl Activity {
+    pub fn duration(&self) -> TimeDelta {
+        self.ended_at - self.started_at
+    }
+
+    // Seconds per km
+    pub fn pace(&self) -> Option<f32> {
+        if self.distance <= 0.0 {
+            return None;
+        }
+        return Some(self.duration().num_seconds() as f32 / self.distance);
+    }
+}
+
+// A point of the track, anything the device did not record is left out
+#[derive(Default)]
+struct Trackpoint {
+    time: Option<DateTime<Utc>>,
+    position: Option<(f64, f64)>, // latitude, longitude in degrees
+    elevation: Option<f64>,       // m
+    distance: Option<f64>,        // m since the start
+    heart_rate: Option<f64>,
+}
+
+// Totals some devices write next to the track, they are preferred over the ones of the track
+#[derive(Default)]
+struct Summary {
+    started_at: Option<DateTime<Utc>>,
+    duration: Option<f64>, // seconds
+    distance: Option<f64>, // m
+    elevation_gain: Option<f64>,
+    heart_rate: Option<f64>,
+}
+
+pub fn parse_activity(data: &[u8], format: Option<ActivityFormat>) -> Result<Activity, String> {
+    let format = match format {
+        Some(format) => format,
+        None => ActivityFormat::detect(data)
+            .ok_or_else(|| String::from("Not a GPX, TCX or FIT file"))?,
+    };
+
+    if format == ActivityFormat::Fit {
+        return parse_fit(data);
+    }
+
+    let text = std::str::from_utf8(data).map_err(|_| String::from("The file is not UTF-8"))?;
+    let document =
+        Document::parse(text).map_err(|e| format!("Failed to read the file: {}", e))?;
+    match format {
+        ActivityFormat::Gpx => parse_gpx(&document),
+        ActivityFormat::Tcx => parse_tcx(&document),
+        ActivityFormat::Fit => unreachable!(),
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
+
+fn child_number(node: Node, name: &str) -> Option<f64> {
+    child_text(node, name)
+        .and_then(|text| text.parse().ok())
+        .filter(|number: &f64| number.is_finite())
+}
+
+fn child_time(node: Node, name: &str) -> Option<DateTime<Utc>> {
+    child_text(node, name)
+        .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn parse_gpx(document: &Document) -> Result<Activity, String> {
+    let root = document.root_element();
+    let track = child(root, "trk").ok_or_else(|| String::from("The GPX file has no track"))?;
+
+    let name = child_text(track, "name")
+        .or_else(|| child(root, "metadata").and_then(|metadata| child_text(metadata, "name")))
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+    let sport = child_text(track, "type").map_or(Sport::Other, Sport::from_name);
+
+    let points = track
+        .descendants()
+        .filter(|node| node.tag_name().name() == "trkpt")
+        .map(|point| Trackpoint {
+            time: child_time(point, "time"),
+            position: point
+                .attribute("lat")
+                .zip(point.attribute("lon"))
+                .and_then(|(lat, lon)| Some((lat.parse().ok()?, lon.parse().ok()?))),
+            elevation: child_number(point, "ele"),
+            distance: None,
+            // Garmin's TrackPointExtension
+            heart_rate: point
+                .descendants()
+                .find(|node| node.tag_name().name() == "hr")
+                .and_then(|node| node.text())
+                .and_then(|text| text.trim().parse().ok()),
+        })
+        .collect();
+
+    return summarize(name, sport, points, Summary::default());
+}
+
+fn parse_tcx(document: &Document) -> Result<Activity, String> {
+    let activity = document
+        .descendants()
+        .find(|node| node.tag_name().name() == "Activity")
+        .ok_or_else(|| String::from("The TCX file has no activity"))?;
+
+    let name = child_text(activity, "Notes")
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+    let sport = activity
+        .attribute("Sport")
+        .map_or(Sport::Other, Sport::from_name);
+
+    let laps: Vec<Node> = activity
+        .children()
+        .filter(|node| node.tag_name().name() == "Lap")
+        .collect();
+    let summary = Summary {
+        started_at: child_time(activity, "Id"),
+        duration: laps
+            .iter()
+            .map(|lap| child_number(*lap, "TotalTimeSeconds"))
+            .sum::<Option<f64>>()
+            .filter(|total| *total > 0.0),
+        distance: laps
+            .iter()
+            .map(|lap| child_number(*lap, "DistanceMeters"))
+            .sum::<Option<f64>>()
+            .filter(|total| *total > 0.0),
+        ..Summary::default()
+    };
+
+    let points = activity
+        .descendants()
+        .filter(|node| node.tag_name().name() == "Trackpoint")
+        .map(|point| Trackpoint {
+            time: child_time(point, "Time"),
+            position: child(point, "Position").and_then(|position| {
+                Some((
+                    child_number(position, "LatitudeDegrees")?,
+                    child_number(position, "LongitudeDegrees")?,
+                ))
+            }),
+            elevation: child_number(point, "AltitudeMeters"),
+            distance: child_number(point, "DistanceMeters"),
+            heart_rate: child(point, "HeartRateBpm").and_then(|bpm| child_number(bpm, "Value")),
+        })
+        .collect();
+
+    return summarize(name, sport, points, summary);
+}
+
+// Reads FIT files the way the FIT SDK lays them out: a header, then definition messages describing
+// the fields of the data messages that follow them
+fn parse_fit(data: &[u8]) -> Result<Activity, String> {
+    let invalid = || String::from("Invalid FIT file");
+
+    let header_size = *data.first().ok_or_else(invalid)? as usize;
+    let data_size = u32::from_le_bytes(
+        data.get(4..8)
+            .ok_or_else(invalid)?
+            .try_into()
+            .unwrap(),
+    ) as usize;
+    let records = data
+        .get(header_size..header_size + data_size)
+        .ok_or_else(invalid)?;
+
+    let mut definitions: [Option<FitDefinition>; 16] = Default::default();
+    let mut points: Vec<Trackpoint> = Vec::new();
+    let mut summary = Summary::default();
+    let mut sport = Sport::Other;
+    let mut last_timestamp: u32 = 0;
+
+    let mut reader = FitReader { data: records, position: 0 };
+    while reader.position < records.len() {
+        let header = reader.bytes(1).ok_or_else(invalid)?[0];
+
+        // Compressed timestamp header, the time is an offset from the last timestamp
+        let (local, timestamp) = if header & 0x80 != 0 {
+            let offset = (header & 0x1F) as u32;
+            let mut timestamp = (last_timestamp & !0x1F).wrapping_add(offset);
+            if offset < last_timestamp & 0x1F {
+                timestamp = timestamp.wrapping_add(0x20);
+            }
+            ((header >> 5) & 0x03, Some(timestamp))
+        } else {
+            (header & 0x0F, None)
+        };
+
+        if header & 0xC0 == 0x40 {
+            definitions[local as usize] =
+                Some(FitDefinition::read(&mut reader, header & 0x20 != 0).ok_or_else(invalid)?);
+            continue;
+        }
+
+        let definition = definitions[local as usize]
+            .as_ref()
+            .ok_or_else(invalid)?;
+        let mut fields: Vec<(u8, Option<f64>)> = Vec::with_capacity(definition.fields.len());
+        for field in &definition.fields {
+            let bytes = reader.bytes(field.size).ok_or_else(invalid)?;
+            fields.push((field.number, fit_value(bytes, field.base_type, definition.big_endian)));
+        }
+        reader.bytes(definition.developer_size).ok_or_else(invalid)?;
+
+        let field = |number: u8| {
+            fields
+                .iter()
+                .find(|(field, _)| *field == number)
+                .and_then(|(_, value)| *value)
+        };
+
+        let timestamp = field(FIT_TIMESTAMP).map(|value| value as u32).or(timestamp);
+        if let Some(timestamp) = timestamp {
+            last_timestamp = timestamp;
+        }
+        let time = |timestamp: u32| DateTime::from_timestamp(FIT_EPOCH + timestamp as i64, 0);
+
+        match definition.message {
+            FIT_RECORD => points.push(Trackpoint {
+                time: timestamp.and_then(time),
+                // Semicircles, 2^31 of them make 180 degrees
+                position: field(0)
+                    .zip(field(1))
+                    .map(|(lat, lon)| (lat * 180.0 / 2f64.powi(31), lon * 180.0 / 2f64.powi(31))),
+                elevation: field(78)
+                    .or(field(2))
+                    .map(|altitude| altitude / 5.0 - 500.0),
+                distance: field(5).map(|distance| distance / 100.0),
+                heart_rate: field(3),
+            }),
+            FIT_SESSION => {
+                summary = Summary {
+                    started_at: field(2).and_then(|start| time(start as u32)),
+                    duration: field(7).map(|elapsed| elapsed / 1000.0),
+                    distance: field(9).map(|distance| distance / 100.0),
+                    elevation_gain: field(22),
+                    heart_rate: field(16),
+                };
+                sport = field(5).map_or(Sport::Other, |sport| Sport::from_fit(sport as u64));
+            }
+            _ => {}
+        }
+    }
+
+    return summarize(None, sport, points, summary);
+}
+
+struct FitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> FitReader<'a> {
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position + count)?;
+        self.position += count;
+        return Some(bytes);
+    }
+}
+
+struct FitField {
+    number: u8,
+    size: usize,
+    base_type: u8,
+}
+
+struct FitDefinition {
+    message: u16,
+    big_endian: bool,
+    fields: Vec<FitField>,
+    developer_size: usize, // developer fields are skipped
+}
+
+impl FitDefinition {
+    fn read(reader: &mut FitReader, developer_fields: bool) -> Option<FitDefinition> {
+        let header = reader.bytes(5)?;
+        let big_endian = header[1] == 1;
+        let message = match big_endian {
+            true => u16::from_be_bytes([header[2], header[3]]),
+            false => u16::from_le_bytes([header[2], header[3]]),
+        };
+
+        let fields = reader
+            .bytes(header[4] as usize * 3)?
+            .chunks(3)
+            .map(|field| FitField {
+                number: field[0],
+                size: field[1] as usize,
+                base_type: field[2],
+            })
+            .collect();
+
+        let mut developer_size = 0;
+        if developer_fields {
+            let count = reader.bytes(1)?[0] as usize;
+            developer_size = reader
+                .bytes(count * 3)?
+                .chunks(3)
+                .map(|field| field[1] as usize)
+                .sum();
+        }
+
+        return Some(FitDefinition {
+            message,
+            big_endian,
+            fields,
+            developer_size,
+        });
+    }
+}
+
+// Integer fields only, None when the device marked the value as invalid
+fn fit_value(bytes: &[u8], base_type: u8, big_endian: bool) -> Option<f64> {
+    if !matches!(bytes.len(), 1 | 2 | 4) {
+        return None;
+    }
+
+    let mut value: u64 = 0;
+    for index in 0..bytes.len() {
+        let byte = match big_endian {
+            true => bytes[index],
+            false => bytes[bytes.len() - 1 - index],
+        };
+        value = value << 8 | byte as u64;
+    }
+
+    let bits = bytes.len() as u32 * 8;
+    let signed = matches!(base_type & 0x1F, 0x01 | 0x03 | 0x05);
+    if signed {
+        if value == (1 << (bits - 1)) - 1 {
+            return None;
+        }
+        let shift = 64 - bits;
+        return Some(((value << shift) as i64 >> shift) as f64);
+    }
+
+    // The z types use 0 as their invalid value
+    let invalid = match base_type & 0x1F {
+        0x0A..=0x0C => 0,
+        _ => (1 << bits) - 1,
+    };
+    if value == invalid {
+        return None;
+    }
+    return Some(value as f64);
+}
+
+// Distance in m between two points on the surface of the earth
+fn haversine((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1).to_radians() / 2.0).sin().powi(2);
+    return 2.0 * EARTH_RADIUS * a.sqrt().asin();
+}
+
+fn summarize(
+    name: Option<String>,
+    sport: Sport,
+    points: Vec<Trackpoint>,
+    summary: Summary,
+) -> Result<Activity, String> {
+    let first = points.iter().find_map(|point| point.time);
+    let last = points.iter().rev().find_map(|point| point.time);
+
+    let started_at = summary
+        .started_at
+        .or(first)
+        .ok_or_else(|| String::from("The activity has no start time"))?;
+    let ended_at = match summary.duration {
+        Some(duration) => TimeDelta::try_milliseconds((duration * 1000.0) as i64)
+            .and_then(|duration| started_at.checked_add_signed(duration))
+            .ok_or_else(|| String::from("Invalid duration"))?,
+        None => last.unwrap_or(started_at),
+    };
+    if ended_at <= started_at {
+        return Err(String::from("The activity has no duration"));
+    }
+    if (ended_at - started_at).num_seconds() > MAX_DURATION {
+        return Err(String::from("Activities can last up to 48 hours"));
+    }
+
+    // Devices that measure the distance themselves are more accurate than the GPS track
+    let distance = summary
+        .distance
+        .or_else(|| {
+            points
+                .iter()
+                .filter_map(|point| point.distance)
+                .max_by(f64::total_cmp)
+        })
+        .unwrap_or_else(|| {
+            let positions: Vec<(f64, f64)> =
+                points.iter().filter_map(|point| point.position).collect();
+            positions
+                .windows(2)
+                .map(|pair| haversine(pair[0], pair[1]))
+                .sum()
+        });
+
+    let elevation_gain = summary.elevation_gain.or_else(|| {
+        let mut elevations = points.iter().filter_map(|point| point.elevation);
+        let mut reference = elevations.next()?;
+        let mut gain = 0.0;
+        for elevation in elevations {
+            if elevation > reference + ELEVATION_HYSTERESIS {
+                gain += elevation - reference;
+                reference = elevation;
+            } else if elevation < reference - ELEVATION_HYSTERESIS {
+                reference = elevation;
+            }
+        }
+        Some(gain)
+    });
+
+    let heart_rate = summary.heart_rate.or_else(|| {
+        let rates: Vec<f64> = points.iter().filter_map(|point| point.heart_rate).collect();
+        match rates.is_empty() {
+            true => None,
+            false => Some(rates.iter().sum::<f64>() / rates.len() as f64),
+        }
+    });
+
+    return Ok(Activity {
+        name,
+        sport,
+        started_at,
+        ended_at,
+        distance: (distance / 1000.0) as f32,
+        elevation_gain: elevation_gain.map(|gain| gain.round() as f32),
+        heart_rate: heart_rate.map(|rate| rate.round() as i32),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        chrono::TimeZone,
+    };
+
+    const RUN_GPX: &[u8] = include_bytes!("../../tests/fixtures/activities/run.gpx");
+    const RIDE_TCX: &[u8] = include_bytes!("../../tests/fixtures/activities/ride.tcx");
+    const RUN_FIT: &[u8] = include_bytes!("../../tests/fixtures/activities/run.fit");
+    // The records of run.fit without its session
+    const TRACK_FIT: &[u8] = include_bytes!("../../tests/fixtures/activities/track.fit");
+
+    fn time(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, hour, minute, second).unwrap()
+    }
+
+    fn point(seconds: i64, elevation: f64) -> Trackpoint {
+        Trackpoint {
+            time: Some(time(6, 0, 0) + TimeDelta::seconds(seconds)),
+            elevation: Some(elevation),
+            ..Trackpoint::default()
+        }
+    }
+
+    #[test]
+    fn formats_are_detected() {
+        assert_eq!(ActivityFormat::detect(RUN_GPX), Some(ActivityFormat::Gpx));
+        assert_eq!(ActivityFormat::detect(RIDE_TCX), Some(ActivityFormat::Tcx));
+        assert_eq!(ActivityFormat::detect(RUN_FIT), Some(ActivityFormat::Fit));
+        assert_eq!(ActivityFormat::detect(b"Date,Workout Name"), None);
+    }
+
+    #[test]
+    fn gpx_tracks_are_summed_up() {
+        let activity = parse_activity(RUN_GPX, None).unwrap();
+        assert_eq!(activity.name.as_deref(), Some("Morning Run"));
+        assert_eq!(activity.sport, Sport::Running);
+        assert_eq!(activity.started_at, time(6, 0, 0));
+        assert_eq!(activity.ended_at, time(6, 5, 0));
+        assert!((activity.distance - 1.0).abs() < 0.01);
+        assert_eq!(activity.elevation_gain, Some(10.0));
+        assert_eq!(activity.heart_rate, Some(150));
+    }
+
+    #[test]
+    fn tcx_laps_are_preferred_over_the_track() {
+        let activity = parse_activity(RIDE_TCX, None).unwrap();
+        assert_eq!(activity.name.as_deref(), Some("Evening Ride"));
+        assert_eq!(activity.sport, Sport::Cycling);
+        assert_eq!(activity.started_at, Utc.with_ymd_and_hms(2024, 5, 2, 17, 30, 0).unwrap());
+        assert_eq!(activity.duration(), TimeDelta::minutes(15));
+        assert_eq!(activity.distance, 7.5);
+        assert_eq!(activity.pace(), Some(120.0));
+        assert_eq!(activity.elevation_gain, Some(10.0));
+        assert_eq!(activity.heart_rate, Some(130));
+    }
+
+    #[test]
+    fn fit_sessions_are_preferred_over_the_records() {
+        let activity = parse_activity(RUN_FIT, None).unwrap();
+        assert_eq!(activity.name, None);
+        assert_eq!(activity.sport, Sport::Running);
+        assert_eq!(activity.started_at, Utc.with_ymd_and_hms(2024, 5, 3, 7, 0, 14).unwrap());
+        assert_eq!(activity.duration(), TimeDelta::seconds(16));
+        assert_eq!(activity.distance, 0.0225);
+        assert_eq!(activity.elevation_gain, Some(10.0));
+        assert_eq!(activity.heart_rate, Some(155));
+    }
+
+    #[test]
+    fn fit_compressed_timestamps_roll_over() {
+        // The first record is at a time ending in 30 seconds of 32, the next ones are 4 and 10
+        // seconds into the following rollovers
+        let activity = parse_activity(TRACK_FIT, Some(ActivityFormat::Fit)).unwrap();
+        assert_eq!(activity.sport, Sport::Other);
+        assert_eq!(activity.started_at, Utc.with_ymd_and_hms(2024, 5, 3, 7, 0, 14).unwrap());
+        assert_eq!(activity.duration(), TimeDelta::seconds(12));
+        assert_eq!(activity.distance, 0.222);
+        // The last record marks its altitude and heart rate as invalid
+        assert_eq!(activity.elevation_gain, Some(10.0));
+        assert_eq!(activity.heart_rate, Some(155));
+    }
+
+    #[test]
+    fn truncated_fit_files_are_rejected() {
+        assert!(parse_fit(&RUN_FIT[..RUN_FIT.len() - 10]).is_err());
+        assert!(parse_fit(&RUN_FIT[..6]).is_err());
+        assert!(parse_fit(&[]).is_err());
+    }
+
+    #[test]
+    fn fit_values_are_read_with_their_byte_order() {
+        assert_eq!(fit_value(&[0x34, 0x12], 0x84, false), Some(4660.0));
+        assert_eq!(fit_value(&[0x12, 0x34], 0x84, true), Some(4660.0));
+        assert_eq!(fit_value(&[0xFE, 0xFF, 0xFF, 0xFF], 0x85, false), Some(-2.0));
+        assert_eq!(fit_value(&[0x80], 0x01, false), Some(-128.0));
+        assert_eq!(fit_value(&[1, 2, 3], 0x86, false), None);
+    }
+
+    #[test]
+    fn invalid_fit_values_are_left_out() {
+        assert_eq!(fit_value(&[0xFF], 0x02, false), None);
+        assert_eq!(fit_value(&[0xFF, 0xFF], 0x84, false), None);
+        assert_eq!(fit_value(&[0xFF, 0xFF, 0xFF, 0x7F], 0x85, false), None);
+        assert_eq!(fit_value(&[0x00, 0x00], 0x8B, false), None);
+        assert_eq!(fit_value(&[0x00, 0x00], 0x84, false), Some(0.0));
+    }
+
+    #[test]
+    fn non_finite_numbers_of_xml_files_are_left_out() {
+        let document = Document::parse(
+            "<trkpt><ele>NaN</ele><hr>inf</hr><cad>-inf</cad><speed>2.5</speed></trkpt>",
+        )
+        .unwrap();
+        let point = document.root_element();
+        assert_eq!(child_number(point, "ele"), None);
+        assert_eq!(child_number(point, "hr"), None);
+        assert_eq!(child_number(point, "cad"), None);
+        assert_eq!(child_number(point, "speed"), Some(2.5));
+    }
+
+    #[test]
+    fn haversine_measures_along_the_surface() {
+        assert_eq!(haversine((45.0, 7.0), (45.0, 7.0)), 0.0);
+        // A degree of latitude, and of longitude on the equator
+        let degree = 2.0 * std::f64::consts::PI * EARTH_RADIUS / 360.0;
+        assert!((haversine((0.0, 0.0), (1.0, 0.0)) - degree).abs() < 0.001);
+        assert!((haversine((0.0, 0.0), (0.0, 1.0)) - degree).abs() < 0.001);
+        assert!((haversine((60.0, 0.0), (60.0, 1.0)) - degree / 2.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn elevation_noise_is_not_climbed() {
+        let elevations = [100.0, 102.0, 101.0, 104.0, 103.0, 110.0, 95.0, 99.0, 100.0];
+        let points = elevations
+            .iter()
+            .enumerate()
+            .map(|(index, elevation)| point(index as i64, *elevation))
+            .collect();
+        let activity = summarize(None, Sport::Other, points, Summary::default()).unwrap();
+        assert_eq!(activity.elevation_gain, Some(14.0));
+    }
+
+    #[test]
+    fn summarize_rejects_invalid_durations() {
+        let summary = |duration: f64| Summary {
+            started_at: Some(time(6, 0, 0)),
+            duration: Some(duration),
+            ..Summary::default()
+        };
+        let summarize = |duration: f64| summarize(None, Sport::Other, vec![], summary(duration));
+
+        assert!(summarize(60.0).is_ok());
+        assert_eq!(summarize(1e30).unwrap_err(), "Invalid duration");
+        assert_eq!(summarize(f64::INFINITY).unwrap_err(), "Invalid duration");
+        assert_eq!(summarize(0.0).unwrap_err(), "The activity has no duration");
+        assert_eq!(summarize(-60.0).unwrap_err(), "The activity has no duration");
+        assert!(summarize(MAX_DURATION as f64).is_ok());
+        assert_eq!(
+            summarize(MAX_DURATION as f64 + 1.0).unwrap_err(),
+            "Activities can last up to 48 hours"
+        );
+    }
+
+    #[test]
+    fn long_tracks_are_rejected() {
+        let points = vec![point(0, 0.0), point(MAX_DURATION + 60, 0.0)];
+        assert!(summarize(None, Sport::Other, points, Summary::default()).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use {
+    crate::utils::{
+        requirements::QuestMetric,
+        schemas::{
+            Exercise,
+            ExerciseCategory,
+            ExerciseMuscle,
+        },
+    },
+    chrono::Utc,
+    sqlx::{
+        PgConnection,
+        types::Json,
+    },
+    strum::VariantArray,
+};
+
+// How far back the baseline looks
+pub const BASELINE_WEEKS: i32 = 4;
+
+// How the user has been training lately, adaptive quests are sized against it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrainingBaseline {
+    pub workouts: i32,
+    pub workouts_per_week: f32,
+    pub duration: f32, // average minutes
+    pub per_workout: Vec<(QuestMetric, f32)>, // average of each metric over a workout
+    pub categories: Vec<(ExerciseCategory, i64)>, // workouts that included the category
+    pub muscles: Vec<(ExerciseMuscle, i64)>, // workouts that trained the muscle as a primary muscle
+}
+
+impl TrainingBaseline {
+    pub async fn load(
+        conn: &mut PgConnection,
+        user_id: i32,
+    ) -> Result<TrainingBaseline, sqlx::Error> {
+        let history = sqlx::query!(
+            r#"
+                SELECT date, duration, exercises as "exercises: Json<Vec<Exercise>>"
+                FROM history
+                WHERE user_id = $1 AND date > CURRENT_DATE - 7 * $2::INTEGER
+            "#,
+            user_id,
+            BASELINE_WEEKS
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if history.is_empty() {
+            return Ok(TrainingBaseline::default());
+        }
+
+        let categories = sqlx::query!(
+            r#"
+                SELECT e.category as "category: ExerciseCategory", COUNT(DISTINCT h.id) as "workouts!"
+                FROM history h
+                CROSS JOIN jsonb_array_elements(h.exercises) AS logged
+                JOIN exercises e ON e.id = logged->>'id'
+                WHERE h.user_id = $1 AND h.date > CURRENT_DATE - 7 * $2::INTEGER
+                GROUP BY e.category
+                ORDER BY e.category
+            "#,
+            user_id,
+            BASELINE_WEEKS
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|record| (record.category, record.workouts))
+        .collect();
+
+        let muscles = sqlx::query!(
+            r#"
+                SELECT muscle as "muscle!: ExerciseMuscle", COUNT(DISTINCT h.id) as "workouts!"
+                FROM history h
+                CROSS JOIN jsonb_array_elements(h.exercises) AS logged
+                JOIN exercises e ON e.id = logged->>'id'
+                CROSS JOIN UNNEST(e.primary_muscles) AS muscle
+                WHERE h.user_id = $1 AND h.date > CURRENT_DATE - 7 * $2::INTEGER
+                GROUP BY muscle
+                ORDER BY muscle
+            "#,
+            user_id,
+            BASELINE_WEEKS
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|record| (record.muscle, record.workouts))
+        .collect();
+
+        let workouts = history.len() as f32;
+
+        // Users that started recently are averaged over the weeks they have trained
+        let first = history.iter().map(|workout| workout.date).min().unwrap();
+        let days = (Utc::now().date_naive() - first).num_days() + 1;
+        let weeks = (days as f32 / 7.0).clamp(1.0, BASELINE_WEEKS as f32);
+
+        let per_workout = QuestMetric::VARIANTS
+            .iter()
+            .map(|&metric| {
+                let total: f32 = history
+                    .iter()
+                    .flat_map(|workout| workout.exercises.iter())
+                    .map(|exercise| metric.measure(exercise))
+                    .sum();
+                (metric, total / workouts)
+            })
+            .collect();
+
+        return Ok(TrainingBaseline {
+            workouts: history.len() as i32,
+            workouts_per_week: workouts / weeks,
+            duration: history
+                .iter()
+                .map(|workout| workout.duration as f32)
+                .sum::<f32>()
+                / workouts,
+            per_workout,
+            categories,
+            muscles,
+        });
+    }
+
+    pub fn per_workout(&self, metric: QuestMetric) -> f32 {
+        self.per_workout
+            .iter()
+            .find(|(other, _)| *other == metric)
+            .map_or(0.0, |&(_, amount)| amount)
+    }
+
+    // Number of workouts in the baseline that trained the muscle
+    pub fn trained(&self, muscle: ExerciseMuscle) -> i64 {
+        self.muscles
+            .iter()
+            .find(|(other, _)| *other == muscle)
+            .map_or(0, |&(_, workouts)| workouts)
+    }
+}
+
+// User preferences for how demanding adaptive quests are
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuestTuning {
+    // each difficulty step asks for this much more than the baseline, 0.1 is 10%
+    pub stretch: f32,
+    // requirements on muscles favour the ones trained the least
+    pub target_neglected_muscles: bool,
+}
+
+impl QuestTuning {
+    pub async fn load(conn: &mut PgConnection, user_id: i32) -> Result<QuestTuning, sqlx::Error> {
+        let tuning = sqlx::query_as!(
+            QuestTuning,
+            r#"
+                SELECT quest_stretch as "stretch", target_neglected_muscles
+                FROM settings
+                WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        return Ok(tuning);
+    }
+}
@@ -0,0 +1,160 @@
+use {
+    crate::utils::{
+        jwt::AuthenticatedUser,
+        schemas::{
+            ChestDrop,
+            ChestSource,
+            ItemRarity,
+        },
+    },
+    rand::{
+        Rng,
+        seq::IndexedRandom,
+    },
+    sqlx::PgConnection,
+};
+
+pub const LEVEL_UP_CHEST: &str = "Level Up Chest";
+
+// Returns the id of the granted chest, None when there is no chest with this name
+pub async fn grant_chest(
+    user: &AuthenticatedUser,
+    conn: &mut PgConnection,
+    chest: &str,
+    source: ChestSource,
+) -> Result<Option<i32>, sqlx::Error> {
+    return sqlx::query_scalar!(
+        r#"
+            INSERT INTO user_chests (user_id, chest_id, source)
+            SELECT $1, id, $3
+            FROM chests
+            WHERE name = $2
+            RETURNING id
+        "#,
+        user.id,
+        chest,
+        source as ChestSource,
+    )
+    .fetch_optional(conn)
+    .await;
+}
+
+// The roll after this many misses in a row is guaranteed to be rare or better
+pub fn pity_reached(misses: i32, pity_threshold: i32) -> bool {
+    misses >= pity_threshold
+}
+
+// Picks the rarity of a drop from the chest's weights.
+// Once the pity counter is reached only rare or better rarities can drop.
+pub fn roll_rarity<R: Rng + ?Sized>(
+    drops: &[ChestDrop],
+    pity_reached: bool,
+    rng: &mut R,
+) -> Option<ItemRarity> {
+    let rare_drops: Vec<ChestDrop> = drops
+        .iter()
+        .filter(|drop| drop.rarity.is_rare_or_better())
+        .copied()
+        .collect();
+
+    let pool = if pity_reached && rare_drops.iter().any(|drop| drop.weight > 0) {
+        &rare_drops[..]
+    } else {
+        drops
+    };
+
+    return pool
+        .choose_weighted(rng, |drop| drop.weight.max(0))
+        .ok()
+        .map(|drop| drop.rarity);
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        rand::SeedableRng,
+        rand_chacha::ChaCha8Rng,
+        std::collections::HashMap,
+    };
+
+    const ROLLS: usize = 100_000;
+
+    fn drops() -> Vec<ChestDrop> {
+        [
+            (ItemRarity::Common, 60),
+            (ItemRarity::Uncommon, 25),
+            (ItemRarity::Rare, 10),
+            (ItemRarity::Epic, 4),
+            (ItemRarity::Legendary, 1),
+        ]
+        .map(|(rarity, weight)| ChestDrop { rarity, weight })
+        .to_vec()
+    }
+
+    fn roll_counts(drops: &[ChestDrop], pity_reached: bool) -> HashMap<ItemRarity, usize> {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut counts = HashMap::new();
+        for _ in 0..ROLLS {
+            let rarity = roll_rarity(drops, pity_reached, &mut rng).unwrap();
+            *counts.entry(rarity).or_insert(0) += 1;
+        }
+        return counts;
+    }
+
+    #[test]
+    fn pity_fires_at_the_threshold() {
+        assert!(!pity_reached(0, 10));
+        assert!(!pity_reached(9, 10));
+        assert!(pity_reached(10, 10));
+        assert!(pity_reached(11, 10));
+    }
+
+    #[test]
+    fn pity_only_drops_rare_or_better() {
+        let counts = roll_counts(&drops(), true);
+        assert!(counts.keys().all(|rarity| rarity.is_rare_or_better()));
+        assert!(counts.contains_key(&ItemRarity::Legendary));
+    }
+
+    #[test]
+    fn pity_falls_back_to_the_whole_table_without_rare_drops() {
+        let drops: Vec<ChestDrop> = drops()
+            .into_iter()
+            .filter(|drop| !drop.rarity.is_rare_or_better())
+            .collect();
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        assert!(roll_rarity(&drops, true, &mut rng).is_some());
+    }
+
+    #[test]
+    fn drops_follow_the_published_odds() {
+        let drops = drops();
+        let total: i32 = drops.iter().map(|drop| drop.weight).sum();
+        let counts = roll_counts(&drops, false);
+
+        for drop in &drops {
+            let expected = drop.weight as f64 / total as f64;
+            let rolled = *counts.get(&drop.rarity).unwrap_or(&0) as f64 / ROLLS as f64;
+            assert!(
+                (rolled - expected).abs() < 0.01,
+                "{:?} dropped {} of the time, expected {}",
+                drop.rarity,
+                rolled,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn empty_tables_drop_nothing() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        assert_eq!(roll_rarity(&[], false, &mut rng), None);
+
+        let zero = [ChestDrop {
+            rarity: ItemRarity::Common,
+            weight: 0,
+        }];
+        assert_eq!(roll_rarity(&zero, false, &mut rng), None);
+    }
+}
@@ -1,15 +1,13 @@
 use {
     crate::utils::jwt::AuthenticatedUser,
-    actix_web::{
-        HttpResponse,
-        web,
-    },
-    sqlx::PgPool,
+    actix_web::HttpResponse,
+    sqlx::PgConnection,
 };
 
+// Negative amounts take coins back, the balance goes negative if they were already spent
 pub async fn add_coins(
     user: &AuthenticatedUser,
-    pool: &web::Data<PgPool>,
+    conn: &mut PgConnection,
     coins: i32,
 ) -> Result<HttpResponse, actix_web::Error> {
     let _query = sqlx::query!(
@@ -21,7 +19,7 @@ pub async fn add_coins(
         user.id,
         coins,
     )
-    .execute(pool.get_ref())
+    .execute(conn)
     .await
     .unwrap();
 
@@ -0,0 +1,693 @@
+use {
+    crate::utils::{
+        baseline::{
+            QuestTuning,
+            TrainingBaseline,
+        },
+        requirements::{
+            IncludesCategory,
+            MinimumSets,
+            QuestGoal,
+            QuestMetric,
+            QuestRequirement,
+            TargetsMuscle,
+            TimeOfDay,
+            WorkoutDuration,
+        },
+        schemas::{
+            ExerciseCategory,
+            ExerciseMuscle,
+            QuestDifficulty,
+        },
+    },
+    chrono::NaiveTime,
+    rand::{
+        SeedableRng,
+        seq::{
+            IndexedRandom,
+            SliceRandom,
+        },
+    },
+    rand_chacha::ChaCha8Rng,
+    sqlx::PgConnection,
+    strum::VariantArray,
+};
+
+// ChaCha8 gives the same stream for a seed on every platform and version, unlike StdRng
+pub type QuestRng = ChaCha8Rng;
+
+// Largest integer JSON clients can read back exactly, so seeds can be copied from bug reports
+const MAX_SEED: i64 = (1 << 53) - 1;
+
+pub fn new_seed() -> i64 {
+    rand::random_range(0..=MAX_SEED)
+}
+
+pub fn quest_rng(seed: i64) -> QuestRng {
+    QuestRng::seed_from_u64(seed as u64)
+}
+
+// Where quest names are drawn from
+pub trait WordSource {
+    fn adjectives(&self) -> &[String];
+    fn nouns(&self) -> &[String];
+}
+
+pub struct Words {
+    pub adjectives: Vec<String>,
+    pub nouns: Vec<String>,
+}
+
+impl Words {
+    // Sorted so a seed picks the same words as long as the table is unchanged
+    pub async fn load(conn: &mut PgConnection) -> Result<Words, sqlx::Error> {
+        let words = sqlx::query!(
+            r#"
+                SELECT word, category::text as "category!"
+                FROM words
+                WHERE category IN ('adjective', 'noun')
+                ORDER BY word
+            "#
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let (adjectives, nouns): (Vec<_>, Vec<_>) = words
+            .into_iter()
+            .partition(|word| word.category == "adjective");
+
+        return Ok(Words {
+            adjectives: adjectives.into_iter().map(|word| word.word).collect(),
+            nouns: nouns.into_iter().map(|word| word.word).collect(),
+        });
+    }
+}
+
+impl WordSource for Words {
+    fn adjectives(&self) -> &[String] {
+        &self.adjectives
+    }
+
+    fn nouns(&self) -> &[String] {
+        &self.nouns
+    }
+}
+
+// Everything generated from a seed, the caller decides the board, deadline and owner
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratedQuest {
+    pub seed: Option<i64>, // None when the seed alone doesn't reproduce the quest
+    pub name: String,
+    pub number_of_workouts_needed: i32,
+    pub requirements: Vec<QuestRequirement>,
+    pub goal: Option<QuestGoal>,
+}
+
+// Adjective and noun pair, e.g. "robust rambutan".
+// Always drawn first, so every kind of quest gets the same name from a seed.
+fn name(rng: &mut QuestRng, words: &impl WordSource) -> String {
+    let adjective = words.adjectives().choose(rng).map_or("", String::as_str);
+    let noun = words.nouns().choose(rng).map_or("", String::as_str);
+
+    return format!("{} {}", adjective, noun).trim().to_string();
+}
+
+// Time windows random quests can ask for
+const TIME_WINDOWS: [(u32, u32); 3] = [(5, 12), (12, 17), (17, 23)];
+
+// Picks distinct kinds of requirements, harder quests get more of them
+fn requirements(difficulty: QuestDifficulty, rng: &mut QuestRng) -> Vec<QuestRequirement> {
+    let mut kinds = vec![
+        "workout_duration",
+        "exercise_category",
+        "exercise_muscle",
+        "minimum_sets",
+        "time_of_day",
+    ];
+    kinds.shuffle(rng);
+
+    return kinds
+        .into_iter()
+        .take(difficulty.requirements())
+        .map(|kind| match kind {
+            "workout_duration" => QuestRequirement::WorkoutDuration(WorkoutDuration {
+                minutes: difficulty.workout_duration(rng),
+            }),
+            "exercise_category" => QuestRequirement::ExerciseCategory(IncludesCategory {
+                category: *ExerciseCategory::VARIANTS.choose(rng).unwrap(),
+            }),
+            "exercise_muscle" => QuestRequirement::ExerciseMuscle(TargetsMuscle {
+                muscle: *ExerciseMuscle::VARIANTS.choose(rng).unwrap(),
+            }),
+            "minimum_sets" => QuestRequirement::MinimumSets(MinimumSets {
+                sets: difficulty.minimum_sets(rng),
+            }),
+            "time_of_day" => {
+                let (start, end) = *TIME_WINDOWS.choose(rng).unwrap();
+                QuestRequirement::TimeOfDay(TimeOfDay {
+                    start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+                })
+            }
+            _ => unreachable!(),
+        })
+        .collect();
+}
+
+// Counts workouts that meet random requirements
+pub fn random_quest(
+    seed: i64,
+    difficulty: QuestDifficulty,
+    words: &impl WordSource,
+) -> GeneratedQuest {
+    let mut rng = quest_rng(seed);
+    let name = name(&mut rng, words);
+    let number_of_workouts_needed = difficulty.number_of_workouts_needed(&mut rng);
+    let requirements = requirements(difficulty, &mut rng);
+
+    return GeneratedQuest {
+        seed: Some(seed),
+        name,
+        number_of_workouts_needed,
+        requirements,
+        goal: None,
+    };
+}
+
+// Goal worth the given number of workout days in a random metric
+pub fn goal_quest(seed: i64, days: i32, words: &impl WordSource) -> GeneratedQuest {
+    let mut rng = quest_rng(seed);
+    let name = name(&mut rng, words);
+    let metric = *QuestMetric::VARIANTS.choose(&mut rng).unwrap();
+
+    return GeneratedQuest {
+        seed: Some(seed),
+        name,
+        number_of_workouts_needed: 0,
+        requirements: Vec::new(),
+        goal: Some(QuestGoal::new(metric, metric.per_day() * days as f32, None)),
+    };
+}
+
+// Only names a quest whose goal was chosen by the user, so the seed isn't kept
+pub fn named_quest(seed: i64, goal: QuestGoal, words: &impl WordSource) -> GeneratedQuest {
+    let mut rng = quest_rng(seed);
+
+    return GeneratedQuest {
+        seed: None,
+        name: name(&mut rng, words),
+        number_of_workouts_needed: 0,
+        requirements: Vec::new(),
+        goal: Some(goal),
+    };
+}
+
+// Everything adaptive quests are generated from besides the seed
+pub struct QuestInputs<W: WordSource> {
+    pub words: W,
+    pub baseline: TrainingBaseline,
+    pub tuning: QuestTuning,
+}
+
+// Workouts in the baseline before quests adapt to it, random quests are used until then
+pub const MIN_BASELINE_WORKOUTS: i32 = 3;
+
+// Easy quests ask for what the user usually does, harder ones stretch further above it
+fn stretched(value: f32, difficulty: QuestDifficulty, tuning: &QuestTuning) -> f32 {
+    let steps = match difficulty {
+        QuestDifficulty::Easy => 0.0,
+        QuestDifficulty::Medium => 1.0,
+        QuestDifficulty::Hard => 2.0,
+    };
+    value * (1.0 + tuning.stretch * steps)
+}
+
+fn round_to(value: f32, step: f32) -> f32 {
+    ((value / step).round() * step).max(step)
+}
+
+// Smallest amount goals of the metric are rounded to
+fn increment(metric: QuestMetric) -> f32 {
+    match metric {
+        QuestMetric::Volume => 50.0,
+        QuestMetric::Reps => 5.0,
+        QuestMetric::Sets => 1.0,
+        QuestMetric::Distance => 0.5,
+    }
+}
+
+// Least trained muscles when targeting neglected ones, otherwise weighted towards the usual ones
+fn pick_muscle(inputs: &QuestInputs<impl WordSource>, rng: &mut QuestRng) -> ExerciseMuscle {
+    if inputs.tuning.target_neglected_muscles {
+        let least = ExerciseMuscle::VARIANTS
+            .iter()
+            .map(|&muscle| inputs.baseline.trained(muscle))
+            .min()
+            .unwrap_or(0);
+        let neglected: Vec<ExerciseMuscle> = ExerciseMuscle::VARIANTS
+            .iter()
+            .copied()
+            .filter(|&muscle| inputs.baseline.trained(muscle) == least)
+            .collect();
+        return *neglected.choose(rng).unwrap();
+    }
+
+    return match inputs.baseline.muscles.choose_weighted(rng, |&(_, workouts)| workouts) {
+        Ok(&(muscle, _)) => muscle,
+        Err(_) => *ExerciseMuscle::VARIANTS.choose(rng).unwrap(),
+    };
+}
+
+// Requirements sized from the baseline, only categories the user already trains are asked for
+fn adaptive_requirements(
+    difficulty: QuestDifficulty,
+    inputs: &QuestInputs<impl WordSource>,
+    rng: &mut QuestRng,
+) -> Vec<QuestRequirement> {
+    let baseline = &inputs.baseline;
+    let tuning = &inputs.tuning;
+
+    let mut kinds = vec!["workout_duration", "exercise_muscle"];
+    if !baseline.categories.is_empty() {
+        kinds.push("exercise_category");
+    }
+    if baseline.per_workout(QuestMetric::Sets) > 0.0 {
+        kinds.push("minimum_sets");
+    }
+    kinds.shuffle(rng);
+
+    if tuning.target_neglected_muscles {
+        kinds.retain(|&kind| kind != "exercise_muscle");
+        kinds.insert(0, "exercise_muscle");
+    }
+
+    return kinds
+        .into_iter()
+        .take(difficulty.requirements())
+        .map(|kind| match kind {
+            "workout_duration" => QuestRequirement::WorkoutDuration(WorkoutDuration {
+                minutes: round_to(stretched(baseline.duration, difficulty, tuning), 5.0) as i32,
+            }),
+            "exercise_category" => {
+                let &(category, _) = baseline
+                    .categories
+                    .choose_weighted(rng, |&(_, workouts)| workouts)
+                    .unwrap();
+                QuestRequirement::ExerciseCategory(IncludesCategory { category })
+            }
+            "exercise_muscle" => QuestRequirement::ExerciseMuscle(TargetsMuscle {
+                muscle: pick_muscle(inputs, rng),
+            }),
+            "minimum_sets" => {
+                let sets = baseline.per_workout(QuestMetric::Sets);
+                QuestRequirement::MinimumSets(MinimumSets {
+                    sets: round_to(stretched(sets, difficulty, tuning), 2.0) as i32,
+                })
+            }
+            _ => unreachable!(),
+        })
+        .collect();
+}
+
+// Random quest sized against the user's recent training.
+// The same seed only reproduces the quest as long as the baseline and tuning are the same, so it is not kept.
+pub fn adaptive_quest(
+    seed: i64,
+    difficulty: QuestDifficulty,
+    inputs: &QuestInputs<impl WordSource>,
+) -> GeneratedQuest {
+    if inputs.baseline.workouts < MIN_BASELINE_WORKOUTS {
+        return random_quest(seed, difficulty, &inputs.words);
+    }
+
+    let mut rng = quest_rng(seed);
+    let name = name(&mut rng, &inputs.words);
+
+    // As many workouts as the user usually fits in the quest's lifetime, at most one a day
+    let days = difficulty.lifetime().num_days() as f32;
+    let usual = inputs.baseline.workouts_per_week / 7.0 * days;
+    let number_of_workouts_needed =
+        (stretched(usual, difficulty, &inputs.tuning).ceil() as i32).clamp(1, days as i32);
+    let requirements = adaptive_requirements(difficulty, inputs, &mut rng);
+
+    return GeneratedQuest {
+        seed: None,
+        name,
+        number_of_workouts_needed,
+        requirements,
+        goal: None,
+    };
+}
+
+// Goal worth the given number of the user's usual workouts, in a metric they actually train.
+// Like adaptive_quest, the seed is only kept when the quest falls back to one the seed reproduces.
+pub fn adaptive_goal_quest(
+    seed: i64,
+    difficulty: QuestDifficulty,
+    days: i32,
+    inputs: &QuestInputs<impl WordSource>,
+) -> GeneratedQuest {
+    let metrics: Vec<(QuestMetric, f32)> = inputs
+        .baseline
+        .per_workout
+        .iter()
+        .copied()
+        .filter(|&(_, amount)| amount > 0.0)
+        .collect();
+
+    if inputs.baseline.workouts < MIN_BASELINE_WORKOUTS || metrics.is_empty() {
+        return goal_quest(seed, days, &inputs.words);
+    }
+
+    let mut rng = quest_rng(seed);
+    let name = name(&mut rng, &inputs.words);
+    let &(metric, amount) = metrics.choose(&mut rng).unwrap();
+    let target = stretched(amount * days as f32, difficulty, &inputs.tuning);
+
+    return GeneratedQuest {
+        seed: None,
+        name,
+        number_of_workouts_needed: 0,
+        requirements: Vec::new(),
+        goal: Some(QuestGoal::new(metric, round_to(target, increment(metric)), None)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::collections::HashSet,
+    };
+
+    struct TestWords {
+        adjectives: Vec<String>,
+        nouns: Vec<String>,
+    }
+
+    impl WordSource for TestWords {
+        fn adjectives(&self) -> &[String] {
+            &self.adjectives
+        }
+
+        fn nouns(&self) -> &[String] {
+            &self.nouns
+        }
+    }
+
+    fn words() -> TestWords {
+        TestWords {
+            adjectives: ["brave", "grande", "robust", "swift"]
+                .map(String::from)
+                .to_vec(),
+            nouns: ["fox", "rambutan", "whale"].map(String::from).to_vec(),
+        }
+    }
+
+    const DIFFICULTIES: [QuestDifficulty; 3] = [
+        QuestDifficulty::Easy,
+        QuestDifficulty::Medium,
+        QuestDifficulty::Hard,
+    ];
+
+    #[test]
+    fn same_seed_reproduces_the_same_quest() {
+        let words = words();
+        for seed in 0..200 {
+            for difficulty in DIFFICULTIES {
+                assert_eq!(
+                    random_quest(seed, difficulty, &words),
+                    random_quest(seed, difficulty, &words)
+                );
+            }
+            assert_eq!(goal_quest(seed, 4, &words), goal_quest(seed, 4, &words));
+        }
+    }
+
+    #[test]
+    fn different_seeds_produce_different_quests() {
+        let words = words();
+        let first = random_quest(0, QuestDifficulty::Hard, &words);
+        assert!((1..20).any(|seed| random_quest(seed, QuestDifficulty::Hard, &words) != first));
+    }
+
+    #[test]
+    fn seed_is_kept_on_the_quest() {
+        let quest = random_quest(42, QuestDifficulty::Medium, &words());
+        assert_eq!(quest.seed, Some(42));
+    }
+
+    #[test]
+    fn named_quests_keep_no_seed() {
+        let goal = QuestGoal::new(QuestMetric::Distance, 20.0, None);
+        assert_eq!(named_quest(42, goal, &words()).seed, None);
+    }
+
+    #[test]
+    fn name_is_an_adjective_and_a_noun() {
+        let words = words();
+        for seed in 0..100 {
+            let quest = random_quest(seed, QuestDifficulty::Easy, &words);
+            let (adjective, noun) = quest.name.split_once(' ').unwrap();
+            assert!(words.adjectives.iter().any(|word| word == adjective));
+            assert!(words.nouns.iter().any(|word| word == noun));
+        }
+    }
+
+    #[test]
+    fn name_does_not_depend_on_the_kind_of_quest() {
+        let words = words();
+        let goal = QuestGoal::new(QuestMetric::Distance, 10.0, None);
+        for seed in 0..50 {
+            let name = random_quest(seed, QuestDifficulty::Hard, &words).name;
+            assert_eq!(goal_quest(seed, 3, &words).name, name);
+            assert_eq!(named_quest(seed, goal.clone(), &words).name, name);
+        }
+    }
+
+    #[test]
+    fn harder_quests_get_more_distinct_requirements() {
+        let words = words();
+        for seed in 0..200 {
+            for difficulty in DIFFICULTIES {
+                let requirements = random_quest(seed, difficulty, &words).requirements;
+                assert_eq!(requirements.len(), difficulty.requirements());
+
+                let kinds: HashSet<_> = requirements.iter().map(std::mem::discriminant).collect();
+                assert_eq!(kinds.len(), requirements.len());
+            }
+        }
+    }
+
+    #[test]
+    fn workouts_needed_stay_in_range() {
+        let words = words();
+        for seed in 0..200 {
+            let easy = random_quest(seed, QuestDifficulty::Easy, &words);
+            let medium = random_quest(seed, QuestDifficulty::Medium, &words);
+            let hard = random_quest(seed, QuestDifficulty::Hard, &words);
+
+            assert_eq!(easy.number_of_workouts_needed, 1);
+            assert!((3..=5).contains(&medium.number_of_workouts_needed));
+            assert!((10..=15).contains(&hard.number_of_workouts_needed));
+        }
+    }
+
+    #[test]
+    fn requirement_values_follow_the_difficulty() {
+        let words = words();
+        for seed in 0..300 {
+            for difficulty in DIFFICULTIES {
+                let (minutes, sets) = match difficulty {
+                    QuestDifficulty::Easy => (5..=30, 6..=12),
+                    QuestDifficulty::Medium => (45..=60, 14..=20),
+                    QuestDifficulty::Hard => (80..=120, 24..=30),
+                };
+
+                for requirement in random_quest(seed, difficulty, &words).requirements {
+                    match requirement {
+                        QuestRequirement::WorkoutDuration(duration) => {
+                            assert!(minutes.contains(&duration.minutes));
+                            assert_eq!(duration.minutes % 5, 0);
+                        }
+                        QuestRequirement::MinimumSets(minimum) => {
+                            assert!(sets.contains(&minimum.sets));
+                            assert_eq!(minimum.sets % 2, 0);
+                        }
+                        QuestRequirement::TimeOfDay(window) => {
+                            assert!(window.start < window.end);
+                        }
+                        QuestRequirement::ExerciseCategory(_)
+                        | QuestRequirement::ExerciseMuscle(_) => {}
+                        other => panic!("unexpected requirement {:?}", other),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn goal_is_sized_by_days() {
+        let words = words();
+        for seed in 0..100 {
+            let quest = goal_quest(seed, 3, &words);
+            let goal = quest.goal.unwrap();
+
+            assert_eq!(goal.target, goal.metric.per_day() * 3.0);
+            assert_eq!(goal.unit, goal.metric.unit());
+            assert_eq!(goal.days(), 3);
+            assert_eq!(quest.number_of_workouts_needed, 0);
+            assert!(quest.requirements.is_empty());
+        }
+    }
+
+    #[test]
+    fn empty_word_source_still_generates() {
+        let words = TestWords {
+            adjectives: Vec::new(),
+            nouns: vec!["whale".to_string()],
+        };
+        assert_eq!(random_quest(7, QuestDifficulty::Easy, &words).name, "whale");
+    }
+
+    fn inputs(stretch: f32, target_neglected_muscles: bool) -> QuestInputs<TestWords> {
+        QuestInputs {
+            words: words(),
+            baseline: TrainingBaseline {
+                workouts: 12,
+                workouts_per_week: 3.0,
+                duration: 40.0,
+                per_workout: vec![
+                    (QuestMetric::Volume, 3_000.0),
+                    (QuestMetric::Reps, 120.0),
+                    (QuestMetric::Sets, 12.0),
+                    (QuestMetric::Distance, 0.0),
+                ],
+                categories: vec![(ExerciseCategory::strength, 12)],
+                muscles: vec![(ExerciseMuscle::chest, 8), (ExerciseMuscle::quadriceps, 4)],
+            },
+            tuning: QuestTuning {
+                stretch,
+                target_neglected_muscles,
+            },
+        }
+    }
+
+    #[test]
+    fn adaptive_quests_fall_back_to_random_without_history() {
+        let mut inputs = inputs(0.1, false);
+        inputs.baseline.workouts = MIN_BASELINE_WORKOUTS - 1;
+
+        for seed in 0..50 {
+            assert_eq!(
+                adaptive_quest(seed, QuestDifficulty::Medium, &inputs),
+                random_quest(seed, QuestDifficulty::Medium, &inputs.words)
+            );
+            assert_eq!(
+                adaptive_goal_quest(seed, QuestDifficulty::Medium, 3, &inputs),
+                goal_quest(seed, 3, &inputs.words)
+            );
+        }
+    }
+
+    #[test]
+    fn adaptive_quests_keep_no_seed() {
+        let inputs = inputs(0.1, false);
+        assert_eq!(adaptive_quest(42, QuestDifficulty::Medium, &inputs).seed, None);
+        assert_eq!(adaptive_goal_quest(42, QuestDifficulty::Medium, 3, &inputs).seed, None);
+    }
+
+    #[test]
+    fn adaptive_quests_are_reproducible() {
+        let inputs = inputs(0.2, true);
+        for seed in 0..100 {
+            for difficulty in DIFFICULTIES {
+                assert_eq!(
+                    adaptive_quest(seed, difficulty, &inputs),
+                    adaptive_quest(seed, difficulty, &inputs)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_requirements_stretch_above_the_baseline() {
+        let inputs = inputs(0.25, false);
+        for seed in 0..200 {
+            for difficulty in DIFFICULTIES {
+                let (minutes, sets) = match difficulty {
+                    QuestDifficulty::Easy => (40, 12),
+                    QuestDifficulty::Medium => (50, 16), // 40 × 1.25, 12 × 1.25 rounded to even
+                    QuestDifficulty::Hard => (60, 18),
+                };
+
+                let quest = adaptive_quest(seed, difficulty, &inputs);
+                assert_eq!(quest.requirements.len(), difficulty.requirements());
+
+                for requirement in quest.requirements {
+                    match requirement {
+                        QuestRequirement::WorkoutDuration(duration) => {
+                            assert_eq!(duration.minutes, minutes)
+                        }
+                        QuestRequirement::MinimumSets(minimum) => assert_eq!(minimum.sets, sets),
+                        QuestRequirement::ExerciseCategory(category) => {
+                            assert_eq!(category.category, ExerciseCategory::strength)
+                        }
+                        QuestRequirement::ExerciseMuscle(target) => assert!(matches!(
+                            target.muscle,
+                            ExerciseMuscle::chest | ExerciseMuscle::quadriceps
+                        )),
+                        other => panic!("unexpected requirement {:?}", other),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_workouts_follow_the_usual_frequency() {
+        let inputs = inputs(0.1, false);
+        for seed in 0..50 {
+            // 3 workouts a week is 1.3 in 3 days and 3 in 7 days before stretching
+            let easy = adaptive_quest(seed, QuestDifficulty::Easy, &inputs);
+            let medium = adaptive_quest(seed, QuestDifficulty::Medium, &inputs);
+            let hard = adaptive_quest(seed, QuestDifficulty::Hard, &inputs);
+
+            assert_eq!(easy.number_of_workouts_needed, 1);
+            assert_eq!(medium.number_of_workouts_needed, 2);
+            assert_eq!(hard.number_of_workouts_needed, 4);
+        }
+    }
+
+    #[test]
+    fn neglected_muscles_are_always_targeted() {
+        let inputs = inputs(0.1, true);
+        for seed in 0..200 {
+            let quest = adaptive_quest(seed, QuestDifficulty::Easy, &inputs);
+            let [QuestRequirement::ExerciseMuscle(target)] = &quest.requirements[..] else {
+                panic!("expected a muscle requirement, got {:?}", quest.requirements);
+            };
+            assert_eq!(inputs.baseline.trained(target.muscle), 0);
+        }
+    }
+
+    #[test]
+    fn adaptive_goals_only_use_trained_metrics() {
+        let inputs = inputs(0.1, false);
+        for seed in 0..200 {
+            let quest = adaptive_goal_quest(seed, QuestDifficulty::Medium, 3, &inputs);
+            let goal = quest.goal.unwrap();
+
+            assert_ne!(goal.metric, QuestMetric::Distance);
+            let usual = inputs.baseline.per_workout(goal.metric) * 3.0;
+            assert!(goal.target >= usual && goal.target <= usual * 1.2);
+        }
+    }
+
+    #[test]
+    fn new_seeds_are_json_safe() {
+        for _ in 0..1000 {
+            assert!((0..=MAX_SEED).contains(&new_seed()));
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use {
+    crate::utils::jwt::AuthenticatedUser,
+    actix_web::{
+        HttpResponse,
+        body::{
+            BoxBody,
+            MessageBody,
+            to_bytes,
+        },
+        dev::{
+            Payload,
+            ServiceRequest,
+            ServiceResponse,
+        },
+        error::{
+            ErrorBadRequest,
+            ErrorConflict,
+            ErrorInternalServerError,
+            ErrorUnprocessableEntity,
+        },
+        http::{
+            Method,
+            StatusCode,
+            Uri,
+            header::CONTENT_TYPE,
+        },
+        middleware::Next,
+        web,
+    },
+    sha2::{
+        Digest,
+        Sha256,
+    },
+    sqlx::PgPool,
+};
+
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+const MAX_KEY_LENGTH: usize = 255;
+
+// Stored responses are replayed for this long
+const KEY_TTL_HOURS: i32 = 24;
+
+// A request that never stored its response frees its key after this long, e.g. when the server went down
+const IN_PROGRESS_TIMEOUT_SECONDS: i32 = 60;
+
+// Replays the stored response of a request that is retried with the same Idempotency-Key, so a retry
+// never logs a workout, buys an item or creates a quest twice. Keys belong to the user sending them,
+// requests without one are handled as usual. Server errors are not stored so the request can be retried.
+// Wrap handlers with it: #[post("/path", wrap = "from_fn(idempotency)")]
+pub async fn idempotency(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+        _ => return Err(ErrorBadRequest("Invalid Idempotency-Key")),
+    };
+
+    // Unauthenticated requests are rejected by the handler
+    let Ok(user) = req.extract::<AuthenticatedUser>().await else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .cloned()
+        .ok_or_else(|| ErrorInternalServerError("Database pool is missing"))?;
+
+    // The body is read to fingerprint the request and handed back to the handler
+    let body = req.extract::<web::Bytes>().await?;
+    let request_hash = request_hash(req.method(), req.uri(), &body);
+    req.set_payload(Payload::from(body));
+
+    let _query = sqlx::query!(
+        r#"
+            DELETE FROM idempotency_keys
+            WHERE created_at < NOW() - make_interval(hours => $1)
+                OR (status_code IS NULL AND created_at < NOW() - make_interval(secs => $2))
+        "#,
+        KEY_TTL_HOURS,
+        IN_PROGRESS_TIMEOUT_SECONDS as f64,
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(ErrorInternalServerError)?;
+
+    let claimed = sqlx::query!(
+        r#"
+            INSERT INTO idempotency_keys (user_id, key, request_hash)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, key) DO NOTHING
+        "#,
+        user.id,
+        key,
+        request_hash,
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(ErrorInternalServerError)?
+    .rows_affected()
+        == 1;
+
+    if !claimed {
+        let stored = sqlx::query!(
+            r#"
+                SELECT request_hash, status_code, content_type, response
+                FROM idempotency_keys
+                WHERE user_id = $1 AND key = $2
+            "#,
+            user.id,
+            key,
+        )
+        .fetch_optional(pool.get_ref())
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+        let Some(stored) = stored else {
+            return Err(ErrorConflict(
+                "The request with this Idempotency-Key was just cleared, retry it",
+            ));
+        };
+
+        if stored.request_hash != request_hash {
+            return Err(ErrorUnprocessableEntity(
+                "This Idempotency-Key was used for a different request",
+            ));
+        }
+
+        let Some(status_code) = stored.status_code else {
+            return Err(ErrorConflict(
+                "The request with this Idempotency-Key is still being handled",
+            ));
+        };
+
+        let mut response = HttpResponse::build(
+            StatusCode::from_u16(status_code as u16).map_err(ErrorInternalServerError)?,
+        );
+        if let Some(content_type) = stored.content_type {
+            response.content_type(content_type);
+        }
+        let response = response
+            .insert_header(("Idempotent-Replayed", "true"))
+            .body(stored.response.unwrap_or_default());
+
+        return Ok(req.into_response(response));
+    }
+
+    let response = match next.call(req).await {
+        Ok(response) => response,
+        Err(e) => {
+            release_key(&pool, user.id, &key).await?;
+            return Err(e);
+        }
+    };
+
+    if response.status().is_server_error() {
+        release_key(&pool, user.id, &key).await?;
+        return Ok(response.map_into_boxed_body());
+    }
+
+    // The response is buffered so it can be stored and still be sent
+    let (req, response) = response.into_parts();
+    let (response, body) = response.into_parts();
+    let body = to_bytes(body)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to read the response"))?;
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok());
+
+    let _query = sqlx::query!(
+        r#"
+            UPDATE idempotency_keys
+            SET status_code = $3, content_type = $4, response = $5
+            WHERE user_id = $1 AND key = $2
+        "#,
+        user.id,
+        key,
+        response.status().as_u16() as i16,
+        content_type,
+        body.as_ref(),
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(ErrorInternalServerError)?;
+
+    return Ok(ServiceResponse::new(req, response.set_body(body)).map_into_boxed_body());
+}
+
+// Fingerprint of a request, a key can only be retried with the same method, path and body
+fn request_hash(method: &Method, uri: &Uri, body: &[u8]) -> Vec<u8> {
+    return Sha256::new()
+        .chain_update(method.as_str())
+        .chain_update(uri.to_string())
+        .chain_update(body)
+        .finalize()
+        .to_vec();
+}
+
+// Lets the request be retried with the same key
+async fn release_key(pool: &PgPool, user_id: i32, key: &str) -> Result<(), actix_web::Error> {
+    let _query = sqlx::query!(
+        r#"
+            DELETE FROM idempotency_keys
+            WHERE user_id = $1 AND key = $2
+        "#,
+        user_id,
+        key,
+    )
+    .execute(pool)
+    .await
+    .map_err(ErrorInternalServerError)?;
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::utils::jwt::{
+            TokenType,
+            generate_jwt,
+        },
+        actix_web::{
+            App,
+            dev::Service,
+            middleware::from_fn,
+            post,
+            test::{
+                self,
+                TestRequest,
+            },
+        },
+        std::sync::atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    };
+
+    // Counts the requests that got through to it
+    #[post("/orders", wrap = "from_fn(idempotency)")]
+    async fn create_order(calls: web::Data<AtomicUsize>) -> HttpResponse {
+        let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        return HttpResponse::Created().json(serde_json::json!({ "call": call }));
+    }
+
+    async fn sign_up(pool: &PgPool) -> (i32, String) {
+        dotenvy::dotenv().ok();
+
+        let user_id = sqlx::query_scalar!(
+            r#"
+                INSERT INTO users (email, password, onboarding_complete)
+                VALUES ('idempotency@test.com', '', TRUE)
+                RETURNING id
+            "#
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        return (user_id, generate_jwt(user_id, TokenType::Access));
+    }
+
+    fn order(token: &str, key: &str, body: &'static str) -> TestRequest {
+        return TestRequest::post()
+            .uri("/orders")
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .insert_header((IDEMPOTENCY_KEY, key))
+            .set_payload(body);
+    }
+
+    // Status of the response, errors of the middleware included
+    async fn status<R>(
+        app: &impl Service<R, Response = ServiceResponse, Error = actix_web::Error>,
+        request: R,
+    ) -> StatusCode {
+        return match test::try_call_service(app, request).await {
+            Ok(response) => response.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+    }
+
+    #[sqlx::test]
+    async fn retries_replay_the_stored_response(pool: PgPool) {
+        let (_, token) = sign_up(&pool).await;
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(calls.clone())
+                .service(create_order),
+        )
+        .await;
+
+        let first = test::call_service(&app, order(&token, "order-1", "{}").to_request()).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(!first.headers().contains_key("Idempotent-Replayed"));
+        let first = test::read_body(first).await;
+
+        let retry = test::call_service(&app, order(&token, "order-1", "{}").to_request()).await;
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert_eq!(retry.headers().get("Idempotent-Replayed").unwrap(), "true");
+        assert_eq!(retry.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+        assert_eq!(test::read_body(retry).await, first);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[sqlx::test]
+    async fn a_key_reused_for_another_request_is_rejected(pool: PgPool) {
+        let (_, token) = sign_up(&pool).await;
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(calls.clone())
+                .service(create_order),
+        )
+        .await;
+
+        let first = status(&app, order(&token, "order-1", r#"{"item":1}"#).to_request()).await;
+        assert_eq!(first, StatusCode::CREATED);
+
+        let reused = status(&app, order(&token, "order-1", r#"{"item":2}"#).to_request()).await;
+        assert_eq!(reused, StatusCode::UNPROCESSABLE_ENTITY);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[sqlx::test]
+    async fn a_retry_while_the_request_is_handled_conflicts(pool: PgPool) {
+        let (user_id, token) = sign_up(&pool).await;
+
+        // The first request claimed the key and is still being handled
+        let _query = sqlx::query!(
+            r#"
+                INSERT INTO idempotency_keys (user_id, key, request_hash)
+                VALUES ($1, 'order-1', $2)
+            "#,
+            user_id,
+            request_hash(&Method::POST, &Uri::from_static("/orders"), b"{}"),
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(calls.clone())
+                .service(create_order),
+        )
+        .await;
+
+        let retry = status(&app, order(&token, "order-1", "{}").to_request()).await;
+        assert_eq!(retry, StatusCode::CONFLICT);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,170 @@
+use {
+    crate::utils::schemas::{
+        Equipped,
+        Inventory,
+        ItemCategory,
+        ItemRarity,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::PgConnection,
+};
+
+// Coins, inventory and loadout of a character.
+// Always read with `lock_holdings` inside a transaction so that transfers are validated and applied atomically.
+#[derive(Clone)]
+pub struct Holdings {
+    pub user_id: i32,
+    pub coins: i32,
+    pub inventory: Inventory,
+    pub equipped: Equipped,
+}
+
+// The item details needed to move an item between inventories
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct TransferItem {
+    pub id: i32,
+    pub category: ItemCategory,
+    pub rarity: ItemRarity,
+}
+
+impl Holdings {
+    // Removes an item from the inventory, default and equipped items can never leave an inventory
+    pub fn take_item(&mut self, item: &TransferItem) -> Result<(), &'static str> {
+        if item.rarity == ItemRarity::Default {
+            return Err("Default items cannot be transferred");
+        }
+
+        if self.equipped.contains(item.id) {
+            return Err("Equipped items cannot be transferred");
+        }
+
+        let items = self.inventory.items_mut(item.category);
+        match items.iter().position(|&id| id == item.id) {
+            Some(index) => {
+                items.remove(index);
+                Ok(())
+            }
+            None => Err("Item is not in inventory"),
+        }
+    }
+
+    pub fn give_item(&mut self, item: &TransferItem) -> Result<(), &'static str> {
+        if self.inventory.contains(item.category, item.id) {
+            return Err("Item is already owned by the recipient");
+        }
+
+        self.inventory.items_mut(item.category).push(item.id);
+        Ok(())
+    }
+
+    pub fn take_coins(&mut self, coins: i32) -> Result<(), &'static str> {
+        if coins < 0 {
+            return Err("Coin amount cannot be negative");
+        }
+
+        if self.coins < coins {
+            return Err("Not enough coins");
+        }
+
+        self.coins -= coins;
+        Ok(())
+    }
+}
+
+// Moves items and coins from one character to another, stopping at the first invalid transfer
+pub fn transfer(
+    from: &mut Holdings,
+    to: &mut Holdings,
+    items: &[TransferItem],
+    coins: i32,
+) -> Result<(), &'static str> {
+    for item in items {
+        from.take_item(item)?;
+        to.give_item(item)?;
+    }
+
+    from.take_coins(coins)?;
+    to.coins += coins;
+
+    Ok(())
+}
+
+pub async fn lock_holdings(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<Holdings, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+            SELECT coins, inventory as "inventory: Inventory", equipped as "equipped: Equipped"
+            FROM characters
+            WHERE user_id = $1
+            FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    return Ok(Holdings {
+        user_id,
+        coins: query.coins,
+        inventory: query.inventory,
+        equipped: query.equipped,
+    });
+}
+
+// Locks two characters, always in the same order so concurrent transfers between them cannot deadlock
+pub async fn lock_holdings_pair(
+    conn: &mut PgConnection,
+    first_id: i32,
+    second_id: i32,
+) -> Result<(Holdings, Holdings), sqlx::Error> {
+    if first_id < second_id {
+        let first = lock_holdings(conn, first_id).await?;
+        let second = lock_holdings(conn, second_id).await?;
+        return Ok((first, second));
+    }
+
+    let second = lock_holdings(conn, second_id).await?;
+    let first = lock_holdings(conn, first_id).await?;
+    return Ok((first, second));
+}
+
+pub async fn save_holdings(conn: &mut PgConnection, holdings: &Holdings) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            UPDATE characters
+            SET coins = $2, inventory = $3
+            WHERE user_id = $1
+        "#,
+        holdings.user_id,
+        holdings.coins,
+        holdings.inventory.clone() as Inventory,
+    )
+    .execute(conn)
+    .await?;
+
+    return Ok(());
+}
+
+pub async fn fetch_transfer_items(
+    conn: &mut PgConnection,
+    item_ids: &[i32],
+) -> Result<Vec<TransferItem>, sqlx::Error> {
+    let items = sqlx::query_as!(
+        TransferItem,
+        r#"
+            SELECT id, category as "category: ItemCategory", rarity as "rarity: ItemRarity"
+            FROM items
+            WHERE id = ANY($1)
+        "#,
+        item_ids
+    )
+    .fetch_all(conn)
+    .await?;
+
+    return Ok(items);
+}
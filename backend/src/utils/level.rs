@@ -1,10 +1,14 @@
 use {
-    crate::utils::jwt::AuthenticatedUser,
-    actix_web::{
-        HttpResponse,
-        web,
+    crate::utils::{
+        chests::{
+            LEVEL_UP_CHEST,
+            grant_chest,
+        },
+        jwt::AuthenticatedUser,
+        schemas::ChestSource,
     },
-    sqlx::PgPool,
+    actix_web::HttpResponse,
+    sqlx::PgConnection,
 };
 
 pub fn exp_needed_for_level(n: i32) -> i32 {
@@ -12,20 +16,21 @@ pub fn exp_needed_for_level(n: i32) -> i32 {
     return exp_needed;
 }
 
+// Negative exp takes levels back, see the de-levelling rules below
 pub async fn add_exp(
     user: &AuthenticatedUser,
-    pool: &web::Data<PgPool>,
+    conn: &mut PgConnection,
     exp: i32,
 ) -> Result<HttpResponse, actix_web::Error> {
     let query = sqlx::query!(
         r#"
-            SELECT level, exp_leftover, pending_stat_points
+            SELECT level, exp_leftover, pending_stat_points, highest_level
             FROM characters
             WHERE user_id = $1
         "#,
         user.id,
     )
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *conn)
     .await
     .unwrap();
 
@@ -45,22 +50,44 @@ pub async fn add_exp(
         }
     }
 
+    // De-levelling: missing exp is taken from the previous levels, a character never drops below level 1.
+    // Stat points of lost levels are taken back even if already spent, pending points can go negative
+    // and are paid back by the next level-ups.
+    while total_exp < 0 && current_level > 1 {
+        current_level -= 1;
+        levels_gained -= 1;
+        total_exp += exp_needed_for_level(current_level);
+    }
+    let total_exp = total_exp.max(0);
+
     let new_pending_points = query.pending_stat_points + levels_gained;
 
+    // Levels lost and gained again don't give another chest
+    let highest_level = query.highest_level.max(query.level);
+    let chests = (current_level - highest_level).max(0);
+
     let _query = sqlx::query!(
         r#"
             UPDATE characters
-            SET level = $2, exp_leftover = $3, pending_stat_points = $4
+            SET level = $2, exp_leftover = $3, pending_stat_points = $4, highest_level = $5
             WHERE user_id = $1
         "#,
         user.id,
         current_level,
         total_exp,
         new_pending_points,
+        highest_level.max(current_level),
     )
-    .execute(pool.get_ref())
+    .execute(&mut *conn)
     .await
     .unwrap();
 
+    // One chest for every new level reached
+    for _ in 0..chests {
+        grant_chest(user, conn, LEVEL_UP_CHEST, ChestSource::LevelUp)
+            .await
+            .unwrap();
+    }
+
     return Ok(HttpResponse::Ok().into());
 }
@@ -0,0 +1,273 @@
+use {
+    serde::Serialize,
+    sqlx::PgPool,
+    strsim::{
+        jaro_winkler,
+        normalized_levenshtein,
+    },
+};
+
+// Matches below this score are only suggested, the exercise is left out until the user maps it
+pub const MATCH_THRESHOLD: f32 = 0.6;
+
+// Words that say nothing about the exercise, a medium grip is what other apps leave unsaid
+const STOP_WORDS: &[&str] = &["a", "an", "and", "grip", "medium", "on", "of", "the", "to", "with"];
+
+// Words other apps use for the word of the catalog
+const SYNONYMS: &[(&str, &str)] = &[
+    ("bb", "barbell"),
+    ("db", "dumbbell"),
+    ("kb", "kettlebell"),
+    ("overhead", "shoulder"),
+];
+
+// Tokens this similar are the same word spelled differently, e.g. "pulldown" and "pulldowns"
+const SAME_TOKEN: f64 = 0.92;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExerciseMatch {
+    pub exercise_id: String,
+    pub name: String,
+    pub score: f32, // 1 is a perfect match
+}
+
+struct CatalogExercise {
+    id: String,
+    name: String,
+    tokens: Vec<String>,
+}
+
+// Maps exercise names from other apps to the catalog, e.g. "Bench Press (Barbell)" from Strong to
+// "Barbell Bench Press - Medium Grip". Names are compared word by word regardless of their order.
+pub struct ExerciseMatcher {
+    catalog: Vec<CatalogExercise>,
+}
+
+impl ExerciseMatcher {
+    pub async fn load(pool: &PgPool) -> Result<ExerciseMatcher, sqlx::Error> {
+        let catalog = sqlx::query!(
+            r#"
+                SELECT id, name
+                FROM exercises
+                ORDER BY id
+            "#
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|exercise| (exercise.id, exercise.name));
+
+        return Ok(ExerciseMatcher::new(catalog));
+    }
+
+    // Ids and names of the exercises of the catalog
+    pub fn new(catalog: impl IntoIterator<Item = (String, String)>) -> ExerciseMatcher {
+        let catalog = catalog
+            .into_iter()
+            .map(|(id, name)| CatalogExercise {
+                tokens: tokens(&name),
+                id,
+                name,
+            })
+            .collect();
+
+        return ExerciseMatcher { catalog };
+    }
+
+    pub fn contains(&self, exercise_id: &str) -> bool {
+        self.catalog
+            .iter()
+            .any(|exercise| exercise.id == exercise_id)
+    }
+
+    // Best matches first
+    pub fn candidates(&self, name: &str, count: usize) -> Vec<ExerciseMatch> {
+        let source = tokens(name);
+        let joined = source.join(" ");
+
+        let mut scored: Vec<(f32, f64, &CatalogExercise)> = self
+            .catalog
+            .iter()
+            .map(|exercise| {
+                let score = if exercise.id.eq_ignore_ascii_case(name.trim()) {
+                    1.0
+                } else {
+                    similarity(&source, &exercise.tokens)
+                };
+                // Ties go to the name spelled the most alike
+                let spelling = normalized_levenshtein(&joined, &exercise.tokens.join(" "));
+                (score, spelling, exercise)
+            })
+            .filter(|(score, _, _)| *score > 0.0)
+            .collect();
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.total_cmp(&a.1)));
+        scored.truncate(count);
+
+        return scored
+            .into_iter()
+            .map(|(score, _, exercise)| ExerciseMatch {
+                exercise_id: exercise.id.clone(),
+                name: exercise.name.clone(),
+                score,
+            })
+            .collect();
+    }
+}
+
+fn tokens(name: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty() && !STOP_WORDS.contains(token))
+        .map(|token| {
+            SYNONYMS
+                .iter()
+                .find(|(synonym, _)| *synonym == token)
+                .map_or(token, |&(_, word)| word)
+        })
+        .map(singular)
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    return tokens;
+}
+
+// "curls" and "curl" are the same word, "press" is not a plural
+fn singular(token: &str) -> String {
+    if token.len() > 3 && token.ends_with('s') && !token.ends_with("ss") {
+        return token[..token.len() - 1].to_string();
+    }
+    return token.to_string();
+}
+
+// Share of the words of both names that appear in the other one
+fn similarity(a: &[String], b: &[String]) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let same = |token: &String, other: &String| {
+        token == other || jaro_winkler(token, other) >= SAME_TOKEN
+    };
+    let found_in_b = a
+        .iter()
+        .filter(|token| b.iter().any(|other| same(token, other)))
+        .count();
+    let found_in_a = b
+        .iter()
+        .filter(|token| a.iter().any(|other| same(token, other)))
+        .count();
+
+    return (found_in_b + found_in_a) as f32 / (a.len() + b.len()) as f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &[(&str, &str)] = &[
+        ("Barbell_Bench_Press_-_Medium_Grip", "Barbell Bench Press - Medium Grip"),
+        ("Barbell_Curl", "Barbell Curl"),
+        ("Barbell_Deadlift", "Barbell Deadlift"),
+        ("Barbell_Shoulder_Press", "Barbell Shoulder Press"),
+        ("Barbell_Squat", "Barbell Squat"),
+        ("Dumbbell_Bench_Press", "Dumbbell Bench Press"),
+        ("Dumbbell_Bicep_Curl", "Dumbbell Bicep Curl"),
+        ("Seated_Cable_Rows", "Seated Cable Rows"),
+        ("Wide-Grip_Lat_Pulldown", "Wide-Grip Lat Pulldown"),
+    ];
+
+    fn matcher() -> ExerciseMatcher {
+        ExerciseMatcher::new(
+            CATALOG
+                .iter()
+                .map(|&(id, name)| (id.to_string(), name.to_string())),
+        )
+    }
+
+    fn best(name: &str) -> ExerciseMatch {
+        matcher().candidates(name, 1).pop().unwrap()
+    }
+
+    fn words(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(|token| token.to_string()).collect()
+    }
+
+    #[test]
+    fn tokens_are_sorted_lowercase_words_without_stop_words() {
+        assert_eq!(
+            tokens("Barbell Bench Press - Medium Grip"),
+            ["barbell", "bench", "press"]
+        );
+        assert_eq!(tokens("Bench Press (Barbell)"), ["barbell", "bench", "press"]);
+        assert_eq!(tokens("Curl with the bar"), ["bar", "curl"]);
+    }
+
+    #[test]
+    fn tokens_replace_synonyms() {
+        assert_eq!(tokens("DB Curl"), ["curl", "dumbbell"]);
+        assert_eq!(tokens("Overhead Press (BB)"), ["barbell", "press", "shoulder"]);
+    }
+
+    #[test]
+    fn tokens_are_deduplicated() {
+        assert_eq!(tokens("Curl curls CURL"), ["curl"]);
+    }
+
+    #[test]
+    fn singular_drops_the_plural_s() {
+        assert_eq!(singular("curls"), "curl");
+        assert_eq!(singular("rows"), "row");
+        assert_eq!(singular("press"), "press");
+        assert_eq!(singular("abs"), "abs");
+    }
+
+    #[test]
+    fn similarity_is_the_share_of_shared_words() {
+        let a = words(&["barbell", "bench", "press"]);
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert_eq!(similarity(&a, &words(&["barbell", "curl"])), 2.0 / 5.0);
+        assert_eq!(similarity(&a, &words(&["squat"])), 0.0);
+        assert_eq!(similarity(&a, &[]), 0.0);
+    }
+
+    #[test]
+    fn similarity_accepts_words_spelled_alike() {
+        let a = words(&["lat", "pulldown"]);
+        let b = words(&["lat", "pulldownn"]);
+        assert_eq!(similarity(&a, &b), 1.0);
+    }
+
+    #[test]
+    fn strong_names_match_the_catalog() {
+        assert_eq!(
+            best("Bench Press (Barbell)").exercise_id,
+            "Barbell_Bench_Press_-_Medium_Grip"
+        );
+        assert_eq!(best("Bench Press (Dumbbell)").exercise_id, "Dumbbell_Bench_Press");
+        assert_eq!(best("Squat (Barbell)").exercise_id, "Barbell_Squat");
+        assert_eq!(best("Seated Cable Row").exercise_id, "Seated_Cable_Rows");
+        assert_eq!(best("Overhead Press (Barbell)").exercise_id, "Barbell_Shoulder_Press");
+    }
+
+    #[test]
+    fn exercise_ids_match_exactly() {
+        let best = best("barbell_curl");
+        assert_eq!(best.exercise_id, "Barbell_Curl");
+        assert_eq!(best.score, 1.0);
+    }
+
+    #[test]
+    fn candidates_are_sorted_and_limited() {
+        let candidates = matcher().candidates("Barbell Press", 3);
+        assert_eq!(candidates.len(), 3);
+        assert!(candidates.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(candidates[0].score >= MATCH_THRESHOLD);
+    }
+
+    #[test]
+    fn unrelated_names_have_no_candidates() {
+        assert!(matcher().candidates("Swimming", 3).is_empty());
+    }
+}
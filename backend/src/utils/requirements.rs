@@ -0,0 +1,665 @@
+use {
+    crate::utils::{
+        schedule::is_scheduled,
+        schemas::{
+            Exercise,
+            ExerciseCategory,
+            ExerciseEquipment,
+            ExerciseLevel,
+            ExerciseMuscle,
+            History,
+        },
+    },
+    chrono::NaiveTime,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sqlx::PgConnection,
+    std::collections::HashMap,
+};
+
+// Something a single workout has to satisfy to count towards a quest
+pub trait Requirement {
+    fn is_met(&self, workout: &WorkoutContext) -> bool;
+}
+
+// Catalog details of an exercise
+#[derive(Clone, Debug)]
+pub struct ExerciseDetails {
+    pub category: ExerciseCategory,
+    pub level: ExerciseLevel,
+    pub equipment: Option<ExerciseEquipment>,
+    pub muscles: Vec<ExerciseMuscle>, // primary and secondary
+}
+
+// An exercise as it was logged, with its catalog details when the id is known
+#[derive(Clone, Debug)]
+pub struct LoggedExercise {
+    pub exercise: Exercise,
+    pub details: Option<ExerciseDetails>,
+}
+
+// Everything requirements can look at, loaded once per workout
+#[derive(Clone, Debug)]
+pub struct WorkoutContext {
+    pub duration: i32, // minutes
+    pub exercises: Vec<LoggedExercise>,
+    pub logged_at: NaiveTime, // when the workout started, in the user's timezone
+    pub scheduled_day: bool,
+}
+
+impl WorkoutContext {
+    pub async fn load(
+        conn: &mut PgConnection,
+        user_id: i32,
+        workout: &History,
+    ) -> Result<WorkoutContext, sqlx::Error> {
+        let settings = sqlx::query!(
+            r#"
+                SELECT ($2 AT TIME ZONE $3)::time as "logged_at!", workout_schedule
+                FROM settings
+                WHERE user_id = $1
+            "#,
+            user_id,
+            workout.started_at,
+            workout.timezone,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let exercises = &workout.exercises.0;
+        let ids: Vec<String> = exercises
+            .iter()
+            .map(|exercise| exercise.id.clone())
+            .collect();
+
+        let details: HashMap<String, ExerciseDetails> = sqlx::query!(
+            r#"
+                SELECT id,
+                    category as "category: ExerciseCategory",
+                    level as "level: ExerciseLevel",
+                    equipment as "equipment: ExerciseEquipment",
+                    primary_muscles as "primary_muscles: Vec<ExerciseMuscle>",
+                    secondary_muscles as "secondary_muscles: Vec<ExerciseMuscle>"
+                FROM exercises
+                WHERE id = ANY($1)
+            "#,
+            &ids
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|record| {
+            let mut muscles = record.primary_muscles;
+            muscles.extend(record.secondary_muscles);
+
+            let details = ExerciseDetails {
+                category: record.category,
+                level: record.level,
+                equipment: record.equipment,
+                muscles,
+            };
+            (record.id, details)
+        })
+        .collect();
+
+        let exercises = exercises
+            .iter()
+            .map(|exercise| LoggedExercise {
+                exercise: exercise.clone(),
+                details: details.get(&exercise.id).cloned(),
+            })
+            .collect();
+
+        let scheduled_day = is_scheduled(&settings.workout_schedule, workout.date);
+
+        return Ok(WorkoutContext {
+            duration: workout.duration,
+            exercises,
+            logged_at: settings.logged_at,
+            scheduled_day,
+        });
+    }
+
+    fn details(&self) -> impl Iterator<Item = &ExerciseDetails> {
+        self.exercises
+            .iter()
+            .filter_map(|logged| logged.details.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WorkoutDuration {
+    pub minutes: i32,
+}
+
+impl Requirement for WorkoutDuration {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout.duration >= self.minutes
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct IncludesCategory {
+    pub category: ExerciseCategory,
+}
+
+impl Requirement for IncludesCategory {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout
+            .details()
+            .any(|details| details.category == self.category)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TargetsMuscle {
+    pub muscle: ExerciseMuscle,
+}
+
+impl Requirement for TargetsMuscle {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout
+            .details()
+            .any(|details| details.muscles.contains(&self.muscle))
+    }
+}
+
+// Sum of sets × reps × weight over every exercise
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TotalVolume {
+    pub volume: f32,
+}
+
+impl Requirement for TotalVolume {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        QuestMetric::Volume.total(workout, None) >= self.volume
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TotalDistance {
+    pub distance: f32,
+}
+
+impl Requirement for TotalDistance {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        QuestMetric::Distance.total(workout, None) >= self.distance
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct IncludesExercise {
+    pub exercise_id: String,
+}
+
+impl Requirement for IncludesExercise {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout
+            .exercises
+            .iter()
+            .any(|logged| logged.exercise.id == self.exercise_id)
+    }
+}
+
+// Sets are counted over every exercise of the workout
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MinimumSets {
+    pub sets: i32,
+}
+
+impl Requirement for MinimumSets {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        QuestMetric::Sets.total(workout, None) >= self.sets as f32
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct UsesEquipment {
+    pub equipment: ExerciseEquipment,
+}
+
+impl Requirement for UsesEquipment {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout
+            .details()
+            .any(|details| details.equipment == Some(self.equipment))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AtLevel {
+    pub level: ExerciseLevel,
+}
+
+impl Requirement for AtLevel {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout.details().any(|details| details.level == self.level)
+    }
+}
+
+// Windows where start is after end wrap around midnight, e.g. 22:00 to 02:00
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TimeOfDay {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Requirement for TimeOfDay {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        let time = workout.logged_at;
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+// The workout has to be on a day of the user's workout schedule
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduledDay {}
+
+impl Requirement for ScheduledDay {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        workout.scheduled_day
+    }
+}
+
+// Stored as tagged JSON on the quest, e.g. {"kind": "workout_duration", "minutes": 30}
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QuestRequirement {
+    WorkoutDuration(WorkoutDuration),
+    ExerciseCategory(IncludesCategory),
+    ExerciseMuscle(TargetsMuscle),
+    TotalVolume(TotalVolume),
+    TotalDistance(TotalDistance),
+    Exercise(IncludesExercise),
+    MinimumSets(MinimumSets),
+    Equipment(UsesEquipment),
+    Level(AtLevel),
+    TimeOfDay(TimeOfDay),
+    ScheduledDay(ScheduledDay),
+}
+
+impl QuestRequirement {
+    fn requirement(&self) -> &dyn Requirement {
+        match self {
+            QuestRequirement::WorkoutDuration(requirement) => requirement,
+            QuestRequirement::ExerciseCategory(requirement) => requirement,
+            QuestRequirement::ExerciseMuscle(requirement) => requirement,
+            QuestRequirement::TotalVolume(requirement) => requirement,
+            QuestRequirement::TotalDistance(requirement) => requirement,
+            QuestRequirement::Exercise(requirement) => requirement,
+            QuestRequirement::MinimumSets(requirement) => requirement,
+            QuestRequirement::Equipment(requirement) => requirement,
+            QuestRequirement::Level(requirement) => requirement,
+            QuestRequirement::TimeOfDay(requirement) => requirement,
+            QuestRequirement::ScheduledDay(requirement) => requirement,
+        }
+    }
+}
+
+impl Requirement for QuestRequirement {
+    fn is_met(&self, workout: &WorkoutContext) -> bool {
+        self.requirement().is_met(workout)
+    }
+}
+
+// Quantities that progress-based quests accumulate across workouts
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, strum::VariantArray)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestMetric {
+    Volume, // reps × weight of every working set
+    Reps,   // reps of every working set
+    Sets,   // working sets
+    Distance,
+}
+
+impl QuestMetric {
+    pub fn unit(self) -> &'static str {
+        match self {
+            QuestMetric::Volume => "lbs",
+            QuestMetric::Reps => "reps",
+            QuestMetric::Sets => "sets",
+            QuestMetric::Distance => "km",
+        }
+    }
+
+    // Amount generated quests ask for per scheduled workout day
+    pub fn per_day(self) -> f32 {
+        match self {
+            QuestMetric::Volume => 2_500.0,
+            QuestMetric::Reps => 150.0,
+            QuestMetric::Sets => 20.0,
+            QuestMetric::Distance => 5.0,
+        }
+    }
+
+    // Amount a single logged exercise adds
+    pub fn measure(self, exercise: &Exercise) -> f32 {
+        match self {
+            QuestMetric::Volume => exercise.volume(),
+            QuestMetric::Reps => exercise.reps() as f32,
+            QuestMetric::Sets => exercise.working_sets().count() as f32,
+            QuestMetric::Distance => exercise.distance(),
+        }
+    }
+
+    // Sum over the workout, optionally only over one exercise
+    pub fn total(self, workout: &WorkoutContext, exercise_id: Option<&str>) -> f32 {
+        workout
+            .exercises
+            .iter()
+            .filter(|logged| exercise_id.is_none_or(|id| logged.exercise.id == id))
+            .map(|logged| self.measure(&logged.exercise))
+            .sum()
+    }
+}
+
+// Target of a progress-based quest, e.g. 5000 lbs of squats or 20 km in a week
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct QuestGoal {
+    pub metric: QuestMetric,
+    pub target: f32,
+    pub unit: String,
+    // only this exercise counts towards the goal when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exercise_id: Option<String>,
+}
+
+impl QuestGoal {
+    pub fn new(metric: QuestMetric, target: f32, exercise_id: Option<String>) -> QuestGoal {
+        return QuestGoal {
+            metric,
+            target,
+            unit: metric.unit().to_string(),
+            exercise_id,
+        };
+    }
+
+    // Progress a workout adds to the quest
+    pub fn measure(&self, workout: &WorkoutContext) -> f32 {
+        self.metric.total(workout, self.exercise_id.as_deref())
+    }
+
+    // Number of workout days the goal amounts to
+    pub fn days(&self) -> i32 {
+        ((self.target / self.metric.per_day()).round() as i32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::utils::schemas::{
+            ExerciseSet,
+            SetType,
+        },
+        serde_json::json,
+    };
+
+    fn set(reps: i32, weight: f32, set_type: SetType) -> ExerciseSet {
+        ExerciseSet {
+            reps,
+            weight,
+            set_type,
+            ..ExerciseSet::default()
+        }
+    }
+
+    fn squat(sets: Vec<ExerciseSet>) -> LoggedExercise {
+        LoggedExercise {
+            exercise: Exercise {
+                id: "Barbell_Squat".to_string(),
+                sets,
+            },
+            details: Some(ExerciseDetails {
+                category: ExerciseCategory::strength,
+                level: ExerciseLevel::intermediate,
+                equipment: Some(ExerciseEquipment::barbell),
+                muscles: vec![ExerciseMuscle::quadriceps, ExerciseMuscle::glutes],
+            }),
+        }
+    }
+
+    // Not in the catalog
+    fn run(distance: f32) -> LoggedExercise {
+        LoggedExercise {
+            exercise: Exercise {
+                id: "Running_Treadmill".to_string(),
+                sets: vec![ExerciseSet {
+                    distance,
+                    duration: 1800,
+                    ..ExerciseSet::default()
+                }],
+            },
+            details: None,
+        }
+    }
+
+    // 45 minutes at 7:30 on a scheduled day, squats with a warmup set and two working sets of 5 × 225
+    // and a 5 km run
+    fn workout() -> WorkoutContext {
+        WorkoutContext {
+            duration: 45,
+            exercises: vec![
+                squat(vec![
+                    set(10, 95.0, SetType::Warmup),
+                    set(5, 225.0, SetType::Working),
+                    set(5, 225.0, SetType::Working),
+                ]),
+                run(5.0),
+            ],
+            logged_at: at(7, 30),
+            scheduled_day: true,
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn met(requirement: serde_json::Value, workout: &WorkoutContext) -> bool {
+        serde_json::from_value::<QuestRequirement>(requirement)
+            .unwrap()
+            .is_met(workout)
+    }
+
+    #[test]
+    fn workout_duration_is_a_minimum() {
+        let workout = workout();
+        assert!(met(
+            json!({"kind": "workout_duration", "minutes": 45}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "workout_duration", "minutes": 46}),
+            &workout
+        ));
+    }
+
+    #[test]
+    fn category_muscle_equipment_and_level_come_from_the_catalog() {
+        let workout = workout();
+        assert!(met(
+            json!({"kind": "exercise_category", "category": "strength"}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "exercise_category", "category": "cardio"}),
+            &workout
+        ));
+        assert!(met(
+            json!({"kind": "exercise_muscle", "muscle": "glutes"}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "exercise_muscle", "muscle": "biceps"}),
+            &workout
+        ));
+        assert!(met(
+            json!({"kind": "equipment", "equipment": "barbell"}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "equipment", "equipment": "dumbbell"}),
+            &workout
+        ));
+        assert!(met(
+            json!({"kind": "level", "level": "intermediate"}),
+            &workout
+        ));
+        assert!(!met(json!({"kind": "level", "level": "expert"}), &workout));
+    }
+
+    #[test]
+    fn exercises_without_catalog_details_only_count_by_id() {
+        let workout = WorkoutContext {
+            exercises: vec![run(5.0)],
+            ..workout()
+        };
+        assert!(met(
+            json!({"kind": "exercise", "exercise_id": "Running_Treadmill"}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "exercise", "exercise_id": "Barbell_Squat"}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "exercise_category", "category": "cardio"}),
+            &workout
+        ));
+    }
+
+    #[test]
+    fn warmup_sets_are_left_out_of_volume_and_sets() {
+        let workout = workout();
+        assert!(met(
+            json!({"kind": "total_volume", "volume": 2250.0}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "total_volume", "volume": 2251.0}),
+            &workout
+        ));
+        // The two working squat sets and the set of the run
+        assert!(met(json!({"kind": "minimum_sets", "sets": 3}), &workout));
+        assert!(!met(json!({"kind": "minimum_sets", "sets": 4}), &workout));
+    }
+
+    #[test]
+    fn distance_is_summed_over_the_workout() {
+        let workout = WorkoutContext {
+            exercises: vec![run(3.0), run(2.5)],
+            ..workout()
+        };
+        assert!(met(
+            json!({"kind": "total_distance", "distance": 5.5}),
+            &workout
+        ));
+        assert!(!met(
+            json!({"kind": "total_distance", "distance": 6.0}),
+            &workout
+        ));
+    }
+
+    #[test]
+    fn time_of_day_windows_include_their_start_only() {
+        let morning = TimeOfDay {
+            start: at(5, 0),
+            end: at(9, 0),
+        };
+        let logged_at = |time| WorkoutContext {
+            logged_at: time,
+            ..workout()
+        };
+        assert!(morning.is_met(&logged_at(at(5, 0))));
+        assert!(morning.is_met(&logged_at(at(8, 59))));
+        assert!(!morning.is_met(&logged_at(at(9, 0))));
+        assert!(!morning.is_met(&logged_at(at(4, 59))));
+    }
+
+    #[test]
+    fn time_of_day_windows_wrap_past_midnight() {
+        let night = TimeOfDay {
+            start: at(22, 0),
+            end: at(2, 0),
+        };
+        let logged_at = |time| WorkoutContext {
+            logged_at: time,
+            ..workout()
+        };
+        assert!(night.is_met(&logged_at(at(22, 0))));
+        assert!(night.is_met(&logged_at(at(23, 59))));
+        assert!(night.is_met(&logged_at(at(0, 0))));
+        assert!(night.is_met(&logged_at(at(1, 59))));
+        assert!(!night.is_met(&logged_at(at(2, 0))));
+        assert!(!night.is_met(&logged_at(at(12, 0))));
+    }
+
+    #[test]
+    fn scheduled_day_comes_from_the_settings() {
+        let rest_day = WorkoutContext {
+            scheduled_day: false,
+            ..workout()
+        };
+        assert!(met(json!({"kind": "scheduled_day"}), &workout()));
+        assert!(!met(json!({"kind": "scheduled_day"}), &rest_day));
+    }
+
+    #[test]
+    fn requirements_are_tagged_by_kind() {
+        let night = QuestRequirement::TimeOfDay(TimeOfDay {
+            start: at(22, 0),
+            end: at(2, 0),
+        });
+        let json = json!({"kind": "time_of_day", "start": "22:00:00", "end": "02:00:00"});
+        assert_eq!(serde_json::to_value(&night).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<QuestRequirement>(json).unwrap(),
+            night
+        );
+
+        let duration = QuestRequirement::WorkoutDuration(WorkoutDuration { minutes: 30 });
+        let json = json!({"kind": "workout_duration", "minutes": 30});
+        assert_eq!(serde_json::to_value(&duration).unwrap(), json);
+
+        assert!(serde_json::from_value::<QuestRequirement>(json!({"kind": "unknown"})).is_err());
+        assert!(serde_json::from_value::<QuestRequirement>(json!({"minutes": 30})).is_err());
+    }
+
+    #[test]
+    fn goals_only_measure_their_exercise_when_given() {
+        let workout = workout();
+        let volume = QuestGoal::new(QuestMetric::Volume, 5000.0, None);
+        assert_eq!(volume.measure(&workout), 2250.0);
+        assert_eq!(volume.unit, "lbs");
+
+        let run = Some("Running_Treadmill".to_string());
+        assert_eq!(
+            QuestGoal::new(QuestMetric::Distance, 20.0, run).measure(&workout),
+            5.0
+        );
+        let squat = Some("Barbell_Squat".to_string());
+        assert_eq!(
+            QuestGoal::new(QuestMetric::Distance, 20.0, squat).measure(&workout),
+            0.0
+        );
+    }
+
+    #[test]
+    fn goal_days_round_to_the_nearest_day_and_are_at_least_one() {
+        // 2500 lbs of volume a day
+        assert_eq!(QuestGoal::new(QuestMetric::Volume, 7500.0, None).days(), 3);
+        assert_eq!(QuestGoal::new(QuestMetric::Volume, 8700.0, None).days(), 3);
+        assert_eq!(QuestGoal::new(QuestMetric::Volume, 8750.0, None).days(), 4);
+        assert_eq!(QuestGoal::new(QuestMetric::Volume, 100.0, None).days(), 1);
+        // 5 km a day
+        assert_eq!(QuestGoal::new(QuestMetric::Distance, 12.4, None).days(), 2);
+    }
+}
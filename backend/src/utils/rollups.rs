@@ -0,0 +1,513 @@
+use {
+    crate::utils::{
+        schedule::is_scheduled,
+        schemas::Exercise,
+    },
+    chrono::{
+        Datelike,
+        NaiveDate,
+        TimeDelta,
+    },
+    serde::Serialize,
+    sqlx::{
+        Acquire,
+        PgConnection,
+        PgPool,
+        types::Json,
+    },
+    std::collections::BTreeMap,
+};
+
+// Totals of the workouts of a day
+#[derive(Debug, Default, PartialEq)]
+pub struct DailyRollup {
+    pub sessions: i32,
+    pub minutes: i32,
+    pub volume: f32, // reps × weight of the working sets
+    pub distance: f32,
+}
+
+// Totals of an exercise over the workouts of a day
+#[derive(Debug, Default, PartialEq)]
+pub struct ExerciseRollup {
+    pub volume: f32,
+    pub sets: i32, // working sets
+    pub reps: i32,
+    pub distance: f32,
+    pub duration: i32, // seconds
+}
+
+pub struct Rollups<'a> {
+    pub days: BTreeMap<NaiveDate, DailyRollup>,
+    pub exercises: BTreeMap<(NaiveDate, &'a str), ExerciseRollup>, // by day and exercise id
+}
+
+// Workouts of the history as (day, minutes, exercises), summed up by day and by exercise of the day
+pub fn roll_up<'a>(
+    workouts: impl IntoIterator<Item = (NaiveDate, i32, &'a [Exercise])>,
+) -> Rollups<'a> {
+    let mut days: BTreeMap<NaiveDate, DailyRollup> = BTreeMap::new();
+    let mut exercises: BTreeMap<(NaiveDate, &str), ExerciseRollup> = BTreeMap::new();
+
+    for (date, minutes, logged) in workouts {
+        let day = days.entry(date).or_default();
+        day.sessions += 1;
+        day.minutes += minutes;
+
+        for exercise in logged {
+            day.volume += exercise.volume();
+            day.distance += exercise.distance();
+
+            let rollup = exercises
+                .entry((date, exercise.id.as_str()))
+                .or_default();
+            rollup.volume += exercise.volume();
+            rollup.sets += exercise.working_sets().count() as i32;
+            rollup.reps += exercise.reps();
+            rollup.distance += exercise.distance();
+            rollup.duration += exercise.duration();
+        }
+    }
+
+    return Rollups { days, exercises };
+}
+
+// Totals of a week or a month
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct AnalyticsPeriod {
+    pub start: NaiveDate, // Monday of the week or first day of the month
+    pub sessions: i32,
+    pub minutes: i32,
+    pub volume: f32,   // reps × weight of the working sets
+    pub distance: f32, // km
+    pub scheduled_days: i32,
+    pub completed_days: i32, // scheduled days with a workout
+}
+
+// Days before today are over, today only counts once it has a workout
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Adherence {
+    pub scheduled_days: i32,
+    pub completed_days: i32,
+    pub unscheduled_days: i32, // days off the schedule with a workout
+    pub rate: Option<f32>,     // share of the scheduled days completed, None without scheduled days
+}
+
+pub struct Trends {
+    pub weekly: BTreeMap<NaiveDate, AnalyticsPeriod>,
+    pub monthly: BTreeMap<NaiveDate, AnalyticsPeriod>,
+    pub adherence: Adherence,
+}
+
+// Weeks start on Monday, the ones cut off by the earliest date start on it
+pub fn week_start(day: NaiveDate) -> NaiveDate {
+    return day
+        .checked_sub_signed(TimeDelta::days(day.weekday().num_days_from_monday() as i64))
+        .unwrap_or(NaiveDate::MIN);
+}
+
+pub fn month_start(day: NaiveDate) -> NaiveDate {
+    return day.with_day(1).unwrap();
+}
+
+// Every period of the range, including the ones without a workout
+fn periods(
+    from: NaiveDate,
+    to: NaiveDate,
+    start: fn(NaiveDate) -> NaiveDate,
+) -> BTreeMap<NaiveDate, AnalyticsPeriod> {
+    return from
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(start)
+        .map(|start| {
+            let period = AnalyticsPeriod {
+                start,
+                ..AnalyticsPeriod::default()
+            };
+            (start, period)
+        })
+        .collect();
+}
+
+// Daily rollups of the days from to to summed up by week and month, and held against the workout
+// schedule up to today
+pub fn trends(
+    from: NaiveDate,
+    to: NaiveDate,
+    today: NaiveDate,
+    workout_schedule: &[bool],
+    days: &BTreeMap<NaiveDate, DailyRollup>,
+) -> Trends {
+    let mut weekly = periods(from, to, week_start);
+    let mut monthly = periods(from, to, month_start);
+    for (date, day) in days.range(from..=to) {
+        for period in [
+            weekly.get_mut(&week_start(*date)),
+            monthly.get_mut(&month_start(*date)),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            period.sessions += day.sessions;
+            period.minutes += day.minutes;
+            period.volume += day.volume;
+            period.distance += day.distance;
+        }
+    }
+
+    let mut adherence = Adherence::default();
+    for day in from.iter_days().take_while(|day| *day <= to) {
+        let completed = days.contains_key(&day);
+        if day > today || (day == today && !completed) {
+            break;
+        }
+
+        if !is_scheduled(workout_schedule, day) {
+            adherence.unscheduled_days += completed as i32;
+            continue;
+        }
+        adherence.scheduled_days += 1;
+        adherence.completed_days += completed as i32;
+        for period in [
+            weekly.get_mut(&week_start(day)),
+            monthly.get_mut(&month_start(day)),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            period.scheduled_days += 1;
+            period.completed_days += completed as i32;
+        }
+    }
+    if adherence.scheduled_days > 0 {
+        adherence.rate = Some(adherence.completed_days as f32 / adherence.scheduled_days as f32);
+    }
+
+    return Trends {
+        weekly,
+        monthly,
+        adherence,
+    };
+}
+
+// Writes of the same user wait for each other until their transaction ends, so the last one sums up every
+// workout of the day. Writes that lock quests or the character take it first.
+pub async fn lock_rollups(conn: &mut PgConnection, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            SELECT pg_advisory_xact_lock(hashtext('history_rollups'), $1)
+        "#,
+        user_id
+    )
+    .execute(conn)
+    .await?;
+
+    return Ok(());
+}
+
+// Recomputes the rollups of days of the user's history, called with every day a write to the
+// history added, changed or removed a workout of
+pub async fn update_rollups(
+    conn: &mut PgConnection,
+    user_id: i32,
+    dates: &[NaiveDate],
+) -> Result<(), sqlx::Error> {
+    if dates.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = conn.begin().await?;
+    lock_rollups(&mut tx, user_id).await?;
+
+    let workouts = sqlx::query!(
+        r#"
+            SELECT date, duration, exercises as "exercises: Json<Vec<Exercise>>"
+            FROM history
+            WHERE user_id = $1 AND date = ANY($2)
+        "#,
+        user_id,
+        dates,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let Rollups { days, exercises } = roll_up(
+        workouts
+            .iter()
+            .map(|workout| (workout.date, workout.duration, workout.exercises.as_slice())),
+    );
+
+    sqlx::query!(
+        r#"
+            DELETE FROM history_daily_rollups
+            WHERE user_id = $1 AND date = ANY($2)
+        "#,
+        user_id,
+        dates,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            DELETE FROM history_exercise_rollups
+            WHERE user_id = $1 AND date = ANY($2)
+        "#,
+        user_id,
+        dates,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO history_daily_rollups (user_id, date, sessions, minutes, volume, distance)
+            SELECT $1, *
+            FROM UNNEST($2::date[], $3::integer[], $4::integer[], $5::real[], $6::real[])
+        "#,
+        user_id,
+        &days.keys().copied().collect::<Vec<NaiveDate>>(),
+        &days.values().map(|day| day.sessions).collect::<Vec<i32>>(),
+        &days.values().map(|day| day.minutes).collect::<Vec<i32>>(),
+        &days.values().map(|day| day.volume).collect::<Vec<f32>>(),
+        &days.values().map(|day| day.distance).collect::<Vec<f32>>(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO history_exercise_rollups
+                (user_id, date, exercise_id, volume, sets, reps, distance, duration)
+            SELECT $1, *
+            FROM UNNEST(
+                $2::date[], $3::text[], $4::real[], $5::integer[], $6::integer[], $7::real[],
+                $8::integer[]
+            )
+        "#,
+        user_id,
+        &exercises.keys().map(|(date, _)| *date).collect::<Vec<NaiveDate>>(),
+        &exercises.keys().map(|(_, id)| *id).collect::<Vec<&str>>() as &[&str],
+        &exercises.values().map(|rollup| rollup.volume).collect::<Vec<f32>>(),
+        &exercises.values().map(|rollup| rollup.sets).collect::<Vec<i32>>(),
+        &exercises.values().map(|rollup| rollup.reps).collect::<Vec<i32>>(),
+        &exercises.values().map(|rollup| rollup.distance).collect::<Vec<f32>>(),
+        &exercises.values().map(|rollup| rollup.duration).collect::<Vec<i32>>(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    return Ok(());
+}
+
+// Rolls up the days of the history without rollups, e.g. the ones logged before they were kept
+pub async fn backfill_rollups(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let missing = sqlx::query!(
+        r#"
+            SELECT h.user_id, ARRAY_AGG(DISTINCT h.date) as "dates!"
+            FROM history h
+            WHERE NOT EXISTS (
+                SELECT 1
+                FROM history_daily_rollups r
+                WHERE r.user_id = h.user_id AND r.date = h.date
+            )
+            GROUP BY h.user_id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut conn = pool.acquire().await?;
+    for user in missing {
+        update_rollups(&mut conn, user.user_id, &user.dates).await?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::utils::schemas::{
+            ExerciseSet,
+            SetType,
+        },
+    };
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn set(reps: i32, weight: f32, set_type: SetType) -> ExerciseSet {
+        ExerciseSet {
+            reps,
+            weight,
+            set_type,
+            ..ExerciseSet::default()
+        }
+    }
+
+    fn exercise(id: &str, sets: Vec<ExerciseSet>) -> Exercise {
+        Exercise {
+            id: id.to_string(),
+            sets,
+        }
+    }
+
+    #[test]
+    fn warmup_sets_are_left_out_of_the_volume() {
+        let workout = vec![exercise(
+            "Barbell_Squat",
+            vec![
+                set(10, 95.0, SetType::Warmup),
+                set(5, 225.0, SetType::Working),
+                set(5, 225.0, SetType::Working),
+            ],
+        )];
+        let Rollups { days, exercises } = roll_up([(day(1), 45, workout.as_slice())]);
+
+        assert_eq!(
+            days[&day(1)],
+            DailyRollup {
+                sessions: 1,
+                minutes: 45,
+                volume: 2250.0,
+                distance: 0.0,
+            }
+        );
+        assert_eq!(
+            exercises[&(day(1), "Barbell_Squat")],
+            ExerciseRollup {
+                volume: 2250.0,
+                sets: 2,
+                reps: 10,
+                distance: 0.0,
+                duration: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn workouts_are_summed_by_day_and_exercise() {
+        let morning = vec![exercise("Barbell_Curl", vec![set(10, 50.0, SetType::Working)])];
+        let evening = vec![
+            exercise("Barbell_Curl", vec![set(8, 60.0, SetType::Working)]),
+            exercise("Barbell_Squat", vec![set(5, 200.0, SetType::Working)]),
+        ];
+        let Rollups { days, exercises } = roll_up([
+            (day(1), 20, morning.as_slice()),
+            (day(1), 40, evening.as_slice()),
+            (day(2), 20, morning.as_slice()),
+        ]);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[&day(1)].sessions, 2);
+        assert_eq!(days[&day(1)].minutes, 60);
+        assert_eq!(days[&day(1)].volume, 500.0 + 480.0 + 1000.0);
+        assert_eq!(days[&day(2)].sessions, 1);
+        assert_eq!(exercises.len(), 3);
+        assert_eq!(exercises[&(day(1), "Barbell_Curl")].sets, 2);
+        assert_eq!(exercises[&(day(1), "Barbell_Curl")].volume, 980.0);
+        assert_eq!(exercises[&(day(2), "Barbell_Curl")].reps, 10);
+    }
+
+    #[test]
+    fn aggregated_exercises_are_expanded_like_everywhere_else() {
+        let workout: Vec<Exercise> = serde_json::from_value(serde_json::json!([
+            {"id": "Barbell_Curl", "sets": 3, "reps": 10, "weight": 50.0, "distance": 0.0},
+            {"id": "Running_Treadmill", "sets": 0, "reps": 0, "weight": 0.0, "distance": 5.0},
+        ]))
+        .unwrap();
+        let Rollups { days, exercises } = roll_up([(day(1), 30, workout.as_slice())]);
+
+        assert_eq!(days[&day(1)].volume, 1500.0);
+        assert_eq!(days[&day(1)].distance, 5.0);
+        assert_eq!(exercises[&(day(1), "Barbell_Curl")].sets, 3);
+        assert_eq!(exercises[&(day(1), "Running_Treadmill")].sets, 1);
+        assert_eq!(exercises[&(day(1), "Running_Treadmill")].distance, 5.0);
+    }
+
+    fn trained(dates: &[u32]) -> BTreeMap<NaiveDate, DailyRollup> {
+        return dates
+            .iter()
+            .map(|date| {
+                let rollup = DailyRollup {
+                    sessions: 1,
+                    minutes: 30,
+                    ..DailyRollup::default()
+                };
+                (day(*date), rollup)
+            })
+            .collect();
+    }
+
+    // Sunday first, like the settings
+    const WEEKDAYS: [bool; 7] = [false, true, false, true, false, true, false];
+
+    #[test]
+    fn weeks_start_on_monday_and_months_on_the_first() {
+        // 2026-10-01 is a Thursday
+        assert_eq!(
+            week_start(day(1)),
+            NaiveDate::from_ymd_opt(2026, 9, 28).unwrap()
+        );
+        assert_eq!(week_start(day(5)), day(5));
+        assert_eq!(week_start(day(11)), day(5));
+        assert_eq!(month_start(day(31)), day(1));
+        assert_eq!(week_start(NaiveDate::MIN), NaiveDate::MIN);
+    }
+
+    #[test]
+    fn periods_cover_the_range_with_and_without_workouts() {
+        let Trends {
+            weekly, monthly, ..
+        } = trends(day(1), day(31), day(31), &WEEKDAYS, &trained(&[1, 2, 20]));
+
+        assert_eq!(weekly.len(), 5);
+        assert_eq!(
+            weekly[&NaiveDate::from_ymd_opt(2026, 9, 28).unwrap()].sessions,
+            2
+        );
+        assert_eq!(weekly[&day(5)].sessions, 0);
+        assert_eq!(weekly[&day(19)].minutes, 30);
+        assert_eq!(monthly.len(), 1);
+        assert_eq!(monthly[&day(1)].sessions, 3);
+    }
+
+    #[test]
+    fn adherence_counts_scheduled_days_up_to_today() {
+        // Mondays, Wednesdays and Fridays from the 5th to the 14th: 5, 7, 9, 12 and 14
+        let Trends {
+            weekly, adherence, ..
+        } = trends(day(5), day(18), day(14), &WEEKDAYS, &trained(&[5, 6, 9]));
+
+        assert_eq!(
+            adherence,
+            Adherence {
+                scheduled_days: 4,
+                completed_days: 2,
+                unscheduled_days: 1,
+                rate: Some(0.5),
+            }
+        );
+        assert_eq!(weekly[&day(5)].scheduled_days, 3);
+        assert_eq!(weekly[&day(5)].completed_days, 2);
+        assert_eq!(weekly[&day(12)].scheduled_days, 1);
+    }
+
+    #[test]
+    fn today_counts_once_it_has_a_workout() {
+        let Trends { adherence, .. } = trends(day(12), day(12), day(12), &WEEKDAYS, &trained(&[]));
+        assert_eq!(adherence.scheduled_days, 0);
+        assert_eq!(adherence.rate, None);
+
+        let Trends { adherence, .. } =
+            trends(day(12), day(12), day(12), &WEEKDAYS, &trained(&[12]));
+        assert_eq!(adherence.scheduled_days, 1);
+        assert_eq!(adherence.rate, Some(1.0));
+    }
+}
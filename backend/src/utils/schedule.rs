@@ -0,0 +1,36 @@
+use chrono::{
+    Datelike,
+    NaiveDate,
+};
+
+// Whether the user trains on this day of the week, index 0 of the schedule is Sunday
+pub fn is_scheduled(workout_schedule: &[bool], day: NaiveDate) -> bool {
+    workout_schedule
+        .get(day.weekday().num_days_from_sunday() as usize)
+        .copied()
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sunday 2026-10-18 to Saturday 2026-10-24
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    #[test]
+    fn the_schedule_starts_on_sunday() {
+        let schedule = [true, false, false, false, false, false, true];
+        assert!(is_scheduled(&schedule, day(18)));
+        assert!(!is_scheduled(&schedule, day(19)));
+        assert!(is_scheduled(&schedule, day(24)));
+    }
+
+    #[test]
+    fn days_missing_from_the_schedule_are_off() {
+        assert!(!is_scheduled(&[], day(18)));
+        assert!(!is_scheduled(&[true, true], day(24)));
+    }
+}
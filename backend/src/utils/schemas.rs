@@ -1,11 +1,21 @@
 use {
+    crate::utils::requirements::{
+        QuestGoal,
+        QuestRequirement,
+    },
+    rand::Rng,
     serde::{
         Deserialize,
         Serialize,
     },
     sqlx::types::{
         Json,
-        chrono::NaiveDate,
+        chrono::{
+            DateTime,
+            NaiveDate,
+            NaiveDateTime,
+            Utc,
+        },
     },
 };
 
@@ -48,13 +58,195 @@ pub struct Class {
 //     pub workout_schedule: Vec<bool>,
 // }
 
-#[derive(Clone, Debug, Deserialize, Serialize, sqlx::Type)]
-pub struct Exercise {
-    pub id: String,
-    pub sets: i32,
+pub const LBS_PER_KG: f32 = 2.204_622_6;
+
+// Weights are stored in lbs, the unit the app logs them in
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightUnit {
+    Kg,
+    #[default]
+    Lbs,
+}
+
+impl WeightUnit {
+    pub fn to_lbs(self, weight: f32) -> f32 {
+        match self {
+            WeightUnit::Kg => weight * LBS_PER_KG,
+            WeightUnit::Lbs => weight,
+        }
+    }
+}
+
+// Kind of a logged set, warmup sets don't count towards volume, reps or sets
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SetType {
+    Warmup,
+    #[default]
+    Working,
+    Drop,
+    Failure,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExerciseSet {
+    #[serde(default)]
     pub reps: i32,
+    #[serde(default)]
     pub weight: f32,
+    #[serde(default)]
     pub distance: f32,
+    #[serde(default)]
+    pub duration: i32, // seconds
+    pub rpe: Option<f32>,
+    #[serde(default, rename = "type")]
+    pub set_type: SetType,
+    pub rest: Option<i32>, // seconds before the next set
+    // Recorded by GPS watches and apps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pace: Option<f32>, // seconds per km
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation_gain: Option<f32>, // m
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heart_rate: Option<i32>, // average bpm
+}
+
+impl ExerciseSet {
+    // Amounts can't be negative and RPE is rated from 1 to 10
+    pub fn validate(&self) -> Result<(), String> {
+        let amounts = [
+            ("reps", self.reps as f32),
+            ("weight", self.weight),
+            ("distance", self.distance),
+            ("duration", self.duration as f32),
+            ("rest", self.rest.unwrap_or(0) as f32),
+        ];
+        if let Some((amount, _)) = amounts
+            .iter()
+            .find(|(_, value)| !value.is_finite() || *value < 0.0)
+        {
+            return Err(format!("Invalid {}, it can't be negative", amount));
+        }
+        if self.rpe.is_some_and(|rpe| !(1.0..=10.0).contains(&rpe)) {
+            return Err(String::from("Invalid rpe, it is rated from 1 to 10"));
+        }
+
+        return Ok(());
+    }
+}
+
+// An exercise of a routine or a logged workout, set by set
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "ExerciseFormat", into = "ExerciseFormat")]
+pub struct Exercise {
+    pub id: String,
+    pub sets: Vec<ExerciseSet>,
+}
+
+// Exercises used to be stored as one number of sets with the same reps, weight and distance for each of them
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum SetsFormat {
+    Sets(Vec<ExerciseSet>),
+    Aggregated(i32),
+}
+
+// The aggregated fields are still written next to the sets for clients reading them
+#[derive(Deserialize, Serialize)]
+struct ExerciseFormat {
+    id: String,
+    sets: SetsFormat,
+    #[serde(default)]
+    reps: i32, // per set
+    #[serde(default)]
+    weight: f32, // average of the working sets
+    #[serde(default)]
+    distance: f32, // total
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    set_details: Option<Vec<ExerciseSet>>,
+}
+
+impl From<ExerciseFormat> for Exercise {
+    fn from(format: ExerciseFormat) -> Exercise {
+        let sets = match (format.set_details, format.sets) {
+            (Some(sets), _) | (None, SetsFormat::Sets(sets)) => sets,
+            // The distance was the total of the exercise, it is kept on the first set
+            (None, SetsFormat::Aggregated(count)) => {
+                let count = if count <= 0 && format.distance > 0.0 { 1 } else { count.max(0) };
+                (0..count)
+                    .map(|index| ExerciseSet {
+                        reps: format.reps,
+                        weight: format.weight,
+                        distance: if index == 0 { format.distance } else { 0.0 },
+                        ..ExerciseSet::default()
+                    })
+                    .collect()
+            }
+        };
+
+        return Exercise {
+            id: format.id,
+            sets,
+        };
+    }
+}
+
+impl From<Exercise> for ExerciseFormat {
+    fn from(exercise: Exercise) -> ExerciseFormat {
+        let working_sets = exercise.working_sets().count() as i32;
+        let (reps, weight) = match working_sets {
+            0 => (0, 0.0),
+            _ => (
+                (exercise.reps() as f32 / working_sets as f32).round() as i32,
+                exercise.working_sets().map(|set| set.weight).sum::<f32>() / working_sets as f32,
+            ),
+        };
+
+        return ExerciseFormat {
+            sets: SetsFormat::Aggregated(exercise.sets.len() as i32),
+            reps,
+            weight,
+            distance: exercise.distance(),
+            id: exercise.id,
+            set_details: Some(exercise.sets),
+        };
+    }
+}
+
+impl Exercise {
+    pub fn validate(&self) -> Result<(), String> {
+        for set in &self.sets {
+            set.validate().map_err(|e| format!("{}: {}", self.id, e))?;
+        }
+        return Ok(());
+    }
+
+    pub fn working_sets(&self) -> impl Iterator<Item = &ExerciseSet> {
+        self.sets
+            .iter()
+            .filter(|set| set.set_type != SetType::Warmup)
+    }
+
+    // reps × weight over the working sets
+    pub fn volume(&self) -> f32 {
+        self.working_sets()
+            .map(|set| set.reps as f32 * set.weight)
+            .sum()
+    }
+
+    pub fn reps(&self) -> i32 {
+        self.working_sets().map(|set| set.reps).sum()
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.sets.iter().map(|set| set.distance).sum()
+    }
+
+    // seconds
+    pub fn duration(&self) -> i32 {
+        self.sets.iter().map(|set| set.duration).sum()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, sqlx::FromRow, Serialize)]
@@ -96,9 +288,11 @@ pub struct History {
     pub id: i32,
     pub name: String,
     pub exercises: Json<Vec<Exercise>>,
-    pub date: NaiveDate,
-    // pub time: NaiveTime,
-    pub duration: i32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub timezone: String, // the user's timezone when the workout was logged
+    pub date: NaiveDate,  // day the workout started on in its timezone
+    pub duration: i32,    // minutes between start and end
     pub points: i32,
     pub coins: i32,
 }
@@ -115,6 +309,13 @@ pub struct Equipped {
     pub weapon: Option<i32>,
 }
 
+impl Equipped {
+    pub fn contains(&self, item_id: i32) -> bool {
+        [self.arms, self.background, self.body, self.head].contains(&item_id)
+            || [self.head_accessory, self.pet, self.weapon].contains(&Some(item_id))
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::Type)]
 #[sqlx(type_name = "inventory")]
 pub struct Inventory {
@@ -127,6 +328,53 @@ pub struct Inventory {
     pub weapons: Vec<i32>,
 }
 
+impl Inventory {
+    pub fn items(&self, category: ItemCategory) -> &Vec<i32> {
+        match category {
+            ItemCategory::Arm => &self.arms,
+            ItemCategory::Background => &self.backgrounds,
+            ItemCategory::Body => &self.bodies,
+            ItemCategory::Head => &self.heads,
+            ItemCategory::Head_Accessory => &self.head_accessories,
+            ItemCategory::Pet => &self.pets,
+            ItemCategory::Weapon => &self.weapons,
+        }
+    }
+
+    pub fn items_mut(&mut self, category: ItemCategory) -> &mut Vec<i32> {
+        match category {
+            ItemCategory::Arm => &mut self.arms,
+            ItemCategory::Background => &mut self.backgrounds,
+            ItemCategory::Body => &mut self.bodies,
+            ItemCategory::Head => &mut self.heads,
+            ItemCategory::Head_Accessory => &mut self.head_accessories,
+            ItemCategory::Pet => &mut self.pets,
+            ItemCategory::Weapon => &mut self.weapons,
+        }
+    }
+
+    pub fn contains(&self, category: ItemCategory, item_id: i32) -> bool {
+        self.items(category).contains(&item_id)
+    }
+
+    // Every owned item id regardless of category
+    pub fn all_items(&self) -> Vec<i32> {
+        [
+            &self.arms,
+            &self.backgrounds,
+            &self.bodies,
+            &self.heads,
+            &self.head_accessories,
+            &self.pets,
+            &self.weapons,
+        ]
+        .into_iter()
+        .flatten()
+        .copied()
+        .collect()
+    }
+}
+
 #[derive(Clone, Deserialize, sqlx::FromRow, Serialize)]
 pub struct CharactersRow {
     pub user_id: i32,
@@ -162,6 +410,9 @@ pub struct SettingsRow {
     pub first_name: String,
     pub last_name: String,
     pub workout_schedule: Vec<bool>,
+    pub timezone: String,
+    pub quest_stretch: f32,
+    pub target_neglected_muscles: bool,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, sqlx::Type)]
@@ -217,6 +468,47 @@ pub enum ExerciseMuscle {
     triceps,
 }
 
+#[derive(
+    Clone, Copy, Serialize, Deserialize, sqlx::Type, strum::VariantArray, Debug, PartialEq,
+)]
+#[sqlx(type_name = "exercise_equipment", rename_all = "lowercase")]
+#[allow(non_camel_case_types)]
+pub enum ExerciseEquipment {
+    #[serde(rename = "medicine ball")]
+    #[sqlx(rename = "medicine ball")]
+    medicine_ball,
+    dumbbell,
+    #[serde(rename = "body only")]
+    #[sqlx(rename = "body only")]
+    body_only,
+    bands,
+    kettlebells,
+    #[serde(rename = "foam roll")]
+    #[sqlx(rename = "foam roll")]
+    foam_roll,
+    cable,
+    machine,
+    barbell,
+    #[serde(rename = "exercise ball")]
+    #[sqlx(rename = "exercise ball")]
+    exercise_ball,
+    #[serde(rename = "e-z curl bar")]
+    #[sqlx(rename = "e-z curl bar")]
+    ez_curl_bar,
+    other,
+}
+
+#[derive(
+    Clone, Copy, Serialize, Deserialize, sqlx::Type, strum::VariantArray, Debug, PartialEq,
+)]
+#[sqlx(type_name = "exercise_level", rename_all = "lowercase")]
+#[allow(non_camel_case_types)]
+pub enum ExerciseLevel {
+    beginner,
+    intermediate,
+    expert,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "quest_difficulty", rename_all = "lowercase")]
 pub enum QuestDifficulty {
@@ -234,6 +526,15 @@ impl QuestDifficulty {
         }
     }
 
+    // Chest granted on top of the coins and exp when a quest is completed
+    pub fn chest(self) -> &'static str {
+        match self {
+            QuestDifficulty::Easy => "Wooden Chest",
+            QuestDifficulty::Medium => "Iron Chest",
+            QuestDifficulty::Hard => "Golden Chest",
+        }
+    }
+
     pub fn coins(&self) -> i32 {
         match self {
             QuestDifficulty::Easy => 250,
@@ -242,11 +543,29 @@ impl QuestDifficulty {
         }
     }
 
-    pub fn number_of_workouts_needed(self) -> i32 {
+    pub fn number_of_workouts_needed(self, rng: &mut impl Rng) -> i32 {
         match self {
             QuestDifficulty::Easy => 1,
-            QuestDifficulty::Medium => rand::random_range(3..=5),
-            QuestDifficulty::Hard => rand::random_range(10..=15),
+            QuestDifficulty::Medium => rng.random_range(3..=5),
+            QuestDifficulty::Hard => rng.random_range(10..=15),
+        }
+    }
+
+    // How long a quest can be worked on before it expires
+    pub fn lifetime(self) -> chrono::Duration {
+        match self {
+            QuestDifficulty::Easy => chrono::Duration::days(1),
+            QuestDifficulty::Medium => chrono::Duration::days(3),
+            QuestDifficulty::Hard => chrono::Duration::days(7),
+        }
+    }
+
+    // Cap on quests that are in progress or waiting to be claimed
+    pub fn max_active(self) -> i64 {
+        match self {
+            QuestDifficulty::Easy => 3,
+            QuestDifficulty::Medium => 2,
+            QuestDifficulty::Hard => 1,
         }
     }
 
@@ -258,20 +577,48 @@ impl QuestDifficulty {
         }
     }
 
-    pub fn workout_duration(self) -> i32 {
+    // Total sets logged in a single workout
+    pub fn minimum_sets(self, rng: &mut impl Rng) -> i32 {
         match self {
-            QuestDifficulty::Easy => rand::random_range(1..=6) * 5,
-            QuestDifficulty::Medium => rand::random_range(9..=12) * 5,
-            QuestDifficulty::Hard => rand::random_range(16..=24) * 5,
+            QuestDifficulty::Easy => rng.random_range(3..=6) * 2,
+            QuestDifficulty::Medium => rng.random_range(7..=10) * 2,
+            QuestDifficulty::Hard => rng.random_range(12..=15) * 2,
+        }
+    }
+
+    pub fn workout_duration(self, rng: &mut impl Rng) -> i32 {
+        match self {
+            QuestDifficulty::Easy => rng.random_range(1..=6) * 5,
+            QuestDifficulty::Medium => rng.random_range(9..=12) * 5,
+            QuestDifficulty::Hard => rng.random_range(16..=24) * 5,
         }
     }
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
+#[sqlx(type_name = "workout_session_status", rename_all = "lowercase")]
+pub enum WorkoutSessionStatus {
+    Active,
+    Paused,
+    Finished,  // logged to the history
+    Abandoned, // discarded by the user or closed after going stale
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
 #[sqlx(type_name = "quest_status", rename_all = "lowercase")]
 pub enum QuestStatus {
     Incomplete,
-    Complete,
+    Complete, // rewards are waiting to be claimed
+    Claimed,
+    Abandoned,
+    Expired,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
+#[sqlx(type_name = "quest_board", rename_all = "lowercase")]
+pub enum QuestBoard {
+    Daily,
+    Weekly,
 }
 
 #[derive(Clone, Deserialize, sqlx::FromRow, Serialize)]
@@ -283,12 +630,52 @@ pub struct QuestRow {
     pub status: QuestStatus,
     pub number_of_workouts_needed: i32,
     pub number_of_workouts_completed: i32,
-    // possible requirements
+    // a workout counts towards the quest when it meets every requirement
+    pub requirements: Json<Vec<QuestRequirement>>,
+    // progress-based quests accumulate towards a goal instead of counting workouts
+    pub goal: Option<Json<QuestGoal>>,
+    pub progress: f32,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>, // campaign quests never expire
+    pub claimed_at: Option<NaiveDateTime>,
+    pub board: Option<QuestBoard>,
+    pub campaign_step_id: Option<i32>,
+    // regenerates the quest, authored quests and quests sized apart from the generator have none
+    pub seed: Option<i64>,
+    // derived from the requirements for clients reading the requirements quests had as columns
     pub workout_duration: Option<i32>,
     pub exercise_category: Option<ExerciseCategory>,
     pub exercise_muscle: Option<ExerciseMuscle>,
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
+#[sqlx(type_name = "group_member_status", rename_all = "lowercase")]
+pub enum GroupMemberStatus {
+    Invited,
+    Joined,
+    Declined,
+    Left,
+}
+
+// Quest shared between friends, rewards are given to every joined member on completion
+#[derive(Clone, Deserialize, sqlx::FromRow, Serialize)]
+pub struct GroupQuestRow {
+    pub id: i32,
+    pub owner_id: i32,
+    pub name: String,
+    pub difficulty: QuestDifficulty,
+    pub status: QuestStatus,
+    pub number_of_workouts_needed: i32,
+    pub number_of_workouts_completed: i32,
+    pub requirements: Json<Vec<QuestRequirement>>,
+    pub goal: Option<Json<QuestGoal>>,
+    pub progress: f32,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub seed: Option<i64>,
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq, Eq, Hash)]
 #[sqlx(type_name = "item_rarity", rename_all = "lowercase")]
 pub enum ItemRarity {
@@ -300,8 +687,15 @@ pub enum ItemRarity {
     Default,
 }
 
+impl ItemRarity {
+    pub fn is_rare_or_better(self) -> bool {
+        matches!(self, ItemRarity::Rare | ItemRarity::Epic | ItemRarity::Legendary)
+    }
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
 #[sqlx(type_name = "item_category", rename_all = "lowercase")]
+#[allow(non_camel_case_types)]
 pub enum ItemCategory {
     Arm,
     Background,
@@ -321,3 +715,179 @@ pub struct ItemRow {
     pub rarity: ItemRarity,
     pub path: String,
 }
+
+#[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
+#[sqlx(type_name = "trade_status", rename_all = "lowercase")]
+pub enum TradeStatus {
+    Pending,
+    Accepted,
+    Rejected,
+    Cancelled,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
+#[sqlx(type_name = "chest_source", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ChestSource {
+    Quest,
+    LevelUp,
+}
+
+#[derive(Clone, Copy, Deserialize, sqlx::FromRow, Serialize)]
+pub struct ChestDrop {
+    pub rarity: ItemRarity,
+    pub weight: i32,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, sqlx::Type, Debug, PartialEq)]
+#[sqlx(type_name = "notification_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    WishlistInShop,
+    WishlistOnSale,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        serde_json::json,
+    };
+
+    fn exercise(value: serde_json::Value) -> Exercise {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn sets_are_read_one_by_one() {
+        let exercise = exercise(json!({
+            "id": "Barbell_Squat",
+            "sets": [
+                {"reps": 5, "weight": 60.0, "type": "warmup"},
+                {"reps": 5, "weight": 100.0, "rpe": 8.0},
+            ]
+        }));
+        assert_eq!(exercise.sets.len(), 2);
+        assert_eq!(exercise.sets[0].set_type, SetType::Warmup);
+        assert_eq!(exercise.sets[1].set_type, SetType::Working);
+        assert_eq!(exercise.sets[1].rpe, Some(8.0));
+    }
+
+    #[test]
+    fn aggregated_sets_are_expanded_with_the_distance_on_the_first_one() {
+        let exercise = exercise(json!({
+            "id": "Rowing_Stationary", "sets": 3, "reps": 10, "weight": 20.0, "distance": 2.5
+        }));
+        assert_eq!(exercise.sets.len(), 3);
+        assert!(exercise.sets.iter().all(|set| set.reps == 10 && set.weight == 20.0));
+        assert_eq!(exercise.sets[0].distance, 2.5);
+        assert_eq!(exercise.distance(), 2.5);
+    }
+
+    #[test]
+    fn distance_without_sets_is_one_set() {
+        let exercise = exercise(json!({"id": "Running_Treadmill", "sets": 0, "distance": 5.0}));
+        assert_eq!(exercise.sets.len(), 1);
+        assert_eq!(exercise.distance(), 5.0);
+    }
+
+    #[test]
+    fn negative_sets_are_no_sets() {
+        let exercise = exercise(json!({"id": "Barbell_Squat", "sets": -2, "reps": 5}));
+        assert!(exercise.sets.is_empty());
+    }
+
+    #[test]
+    fn set_details_win_over_the_aggregated_fields() {
+        let exercise = exercise(json!({
+            "id": "Barbell_Squat",
+            "sets": 1,
+            "reps": 5,
+            "weight": 100.0,
+            "distance": 0.0,
+            "set_details": [{"reps": 3, "weight": 120.0}, {"reps": 3, "weight": 125.0}]
+        }));
+        assert_eq!(exercise.sets.len(), 2);
+        assert_eq!(exercise.volume(), 3.0 * 120.0 + 3.0 * 125.0);
+    }
+
+    #[test]
+    fn aggregated_fields_are_written_next_to_the_sets() {
+        let exercise = exercise(json!({
+            "id": "Barbell_Squat",
+            "sets": [
+                {"reps": 10, "weight": 40.0, "type": "warmup"},
+                {"reps": 5, "weight": 100.0},
+                {"reps": 4, "weight": 110.0},
+            ]
+        }));
+        let value = serde_json::to_value(&exercise).unwrap();
+        assert_eq!(value["sets"], 3);
+        assert_eq!(value["reps"], 5); // 4.5 rounded
+        assert_eq!(value["weight"], 105.0);
+        assert_eq!(value["distance"], 0.0);
+        assert_eq!(value["set_details"].as_array().unwrap().len(), 3);
+
+        let read_back: Exercise = serde_json::from_value(value).unwrap();
+        assert_eq!(read_back.sets.len(), 3);
+        assert_eq!(read_back.volume(), exercise.volume());
+    }
+
+    #[test]
+    fn sets_with_negative_amounts_or_rpe_off_the_scale_are_invalid() {
+        let valid = ExerciseSet {
+            reps: 5,
+            weight: 100.0,
+            rpe: Some(10.0),
+            rest: Some(0),
+            ..ExerciseSet::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = [
+            ExerciseSet {
+                reps: -1,
+                ..valid.clone()
+            },
+            ExerciseSet {
+                weight: -0.5,
+                ..valid.clone()
+            },
+            ExerciseSet {
+                weight: f32::INFINITY,
+                ..valid.clone()
+            },
+            ExerciseSet {
+                distance: -1.0,
+                ..valid.clone()
+            },
+            ExerciseSet {
+                duration: -30,
+                ..valid.clone()
+            },
+            ExerciseSet {
+                rest: Some(-60),
+                ..valid.clone()
+            },
+            ExerciseSet {
+                rpe: Some(0.0),
+                ..valid.clone()
+            },
+            ExerciseSet {
+                rpe: Some(10.5),
+                ..valid.clone()
+            },
+        ];
+        for set in invalid {
+            assert!(set.validate().is_err(), "{:?}", set);
+        }
+
+        let exercise = exercise(json!({"id": "Barbell_Squat", "sets": [{"reps": -5}]}));
+        assert_eq!(
+            exercise.validate(),
+            Err(String::from(
+                "Barbell_Squat: Invalid reps, it can't be negative"
+            ))
+        );
+    }
+}
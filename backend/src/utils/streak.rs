@@ -0,0 +1,139 @@
+use {
+    crate::utils::schedule::is_scheduled,
+    chrono::{
+        Duration,
+        NaiveDate,
+    },
+    sqlx::PgConnection,
+    std::collections::HashSet,
+};
+
+// Consecutive scheduled days with a workout, counting back from today.
+// Days off the schedule never break the streak and today only breaks it once it is over.
+// Without any scheduled day every day counts.
+pub fn streak(
+    today: NaiveDate,
+    workout_schedule: &[bool],
+    workout_days: &HashSet<NaiveDate>,
+) -> i32 {
+    let Some(&first) = workout_days.iter().min() else {
+        return 0;
+    };
+
+    let rest_week = !workout_schedule.contains(&true);
+
+    let mut streak = 0;
+    let mut day = today;
+    while day >= first {
+        if workout_days.contains(&day) {
+            streak += 1;
+        } else if (rest_week || is_scheduled(workout_schedule, day)) && day != today {
+            break;
+        }
+        day -= Duration::days(1);
+    }
+
+    return streak;
+}
+
+// Recomputes the streak from the whole history, so edited and deleted workouts are accounted for
+pub async fn update_streak(conn: &mut PgConnection, user_id: i32) -> Result<i32, sqlx::Error> {
+    let settings = sqlx::query!(
+        r#"
+            SELECT (NOW() AT TIME ZONE timezone)::date as "today!", workout_schedule
+            FROM settings
+            WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let workout_days: HashSet<NaiveDate> = sqlx::query_scalar!(
+        r#"
+            SELECT DISTINCT date
+            FROM history
+            WHERE user_id = $1 AND date <= $2
+        "#,
+        user_id,
+        settings.today
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .collect();
+
+    let streak = streak(settings.today, &settings.workout_schedule, &workout_days);
+
+    sqlx::query!(
+        r#"
+            UPDATE characters
+            SET streak = $2
+            WHERE user_id = $1
+        "#,
+        user_id,
+        streak
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    return Ok(streak);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monday, Wednesday and Friday
+    const SCHEDULE: [bool; 7] = [false, true, false, true, false, true, false];
+
+    // 2026-10-19 is a Monday
+    const TODAY: u32 = 19;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn days(days: &[u32]) -> HashSet<NaiveDate> {
+        days.iter().map(|&d| day(d)).collect()
+    }
+
+    #[test]
+    fn no_workouts_is_no_streak() {
+        assert_eq!(streak(day(TODAY), &SCHEDULE, &HashSet::new()), 0);
+    }
+
+    #[test]
+    fn counts_scheduled_days_with_a_workout() {
+        assert_eq!(streak(day(TODAY), &SCHEDULE, &days(&[12, 14, 16, 19])), 4);
+    }
+
+    #[test]
+    fn today_only_breaks_the_streak_once_it_is_over() {
+        assert_eq!(streak(day(TODAY), &SCHEDULE, &days(&[12, 14, 16])), 3);
+    }
+
+    #[test]
+    fn days_off_the_schedule_never_break_it_but_count_when_trained() {
+        assert_eq!(streak(day(TODAY), &SCHEDULE, &days(&[14, 16, 17])), 3);
+    }
+
+    #[test]
+    fn missed_scheduled_day_breaks_it() {
+        assert_eq!(streak(day(TODAY), &SCHEDULE, &days(&[12, 16])), 1);
+        assert_eq!(streak(day(TODAY), &SCHEDULE, &days(&[12, 14])), 0);
+    }
+
+    #[test]
+    fn without_scheduled_days_every_day_counts() {
+        let schedule = [false; 7];
+        assert_eq!(streak(day(TODAY), &schedule, &days(&[17, 18, 19])), 3);
+        assert_eq!(streak(day(TODAY), &schedule, &days(&[17, 18])), 2);
+        assert_eq!(streak(day(TODAY), &schedule, &days(&[16, 18, 19])), 2);
+    }
+
+    #[test]
+    fn workouts_after_today_are_not_counted() {
+        assert_eq!(streak(day(16), &SCHEDULE, &days(&[14, 16, 19])), 2);
+    }
+}
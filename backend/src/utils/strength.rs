@@ -0,0 +1,207 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+// Estimates drift too far from a real max past this many reps, such sets are not estimated
+pub const MAX_ESTIMATED_REPS: i32 = 12;
+
+// Formulas estimating the one-rep max (e1RM) of a set from its weight and reps
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OneRepMaxFormula {
+    #[default]
+    Epley,
+    Brzycki,
+    Lombardi,
+}
+
+impl OneRepMaxFormula {
+    // None for sets without weight or with too many reps
+    pub fn estimate(self, weight: f32, reps: i32) -> Option<f32> {
+        if weight <= 0.0 || reps <= 0 || reps > MAX_ESTIMATED_REPS {
+            return None;
+        }
+        if reps == 1 {
+            return Some(weight);
+        }
+
+        let reps = reps as f32;
+        let estimate = match self {
+            OneRepMaxFormula::Epley => weight * (1.0 + reps / 30.0),
+            OneRepMaxFormula::Brzycki => weight * 36.0 / (37.0 - reps),
+            OneRepMaxFormula::Lombardi => weight * reps.powf(0.1),
+        };
+        return Some(estimate);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrengthLevel {
+    Beginner, // below the novice threshold
+    Novice,
+    Intermediate,
+    Advanced,
+}
+
+// e1RM over bodyweight reached at each level
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct StrengthThresholds {
+    pub novice: f32,
+    pub intermediate: f32,
+    pub advanced: f32,
+}
+
+impl StrengthThresholds {
+    pub fn level(&self, ratio: f32) -> StrengthLevel {
+        if ratio >= self.advanced {
+            return StrengthLevel::Advanced;
+        }
+        if ratio >= self.intermediate {
+            return StrengthLevel::Intermediate;
+        }
+        if ratio >= self.novice {
+            return StrengthLevel::Novice;
+        }
+        return StrengthLevel::Beginner;
+    }
+
+    // Ratio of the level after this one, None once advanced
+    pub fn next(&self, ratio: f32) -> Option<f32> {
+        [self.novice, self.intermediate, self.advanced]
+            .into_iter()
+            .find(|&threshold| ratio < threshold)
+    }
+}
+
+const fn thresholds(novice: f32, intermediate: f32, advanced: f32) -> StrengthThresholds {
+    StrengthThresholds {
+        novice,
+        intermediate,
+        advanced,
+    }
+}
+
+// Lifts of the catalog with standards, the ratios are the usual ones of strength training programs
+pub const STRENGTH_STANDARDS: &[(&str, StrengthThresholds)] = &[
+    ("Barbell_Squat", thresholds(1.0, 1.5, 2.0)),
+    ("Barbell_Bench_Press_-_Medium_Grip", thresholds(0.75, 1.0, 1.5)),
+    ("Barbell_Deadlift", thresholds(1.25, 1.75, 2.5)),
+    ("Standing_Military_Press", thresholds(0.5, 0.75, 1.0)),
+    ("Barbell_Shoulder_Press", thresholds(0.5, 0.75, 1.0)),
+    ("Bent_Over_Barbell_Row", thresholds(0.6, 0.9, 1.25)),
+    ("Power_Clean", thresholds(0.6, 0.9, 1.25)),
+];
+
+pub fn strength_thresholds(exercise_id: &str) -> Option<StrengthThresholds> {
+    STRENGTH_STANDARDS
+        .iter()
+        .find(|(id, _)| *id == exercise_id)
+        .map(|&(_, thresholds)| thresholds)
+}
+
+// How the best e1RM of a lift compares to its standards
+#[derive(Debug, Serialize)]
+pub struct StrengthStandard {
+    pub thresholds: StrengthThresholds,
+    pub e1rm: Option<f32>,       // None until the lift is logged
+    pub bodyweight: Option<f32>, // None until the user enters it
+    pub ratio: Option<f32>,      // e1RM over bodyweight
+    pub level: Option<StrengthLevel>,
+    pub next_level_e1rm: Option<f32>, // to reach the next level
+}
+
+impl StrengthStandard {
+    pub fn new(
+        thresholds: StrengthThresholds,
+        e1rm: Option<f32>,
+        bodyweight: Option<f32>,
+    ) -> StrengthStandard {
+        let ratio = e1rm
+            .zip(bodyweight)
+            .map(|(e1rm, bodyweight)| e1rm / bodyweight);
+        return StrengthStandard {
+            thresholds,
+            e1rm,
+            bodyweight,
+            ratio,
+            level: ratio.map(|ratio| thresholds.level(ratio)),
+            next_level_e1rm: ratio
+                .zip(bodyweight)
+                .and_then(|(ratio, bodyweight)| Some(thresholds.next(ratio)? * bodyweight)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BENCH: StrengthThresholds = thresholds(0.75, 1.0, 1.5);
+
+    fn estimate(formula: OneRepMaxFormula, weight: f32, reps: i32) -> f32 {
+        formula.estimate(weight, reps).unwrap()
+    }
+
+    #[test]
+    fn formulas_estimate_known_maxes() {
+        assert_eq!(estimate(OneRepMaxFormula::Epley, 225.0, 5), 262.5);
+        assert_eq!(estimate(OneRepMaxFormula::Epley, 100.0, 10), 100.0 * (1.0 + 10.0 / 30.0));
+        assert_eq!(estimate(OneRepMaxFormula::Brzycki, 225.0, 5), 253.125);
+        assert_eq!(estimate(OneRepMaxFormula::Brzycki, 200.0, 10), 200.0 * 36.0 / 27.0);
+        assert!((estimate(OneRepMaxFormula::Lombardi, 100.0, 10) - 125.89254).abs() < 0.001);
+    }
+
+    #[test]
+    fn singles_are_their_own_max() {
+        for formula in [
+            OneRepMaxFormula::Epley,
+            OneRepMaxFormula::Brzycki,
+            OneRepMaxFormula::Lombardi,
+        ] {
+            assert_eq!(formula.estimate(315.0, 1), Some(315.0));
+        }
+    }
+
+    #[test]
+    fn sets_without_a_usable_estimate_are_skipped() {
+        let formula = OneRepMaxFormula::default();
+        assert_eq!(formula.estimate(0.0, 5), None);
+        assert_eq!(formula.estimate(-10.0, 5), None);
+        assert_eq!(formula.estimate(100.0, 0), None);
+        assert_eq!(formula.estimate(100.0, MAX_ESTIMATED_REPS + 1), None);
+        assert!(formula.estimate(100.0, MAX_ESTIMATED_REPS).is_some());
+    }
+
+    #[test]
+    fn levels_start_at_their_threshold() {
+        assert_eq!(BENCH.level(0.5), StrengthLevel::Beginner);
+        assert_eq!(BENCH.level(0.75), StrengthLevel::Novice);
+        assert_eq!(BENCH.level(0.99), StrengthLevel::Novice);
+        assert_eq!(BENCH.level(1.0), StrengthLevel::Intermediate);
+        assert_eq!(BENCH.level(1.5), StrengthLevel::Advanced);
+        assert_eq!(BENCH.level(3.0), StrengthLevel::Advanced);
+    }
+
+    #[test]
+    fn next_is_the_threshold_of_the_next_level() {
+        assert_eq!(BENCH.next(0.5), Some(0.75));
+        assert_eq!(BENCH.next(0.75), Some(1.0));
+        assert_eq!(BENCH.next(1.2), Some(1.5));
+        assert_eq!(BENCH.next(1.5), None);
+    }
+
+    #[test]
+    fn standards_compare_the_max_to_the_bodyweight() {
+        let standard = StrengthStandard::new(BENCH, Some(180.0), Some(200.0));
+        assert_eq!(standard.ratio, Some(0.9));
+        assert_eq!(standard.level, Some(StrengthLevel::Novice));
+        assert_eq!(standard.next_level_e1rm, Some(200.0));
+
+        let standard = StrengthStandard::new(BENCH, Some(180.0), None);
+        assert_eq!(standard.ratio, None);
+        assert_eq!(standard.level, None);
+        assert_eq!(standard.next_level_e1rm, None);
+    }
+}